@@ -0,0 +1,62 @@
+//! Frame times of the compute rasteriser against the hardware `PointcloudPipeline`.
+//! Run with `cargo bench --bench raster`, the point count can be set with
+//! `RASTER_BENCH_POINTS`.
+
+use std::time::{Duration, Instant};
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::{
+    engine::{headless_device, Camera, Offscreen, Projection, RasterBackend, Renderer},
+    pointcloud::Pointcloud,
+    points::{make_random_intensities, make_random_points},
+};
+
+const SIZE: u32 = 1024;
+const WARMUP_FRAMES: u32 = 5;
+const FRAMES: u32 = 50;
+
+fn bench(backend: RasterBackend, points: usize) -> Option<Duration> {
+    let (device, queue) = pollster::block_on(headless_device())?;
+    let camera = Camera {
+        eye: Vec3::new(1.2, -1.2, 1.),
+        target: Vec3::ZERO,
+        up: Vec3::Z,
+        projection: Projection::perspective(0.01, 1000., SIZE as f32, SIZE as f32, 60f32.to_radians()),
+    };
+
+    let pointcloud = Pointcloud::from_points(&device, &queue, make_random_points(points), make_random_intensities(points));
+    let target = Offscreen::new(&device, SIZE, SIZE);
+    let mut renderer = Renderer::with_backend(device, queue, Offscreen::FORMAT, &camera, SIZE, SIZE, backend);
+    renderer.set_pointcloud(pointcloud);
+    renderer.point_size_mut().pixels = 2.;
+    renderer.update(&camera);
+
+    let frame = || {
+        renderer.render(target.view());
+        renderer.device().poll(wgpu::PollType::Wait).unwrap();
+    };
+    for _ in 0..WARMUP_FRAMES {
+        frame();
+    }
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+
+    Some(start.elapsed() / FRAMES)
+}
+
+fn main() {
+    let points = std::env::var("RASTER_BENCH_POINTS")
+        .ok()
+        .and_then(|points| points.parse().ok())
+        .unwrap_or(1_000_000);
+
+    println!("{points} points, {SIZE}x{SIZE}, {FRAMES} frames");
+    for backend in [RasterBackend::Pipeline, RasterBackend::Compute] {
+        match bench(backend, points) {
+            Some(frame_time) => println!("{backend:?}: {:.2} ms/frame", frame_time.as_secs_f64() * 1000.),
+            None => println!("{backend:?}: no adapter available"),
+        }
+    }
+}
@@ -1,6 +1,14 @@
 use naga::front::wgsl;
 use std::fs;
 
+// Shaders and what is prepended to them, as `common_shader!` does
+const PRELUDES: [(&str, &[&str]); 4] = [
+    ("intensity.wgsl", &["common.wgsl"]),
+    ("raster_common.wgsl", &["common.wgsl"]),
+    ("raster.wgsl", &["common.wgsl", "raster_common.wgsl"]),
+    ("raster64.wgsl", &["common.wgsl", "raster_common.wgsl"]),
+];
+
 fn main() {
     println!("cargo::rerun-if-changed=src/shaders");
     fs::read_dir("src/shaders/").expect("src/shaders/ folder required")
@@ -11,6 +19,10 @@ fn main() {
             )
         )
         .for_each(|(path, content)| {        
+            let preludes = PRELUDES.iter().find(|(name, _)| path.ends_with(&format!("/{name}"))).map_or(&[][..], |(_, preludes)| preludes);
+            let content = preludes.iter()
+                .map(|prelude| fs::read_to_string(format!("src/shaders/{prelude}")).unwrap())
+                .collect::<String>() + &content;
             if let Err(e) = wgsl::parse_str(&content) {
                 panic!("Error @{}: {}",  path, e);
             }
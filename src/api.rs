@@ -0,0 +1,398 @@
+//! The viewer as JavaScript sees it. The engine lives in the event loop, so
+//! `Viewer` methods queue commands that it applies on its next update, and it
+//! publishes what they read back.
+
+use std::{cell::{Cell, RefCell}, rc::Rc};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bookmarks::{Bookmarks, CameraView},
+    controls::Bindings,
+    engine::{ColorMode, FilterSettings, PickedPoint, PointSizeMode, ViewPreset},
+    flythrough::CameraPath,
+};
+
+pub(crate) enum Command {
+    Load(String),
+    SetFilter(FilterSettings),
+    SetColorMode(ColorMode),
+    SetPointSize(f32),
+    SetPointSizeMode(PointSizeMode),
+    SetOrthographic(bool),
+    SetView(ViewPreset),
+    SetCamera(CameraView, f32),
+    FitToExtent,
+    Pick(f64, f64),
+    SetBindings(Bindings),
+    SaveBookmark(String),
+    FlyToBookmark(String),
+    RemoveBookmark(String),
+    SetBookmarks(Bookmarks),
+    AddKeyframe,
+    RemoveKeyframe,
+    SetPath(CameraPath),
+    PlayPath,
+    StopPath,
+    SetPathSpeed(f32),
+}
+
+/// What the engine tells the page, through the callbacks given to `Viewer.on`
+pub(crate) enum Event<'a> {
+    Progress(f32), // fraction of the pointcloud downloaded
+    Load { url: &'a str, points: usize },
+    Select(Option<&'a PickedPoint>),
+    Error(&'a str),
+}
+
+impl Event<'_> {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Event::Progress(_) => "progress",
+            Event::Load { .. } => "load",
+            Event::Select(_) => "select",
+            Event::Error(_) => "error",
+        }
+    }
+
+    /// Argument of the callbacks
+    pub(crate) fn to_json(&self) -> String {
+        match self {
+            Event::Progress(fraction) => fraction.to_string(),
+            Event::Load { url, points } => serde_json::json!({ "url": url, "points": points }).to_string(),
+            Event::Select(picked) => serde_json::to_string(picked).unwrap_or_else(|_| "null".to_owned()),
+            Event::Error(message) => serde_json::Value::from(*message).to_string(),
+        }
+    }
+}
+
+/// What passes between a `Viewer` and its engine, each viewer on a page
+/// having its own
+#[derive(Default)]
+pub(crate) struct Channel {
+    commands: RefCell<Vec<Command>>,
+    bookmarks: RefCell<String>,
+    path: RefCell<String>,
+    camera: Cell<Option<CameraView>>,
+    closed: Cell<bool>, // by `Viewer.destroy`, the engine is dropped
+    #[cfg(target_arch = "wasm32")]
+    callbacks: RefCell<Vec<(String, js_sys::Function)>>,
+}
+
+impl Channel {
+    /// Commands queued since the last call
+    pub(crate) fn take_commands(&self) -> Vec<Command> {
+        self.commands.take()
+    }
+
+    pub(crate) fn push(&self, command: Command) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    /// Keeps the engine's bookmarks, as JSON, for `getBookmarks`
+    pub(crate) fn publish_bookmarks(&self, json: String) {
+        self.bookmarks.replace(json);
+    }
+
+    /// Keeps the engine's camera path, as JSON, for `getPath`
+    pub(crate) fn publish_path(&self, json: String) {
+        self.path.replace(json);
+    }
+
+    /// Keeps where the camera is, for `getCamera`
+    pub(crate) fn publish_camera(&self, view: CameraView) {
+        self.camera.set(Some(view));
+    }
+
+    /// Stops the engine on the event loop's next event, and the callbacks now
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn close(&self) {
+        self.closed.set(true);
+        self.callbacks.borrow_mut().clear();
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Calls the page's callbacks for `event`, natively there are none so it is logged
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn emit(&self, event: Event) {
+        log::debug!("{}: {}", event.name(), event.to_json());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn emit(&self, event: Event) {
+        let callbacks: Vec<_> = self.callbacks.borrow().iter()
+            .filter(|(name, _)| name == event.name())
+            .map(|(_, callback)| callback.clone())
+            .collect();
+        if callbacks.is_empty() {
+            return;
+        }
+        let argument = js_sys::JSON::parse(&event.to_json()).unwrap_or(JsValue::NULL);
+        for callback in callbacks {
+            if let Err(e) = callback.call1(&JsValue::NULL, &argument) {
+                log::error!("The {} callback failed: {e:?}", event.name());
+            }
+        }
+    }
+}
+
+/// Handle on a viewer, made from JavaScript with `new Viewer(element, url)`.
+/// Natively `run` opens the window, and a default one queues commands that
+/// nothing applies.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Default)]
+pub struct Viewer {
+    channel: Rc<Channel>,
+}
+
+impl Viewer {
+    fn push(&self, command: Command) {
+        self.channel.push(command);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Viewer {
+    /// Replaces the pointcloud with the LAS file at `url` once downloaded,
+    /// reporting `progress` meanwhile and `load` or `error` at the end
+    pub fn load(&self, url: &str) {
+        self.push(Command::Load(url.to_owned()));
+    }
+
+    /// Replaces the attribute filters with JSON such as
+    /// `{"classifications": [2, 6], "returns": "last", "elevation": [100, 250]}`,
+    /// missing fields let every point through
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setFilter))]
+    pub fn set_filter(&self, json: &str) -> Result<(), String> {
+        let filter = serde_json::from_str(json).map_err(|e| format!("Invalid filter: {e}"))?;
+        self.push(Command::SetFilter(filter));
+        Ok(())
+    }
+
+    /// Shows every point again
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = clearFilter))]
+    pub fn clear_filter(&self) {
+        self.push(Command::SetFilter(FilterSettings::default()));
+    }
+
+    /// Colours by `"intensity"` or `"elevation"`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setColorMode))]
+    pub fn set_color_mode(&self, name: &str) -> Result<(), String> {
+        self.push(Command::SetColorMode(name.parse()?));
+        Ok(())
+    }
+
+    /// Sets the size of the current point size mode: pixels when fixed, scene
+    /// units when attenuated, multiples of the point spacing when adaptive
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setPointSize))]
+    pub fn set_point_size(&self, size: f32) -> Result<(), String> {
+        if !size.is_finite() || size <= 0. {
+            return Err(format!("Invalid point size: {size}"));
+        }
+        self.push(Command::SetPointSize(size));
+        Ok(())
+    }
+
+    /// Sizes points in `"fixed"` pixels, `"attenuated"` with distance or
+    /// `"adaptive"` to the point spacing
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setPointSizeMode))]
+    pub fn set_point_size_mode(&self, name: &str) -> Result<(), String> {
+        self.push(Command::SetPointSizeMode(name.parse()?));
+        Ok(())
+    }
+
+    /// Switches between orthographic and perspective projection, keeping the
+    /// scale at the camera target
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setOrthographic))]
+    pub fn set_orthographic(&self, orthographic: bool) {
+        self.push(Command::SetOrthographic(orthographic));
+    }
+
+    /// Looks from `"top"`, `"bottom"`, `"front"`, `"back"`, `"left"`, `"right"`
+    /// or `"isometric"`, front looking north
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setView))]
+    pub fn set_view(&self, name: &str) -> Result<(), String> {
+        self.push(Command::SetView(name.parse()?));
+        Ok(())
+    }
+
+    /// Where the camera is, as JSON `{"target", "theta", "phi", "farness",
+    /// "orthographic"}` in scene coordinates, as of the last update
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = getCamera))]
+    pub fn get_camera(&self) -> String {
+        self.channel.camera.get().and_then(|view| serde_json::to_string(&view).ok()).unwrap_or_else(|| "null".to_owned())
+    }
+
+    /// Moves the camera to JSON as `getCamera` gives, flying there over
+    /// `seconds` if given
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setCamera))]
+    pub fn set_camera(&self, json: &str, seconds: Option<f32>) -> Result<(), String> {
+        let view = serde_json::from_str(json).map_err(|e| format!("Invalid camera: {e}"))?;
+        self.push(Command::SetCamera(view, seconds.unwrap_or_default().max(0.)));
+        Ok(())
+    }
+
+    /// Brings the whole pointcloud into view
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = fitToExtent))]
+    pub fn fit_to_extent(&self) {
+        self.push(Command::FitToExtent);
+    }
+
+    /// Picks the point at `x`, `y` in CSS pixels from the top left of the
+    /// canvas, reported to the `select` callbacks as clicks are
+    pub fn pick(&self, x: f64, y: f64) {
+        self.push(Command::Pick(x, y));
+    }
+
+    /// Remaps controls with JSON such as `{"move_forward": ["KeyZ", "ArrowUp"],
+    /// "view_back": ["Ctrl+Numpad1"]}`, actions left out keep their defaults.
+    /// Fails on bindings that conflict.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setBindings))]
+    pub fn set_bindings(&self, json: &str) -> Result<(), String> {
+        self.push(Command::SetBindings(Bindings::from_json(json).map_err(|e| e.to_string())?));
+        Ok(())
+    }
+
+    /// Puts back the default controls
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = resetBindings))]
+    pub fn reset_bindings(&self) {
+        self.push(Command::SetBindings(Bindings::default()));
+    }
+
+    /// Bookmarks the current view as `name`, replacing any called that
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = saveBookmark))]
+    pub fn save_bookmark(&self, name: &str) {
+        self.push(Command::SaveBookmark(name.to_owned()));
+    }
+
+    /// Flies to the view bookmarked as `name`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = flyToBookmark))]
+    pub fn fly_to_bookmark(&self, name: &str) {
+        self.push(Command::FlyToBookmark(name.to_owned()));
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = removeBookmark))]
+    pub fn remove_bookmark(&self, name: &str) {
+        self.push(Command::RemoveBookmark(name.to_owned()));
+    }
+
+    /// Bookmarks as a JSON array of `{"name", "target", "theta", "phi",
+    /// "farness", "orthographic"}`, as of the last update
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = getBookmarks))]
+    pub fn get_bookmarks(&self) -> String {
+        published(&self.channel.bookmarks)
+    }
+
+    /// Replaces the bookmarks with JSON as `getBookmarks` gives
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setBookmarks))]
+    pub fn set_bookmarks(&self, json: &str) -> Result<(), String> {
+        let bookmarks = Bookmarks::from_json(json).map_err(|e| format!("Invalid bookmarks: {e}"))?;
+        self.push(Command::SetBookmarks(bookmarks));
+        Ok(())
+    }
+
+    /// Adds the current camera pose to the end of the flythrough path
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = addKeyframe))]
+    pub fn add_keyframe(&self) {
+        self.push(Command::AddKeyframe);
+    }
+
+    /// Removes the last keyframe of the flythrough path
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = removeKeyframe))]
+    pub fn remove_keyframe(&self) {
+        self.push(Command::RemoveKeyframe);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = clearPath))]
+    pub fn clear_path(&self) {
+        self.push(Command::SetPath(CameraPath::default()));
+    }
+
+    /// Keyframes as a JSON array of `{"eye", "rotation", "focus"}`, rotation
+    /// being a quaternion, as of the last update
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = getPath))]
+    pub fn get_path(&self) -> String {
+        published(&self.channel.path)
+    }
+
+    /// Replaces the flythrough path with JSON as `getPath` gives
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setPath))]
+    pub fn set_path(&self, json: &str) -> Result<(), String> {
+        let path = CameraPath::from_json(json).map_err(|e| format!("Invalid path: {e}"))?;
+        self.push(Command::SetPath(path));
+        Ok(())
+    }
+
+    /// Flies the camera along the path from its first keyframe
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = playPath))]
+    pub fn play_path(&self) {
+        self.push(Command::PlayPath);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = stopPath))]
+    pub fn stop_path(&self) {
+        self.push(Command::StopPath);
+    }
+
+    /// Sets how fast the path plays, in meters per second
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = setPathSpeed))]
+    pub fn set_path_speed(&self, speed: f32) -> Result<(), String> {
+        if !speed.is_finite() || speed <= 0. {
+            return Err(format!("Invalid speed: {speed}"));
+        }
+        self.push(Command::SetPathSpeed(speed));
+        Ok(())
+    }
+}
+
+/// JSON the engine published, an empty array until it has
+fn published(json: &RefCell<String>) -> String {
+    let json = json.borrow();
+    if json.is_empty() { "[]".to_owned() } else { json.clone() }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Viewer {
+    /// Opens a viewer on `target`, a canvas or an element to fill with one, or
+    /// at the end of the page if left out. It follows the size the page gives
+    /// it and the screen's pixel ratio. `url` is the LAS file to open, unless
+    /// this is the first viewer on the page and its URL links to another.
+    #[wasm_bindgen(constructor)]
+    pub fn new(target: Option<web_sys::Element>, url: Option<String>) -> Result<Viewer, String> {
+        let viewer = Viewer::default();
+        crate::open(crate::app::ViewerOptions {
+            channel: viewer.channel.clone(),
+            dataset: url,
+            canvas: crate::platform::viewer_canvas(target)?,
+        })?;
+        Ok(viewer)
+    }
+
+    /// Calls `callback` on `"progress"` with the fraction downloaded, `"load"`
+    /// with `{url, points}`, `"select"` with the picked point or `null`, and
+    /// `"error"` with a message
+    pub fn on(&self, event: &str, callback: js_sys::Function) -> Result<(), String> {
+        if !["progress", "load", "select", "error"].contains(&event) {
+            return Err(format!("Unknown event {event:?}"));
+        }
+        self.channel.callbacks.borrow_mut().push((event.to_owned(), callback));
+        Ok(())
+    }
+
+    /// Stops calling `callback` on `event`
+    pub fn off(&self, event: &str, callback: &js_sys::Function) {
+        self.channel.callbacks.borrow_mut().retain(|(name, other)| name != event || other != callback);
+    }
+
+    /// Stops the viewer and takes its overlay and labels off the page, the
+    /// canvas is left as it is. Nothing it is asked after does anything.
+    pub fn destroy(&self) {
+        self.channel.close();
+        crate::wake();
+    }
+}
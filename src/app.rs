@@ -1,75 +1,165 @@
-use std::sync::Arc;
+use std::{collections::HashMap, rc::Rc, sync::Arc};
 use winit::{
     application::ApplicationHandler, event::WindowEvent, event_loop::ActiveEventLoop, window::{Window, WindowId}
 };
 #[cfg(not(target_family = "wasm"))]
 use pollster::FutureExt;
 #[cfg(target_family = "wasm")]
-use winit::event_loop::EventLoop;
+use winit::event_loop::EventLoopProxy;
 
-use crate::engine::Engine;
+use crate::{api::Channel, engine::{Engine, RasterBackend}};
 
-#[allow(unused)]
-const WIDTH: u32 = 500;
-#[allow(unused)]
-const HEIGHT: u32 = 500;
+/// A viewer to open: what its `Viewer` talks through, the LAS file to show
+/// and, on the web, the canvas to draw on
+pub(crate) struct ViewerOptions {
+    pub channel: Rc<Channel>,
+    pub dataset: Option<String>,
+    #[cfg(target_family = "wasm")]
+    pub canvas: web_sys::HtmlCanvasElement,
+}
+
+/// Sent to the event loop every viewer on a page shares
+#[cfg(target_family = "wasm")]
+pub(crate) enum UserEvent {
+    Open(ViewerOptions),
+    /// The engine of a window, once its GPU device is ready
+    Opened(Box<Engine>),
+    /// A viewer was destroyed, its channel says which
+    Closed,
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) type UserEvent = ();
 
 pub struct App {
-    state: Option<Engine>,
+    engines: HashMap<WindowId, Engine>,
+    backend: RasterBackend,
+    opened: usize, // windows made so far, the first follows the page URL
+    focused: Option<WindowId>, // last focused or used, gamepads drive it
+    #[cfg(not(target_family = "wasm"))]
+    pending: Option<ViewerOptions>, // opened once the event loop resumes
+    #[cfg(target_family = "wasm")]
+    proxy: EventLoopProxy<UserEvent>,
 }
 
 impl App {
-    pub const fn new() -> Self {
-        Self { 
-            state: None,
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn new(backend: RasterBackend, options: ViewerOptions) -> Self {
+        Self {
+            engines: HashMap::new(),
+            backend,
+            opened: 0,
+            focused: None,
+            pending: Some(options),
         }
     }
-}
 
-impl App {
-    pub async fn make_state(
-        &mut self,
-        #[cfg(target_family = "wasm")]
-        event_loop: &EventLoop<()>,
-        #[cfg(not(target_family = "wasm"))]
-        event_loop: &ActiveEventLoop,
-    ) {
-        let attributes = Window::default_attributes()
+    #[cfg(target_family = "wasm")]
+    pub(crate) fn new(backend: RasterBackend, proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self {
+            engines: HashMap::new(),
+            backend,
+            opened: 0,
+            focused: None,
+            proxy,
+        }
+    }
+
+    /// Makes the window of a viewer, its engine is set up in the background
+    /// on the web
+    fn open(&mut self, event_loop: &ActiveEventLoop, options: ViewerOptions) {
+        #[allow(unused_mut)]
+        let mut attributes = Window::default_attributes()
             .with_title("Pointcloud Viewer");
 
-        #[allow(deprecated)]
-        let window = event_loop
-            .create_window(attributes)
-            .expect("Couldn't create window");
-
-        #[cfg(target_arch = "wasm32")] {
-            use winit::dpi::PhysicalSize;
-            let _ = window.request_inner_size(PhysicalSize::new(WIDTH, HEIGHT));
-
-            use winit::platform::web::WindowExtWebSys;
-            web_sys::window()
-                .and_then(|win| win.document())
-                .and_then(|doc| {
-                    let dst = doc.get_element_by_id("body")?;
-                    let canvas = web_sys::HtmlCanvasElement::from(window.canvas()?);
-                    canvas.set_height(HEIGHT);
-                    canvas.set_width(WIDTH);
-                    dst.append_child(&canvas).ok()?;
-                    Some(())
-                })
-                .expect("Couldn't append canvas to document body.");
+        // No size is asked for, so the canvas keeps the size the page lays it
+        // out at, in device pixels
+        #[cfg(target_family = "wasm")] {
+            use winit::platform::web::WindowAttributesExtWebSys;
+            attributes = attributes.with_canvas(Some(options.canvas));
         }
 
-        self.state = Some((Engine::new(Arc::new(window))).await);
+        let window = Arc::new(event_loop.create_window(attributes).expect("Couldn't create window"));
+        let follow_url = self.opened == 0;
+        self.opened += 1;
+        let engine = Engine::new(window, self.backend, options.channel, options.dataset, follow_url);
+
+        #[cfg(not(target_family = "wasm"))]
+        self.add(engine.block_on());
+
+        #[cfg(target_family = "wasm")] {
+            let proxy = self.proxy.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = proxy.send_event(UserEvent::Opened(Box::new(engine.await)));
+            });
+        }
+    }
+
+    fn add(&mut self, mut engine: Engine) {
+        // Destroyed while its GPU device was set up
+        if engine.channel().is_closed() {
+            return;
+        }
+        // The window may have been resized while the engine was set up
+        engine.resize(engine.window().inner_size());
+        engine.window().request_redraw();
+        let id = engine.window().id();
+        self.engines.insert(id, engine);
+        if self.focused.is_none() {
+            self.focus(id);
+        }
+    }
+
+    /// Drops the engine of `window_id` and what it added to the page
+    fn remove(&mut self, window_id: WindowId) {
+        self.engines.remove(&window_id);
+        crate::platform::remove_layer(window_id);
+        if self.focused == Some(window_id) {
+            self.focused = None;
+            if let Some(&other) = self.engines.keys().next() {
+                self.focus(other);
+            }
+        }
+    }
+
+    /// Hands the gamepads to the viewer of `window_id`
+    fn focus(&mut self, window_id: WindowId) {
+        if self.focused == Some(window_id) {
+            return;
+        }
+        if let Some(engine) = self.focused.and_then(|id| self.engines.get_mut(&id)) {
+            engine.set_gamepad_focus(false);
+        }
+        if let Some(engine) = self.engines.get_mut(&window_id) {
+            engine.set_gamepad_focus(true);
+            self.focused = Some(window_id);
+        }
     }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<UserEvent> for App {
     #[allow(unused_variables)] // For wasm
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         #[cfg(not(target_family = "wasm"))]
-        if self.state.is_none() { // Assume there is no window if we haven't made a State already
-            self.make_state(event_loop).block_on();
+        if let Some(options) = self.pending.take() {
+            self.open(event_loop, options);
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+        match event {
+            UserEvent::Open(options) => self.open(event_loop, options),
+            UserEvent::Opened(engine) => self.add(*engine),
+            UserEvent::Closed => {
+                let closed: Vec<_> = self.engines.iter()
+                    .filter(|(_, engine)| engine.channel().is_closed())
+                    .map(|(window_id, _)| *window_id)
+                    .collect();
+                for window_id in closed {
+                    self.remove(window_id);
+                }
+            }
         }
     }
 
@@ -79,21 +169,37 @@ impl ApplicationHandler for App {
         window_id: WindowId,
         event: WindowEvent,
     ) {
-        let state = self.state.as_mut().unwrap();
+        let used = matches!(event,
+            WindowEvent::Focused(true) | WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. }
+            | WindowEvent::KeyboardInput { .. } | WindowEvent::Touch(_)
+        );
+        if used {
+            self.focus(window_id);
+        }
+        let Some(state) = self.engines.get_mut(&window_id) else {
+            return;
+        };
 
         state.window().request_redraw();
 
-        if state.window().id() == window_id && !state.input(&event) {
+        if !state.input(&event) {
             match event {
                 WindowEvent::CloseRequested => {
-                    event_loop.exit();
+                    self.remove(window_id);
+                    if self.engines.is_empty() {
+                        event_loop.exit();
+                    }
                 }
                 WindowEvent::Resized(physical_size) => {
                     state.resize(physical_size);
                 }
                 WindowEvent::RedrawRequested => {
                     state.update();
-                    state.render().expect("Render ERROR!");
+                    // Not laid out yet, the canvas has no pixels to draw
+                    let size = state.window().inner_size();
+                    if size.width > 0 && size.height > 0 {
+                        state.render().expect("Render ERROR!");
+                    }
                 },
                 _ => {}
             }
@@ -106,8 +212,10 @@ impl ApplicationHandler for App {
             _device_id: winit::event::DeviceId,
             event: winit::event::DeviceEvent,
         ) {
-        let state = self.state.as_mut().unwrap();
-        state.device_input(&event);
+        // Each engine only follows the mouse while it is dragged on its window
+        for state in self.engines.values_mut() {
+            state.device_input(&event);
+        }
     }
 
 }
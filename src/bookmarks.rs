@@ -0,0 +1,116 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{Camera, Projection};
+
+/// Where an orbiting camera is, in scene coordinates and the angles of
+/// `CameraController`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraView {
+    pub target: Vec3,
+    pub theta: f32, // from up
+    pub phi: f32, // around up, from +X
+    pub farness: f32, // from the target, or where perspective would show as much when orthographic
+    pub orthographic: bool,
+}
+
+impl CameraView {
+    /// View of `camera`, which had a vertical field of view of `fovy` before
+    /// it turned orthographic
+    pub fn from_camera(camera: &Camera, fovy: f32) -> Self {
+        let offset = camera.eye - camera.target;
+        let distance = offset.length().max(f32::EPSILON);
+        let (farness, orthographic) = match camera.projection {
+            Projection::Orthographic { height, .. } => (height * 0.5 / (fovy * 0.5).tan(), true),
+            Projection::Perspective { .. } => (distance, false),
+        };
+        Self {
+            target: camera.target,
+            theta: (offset.z / distance).clamp(-1., 1.).acos(),
+            phi: offset.y.atan2(offset.x),
+            farness,
+            orthographic,
+        }
+    }
+
+    /// In between `self` and `other` at `t` from 0 to 1: turning the short way
+    /// round, and moving away as fast relative to the distance as moving closer.
+    /// The projection switches half way.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let turn = (other.phi - self.phi + PI).rem_euclid(TAU) - PI;
+        Self {
+            target: self.target.lerp(other.target, t),
+            theta: self.theta + (other.theta - self.theta) * t,
+            phi: self.phi + turn * t,
+            farness: self.farness * (other.farness / self.farness).powf(t),
+            orthographic: if t < 0.5 { self.orthographic } else { other.orthographic },
+        }
+    }
+}
+
+/// A view saved under a name
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    #[serde(flatten)]
+    pub view: CameraView,
+}
+
+/// Bookmarks in the order they were saved. Positions are in scene
+/// coordinates, so they only make sense for the cloud they were saved on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks {
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// Saves `view` as `name`, in place of any bookmark already called that
+    pub fn save(&mut self, name: &str, view: CameraView) {
+        match self.bookmarks.iter_mut().find(|bookmark| bookmark.name == name) {
+            Some(bookmark) => bookmark.view = view,
+            None => self.bookmarks.push(Bookmark { name: name.to_owned(), view }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    /// Whether there was a bookmark called `name`
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.bookmarks.len();
+        self.bookmarks.retain(|bookmark| bookmark.name != name);
+        self.bookmarks.len() != len
+    }
+
+    /// The bookmark saved after `name`, wrapping around, the first one without
+    pub fn after(&self, name: Option<&str>) -> Option<&Bookmark> {
+        let next = name
+            .and_then(|name| self.bookmarks.iter().position(|bookmark| bookmark.name == name))
+            .map_or(0, |index| index + 1);
+        self.bookmarks.get(next).or(self.bookmarks.first())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
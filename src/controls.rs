@@ -1,3 +1,417 @@
-pub struct KeyboardControls {
+//! Which keys and mouse buttons do what. The defaults can be remapped from
+//! TOML or JSON mapping actions to their bindings, such as
+//! `move_forward = ["KeyZ", "ArrowUp"]` or `{"view_back": ["Ctrl+Numpad1"]}`.
+//! Keys are named by where they sit on a US keyboard, as winit's `KeyCode`
+//! does, so a layout puts them in the same place. Mouse buttons are
+//! `MouseLeft`, `MouseRight`, `MouseMiddle`, `MouseBack` and `MouseForward`,
+//! gamepad buttons `GamepadSouth`, `GamepadLeftShoulder`, `GamepadDPadUp` and
+//! so on, as in `GamepadButton`.
 
-}
\ No newline at end of file
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{de::{value::StrDeserializer, IntoDeserializer}, Deserialize, Serialize};
+use winit::{event::MouseButton, keyboard::{KeyCode, ModifiersState}};
+
+use crate::engine::GamepadButton;
+
+/// What a key or button does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    // Camera, held down
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Faster,
+    Slower,
+    Rotate, // drags, and clicks pick
+    Pan,
+    // Camera
+    CycleCameraMode,
+    FitToExtent,
+    ToggleOrthographic,
+    ViewTop,
+    ViewBottom,
+    ViewFront,
+    ViewBack,
+    ViewLeft,
+    ViewRight,
+    ViewIsometric,
+    SaveBookmark,
+    NextBookmark,
+    AddKeyframe,
+    RemoveKeyframe,
+    PlayPath,
+    TogglePathPreview,
+    // Rendering
+    CycleColorMode,
+    ToggleEdl,
+    ToggleSsao,
+    ToggleSplatQuality,
+    CyclePointSizeMode,
+    GrowPoints,
+    ShrinkPoints,
+    CycleFilter,
+    // Measurements and profiles
+    CycleMeasureTool,
+    Finish,
+    Cancel,
+    ClearMeasurements,
+    Export,
+    ToggleProfile,
+    WidenProfile,
+    NarrowProfile,
+    // Clipping
+    CycleClipMode,
+    AddClipBox,
+    AddClipPlane,
+    SelectNextGizmo,
+    RemoveGizmo,
+    RaiseGizmo,
+    LowerGizmo,
+    GrowGizmo,
+    ShrinkGizmo,
+    RotateGizmoForward,
+    RotateGizmoBackward,
+    MoveGizmo, // drags the selected gizmo, rotates without one
+}
+
+impl Action {
+    /// Lasts while its key or button is down, whatever modifiers are held
+    /// meanwhile. Other actions happen once when pressed.
+    pub fn is_held(self) -> bool {
+        matches!(self,
+            Action::MoveForward | Action::MoveBackward | Action::MoveLeft | Action::MoveRight
+            | Action::MoveUp | Action::MoveDown | Action::Faster | Action::Slower
+            | Action::Rotate | Action::Pan | Action::MoveGizmo
+        )
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The name used in bindings files
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => f.write_str(&name),
+            _ => write!(f, "{self:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self { shift: state.shift_key(), ctrl: state.control_key(), alt: state.alt_key() }
+    }
+}
+
+/// A key or button with the modifiers held along, written as
+/// `Ctrl+Shift+KeyS`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub const fn key(key: KeyCode) -> Self {
+        Self { trigger: Trigger::Key(key), modifiers: Modifiers { shift: false, ctrl: false, alt: false } }
+    }
+
+    pub const fn mouse(button: MouseButton) -> Self {
+        Self { trigger: Trigger::Mouse(button), modifiers: Modifiers { shift: false, ctrl: false, alt: false } }
+    }
+
+    pub const fn gamepad(button: GamepadButton) -> Self {
+        Self { trigger: Trigger::Gamepad(button), modifiers: Modifiers { shift: false, ctrl: false, alt: false } }
+    }
+
+    pub const fn shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+
+    pub const fn ctrl(mut self) -> Self {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    pub const fn alt(mut self) -> Self {
+        self.modifiers.alt = true;
+        self
+    }
+}
+
+const MOUSE_BUTTONS: [(&str, MouseButton); 5] = [
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+    ("MouseBack", MouseButton::Back),
+    ("MouseForward", MouseButton::Forward),
+];
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, name) = s.trim().rsplit_once('+').unwrap_or(("", s.trim()));
+        let mouse = MOUSE_BUTTONS.iter().find(|(mouse, _)| mouse.eq_ignore_ascii_case(name)).map(|(_, button)| Trigger::Mouse(*button));
+        let gamepad = || name.strip_prefix("Gamepad")
+            .and_then(|button| GamepadButton::ALL.into_iter().find(|pad| format!("{pad:?}") == button))
+            .map(Trigger::Gamepad);
+        let trigger = match mouse.or_else(gamepad) {
+            Some(trigger) => trigger,
+            None => {
+                let deserializer: StrDeserializer<serde::de::value::Error> = name.into_deserializer();
+                Trigger::Key(KeyCode::deserialize(deserializer).map_err(|_| format!("Unknown key {name:?} in {s:?}"))?)
+            }
+        };
+
+        let mut binding = Binding { trigger, modifiers: Modifiers::default() };
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "shift" => binding.modifiers.shift = true,
+                "ctrl" | "control" => binding.modifiers.ctrl = true,
+                "alt" => binding.modifiers.alt = true,
+                _ => return Err(format!("Unknown modifier {modifier:?} in {s:?}")),
+            }
+        }
+
+        Ok(binding)
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.modifiers.ctrl, "Ctrl"), (self.modifiers.alt, "Alt"), (self.modifiers.shift, "Shift")] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        match self.trigger {
+            Trigger::Key(key) => write!(f, "{key:?}"),
+            Trigger::Mouse(button) => match MOUSE_BUTTONS.iter().find(|(_, mouse)| *mouse == button) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{button:?}"),
+            },
+            Trigger::Gamepad(button) => write!(f, "Gamepad{button:?}"),
+        }
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// Two actions that the same input would set off
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub binding: Binding,
+    pub actions: (Action, Action),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is bound to both {} and {}", self.binding, self.actions.0, self.actions.1)
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Invalid(String),
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Invalid(message) => write!(f, "Invalid bindings: {message}"),
+            BindingsError::Conflicts(conflicts) => {
+                let conflicts: Vec<_> = conflicts.iter().map(Conflict::to_string).collect();
+                write!(f, "Conflicting bindings: {}", conflicts.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+/// Keys and mouse buttons of every action
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Bindings {
+    actions: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use GamepadButton::*;
+        use KeyCode::*;
+        let (key, pad) = (Binding::key, Binding::gamepad);
+        let actions = [
+            (Action::MoveForward, vec![key(KeyW), key(ArrowUp)]),
+            (Action::MoveBackward, vec![key(KeyS), key(ArrowDown)]),
+            (Action::MoveLeft, vec![key(KeyA), key(ArrowLeft)]),
+            (Action::MoveRight, vec![key(KeyD), key(ArrowRight)]),
+            (Action::MoveUp, vec![key(KeyE), key(Space)]),
+            (Action::MoveDown, vec![key(KeyQ)]),
+            (Action::Faster, vec![key(ShiftLeft), key(ShiftRight), pad(RightShoulder)]),
+            (Action::Slower, vec![key(ControlLeft), key(ControlRight), pad(LeftShoulder)]),
+            (Action::Rotate, vec![Binding::mouse(MouseButton::Left)]),
+            (Action::Pan, vec![Binding::mouse(MouseButton::Right), Binding::mouse(MouseButton::Middle)]),
+            (Action::CycleCameraMode, vec![key(KeyR), pad(North)]),
+            (Action::FitToExtent, vec![key(KeyF), pad(South)]),
+            (Action::ToggleOrthographic, vec![key(Numpad5), key(Digit5), pad(West)]),
+            (Action::ViewTop, vec![key(Numpad7), key(Digit7), pad(DPadUp)]),
+            (Action::ViewBottom, vec![key(Numpad7).ctrl(), key(Digit7).ctrl()]),
+            (Action::ViewFront, vec![key(Numpad1), key(Digit1), pad(DPadDown)]),
+            (Action::ViewBack, vec![key(Numpad1).ctrl(), key(Digit1).ctrl()]),
+            (Action::ViewRight, vec![key(Numpad3), key(Digit3), pad(DPadRight)]),
+            (Action::ViewLeft, vec![key(Numpad3).ctrl(), key(Digit3).ctrl(), pad(DPadLeft)]),
+            (Action::ViewIsometric, vec![key(Numpad0), key(Digit0), pad(Start)]),
+            (Action::SaveBookmark, vec![key(KeyJ).shift()]),
+            (Action::NextBookmark, vec![key(KeyJ), pad(RightStick)]),
+            (Action::AddKeyframe, vec![key(KeyI)]),
+            (Action::RemoveKeyframe, vec![key(KeyI).shift()]),
+            (Action::PlayPath, vec![key(KeyT)]),
+            (Action::TogglePathPreview, vec![key(KeyT).shift()]),
+            (Action::CycleColorMode, vec![key(KeyC), pad(East)]),
+            (Action::ToggleEdl, vec![key(KeyL)]),
+            (Action::ToggleSsao, vec![key(KeyO)]),
+            (Action::ToggleSplatQuality, vec![key(KeyH)]),
+            (Action::CyclePointSizeMode, vec![key(KeyP)]),
+            (Action::GrowPoints, vec![key(Equal), key(NumpadAdd)]),
+            (Action::ShrinkPoints, vec![key(Minus), key(NumpadSubtract)]),
+            (Action::CycleFilter, vec![key(KeyG)]),
+            (Action::CycleMeasureTool, vec![key(KeyM)]),
+            (Action::Finish, vec![key(Enter), key(NumpadEnter)]),
+            (Action::Cancel, vec![key(Escape)]),
+            (Action::ClearMeasurements, vec![key(Delete)]),
+            (Action::Export, vec![key(KeyX)]),
+            (Action::ToggleProfile, vec![key(KeyK)]),
+            (Action::WidenProfile, vec![key(BracketRight).shift()]),
+            (Action::NarrowProfile, vec![key(BracketLeft).shift()]),
+            (Action::CycleClipMode, vec![key(KeyV)]),
+            (Action::AddClipBox, vec![key(KeyB)]),
+            (Action::AddClipPlane, vec![key(KeyN)]),
+            (Action::SelectNextGizmo, vec![key(Tab)]),
+            (Action::RemoveGizmo, vec![key(Backspace)]),
+            (Action::RaiseGizmo, vec![key(PageUp)]),
+            (Action::LowerGizmo, vec![key(PageDown)]),
+            (Action::GrowGizmo, vec![key(BracketRight)]),
+            (Action::ShrinkGizmo, vec![key(BracketLeft)]),
+            (Action::RotateGizmoForward, vec![key(Period)]),
+            (Action::RotateGizmoBackward, vec![key(Comma)]),
+            (Action::MoveGizmo, vec![Binding::mouse(MouseButton::Left).shift()]),
+        ];
+
+        Self { actions: actions.into_iter().collect() }
+    }
+}
+
+impl Bindings {
+    /// Defaults with the actions in `json` remapped, such as
+    /// `{"move_forward": ["KeyZ"], "rotate": ["MouseRight"], "pan": ["MouseLeft"]}`
+    pub fn from_json(json: &str) -> Result<Self, BindingsError> {
+        let remapped = serde_json::from_str(json).map_err(|e| BindingsError::Invalid(e.to_string()))?;
+        Self::default().remap(remapped)
+    }
+
+    /// Defaults with the actions in `toml` remapped, as in `from_json`
+    pub fn from_toml(toml: &str) -> Result<Self, BindingsError> {
+        let remapped = toml::from_str(toml).map_err(|e| BindingsError::Invalid(e.to_string()))?;
+        Self::default().remap(remapped)
+    }
+
+    /// Reads a `.json` file, TOML otherwise
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: &std::path::Path) -> Result<Self, BindingsError> {
+        let text = std::fs::read_to_string(path).map_err(|e| BindingsError::Invalid(format!("{}: {e}", path.display())))?;
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// Replaces the bindings of each action in `remapped`, an empty list
+    /// unbinds it. Fails rather than leave two actions on the same input.
+    pub fn remap(mut self, remapped: BTreeMap<Action, Vec<Binding>>) -> Result<Self, BindingsError> {
+        self.actions.extend(remapped);
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            Ok(self)
+        } else {
+            Err(BindingsError::Conflicts(conflicts))
+        }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Action, Binding)> + '_ {
+        self.actions.iter().flat_map(|(action, bindings)| bindings.iter().map(move |binding| (*action, *binding)))
+    }
+
+    /// Inputs that set off more than one action. Held actions claim their key
+    /// or button with any modifiers, except those another held action is
+    /// bound with.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let bindings: Vec<_> = self.iter().collect();
+        let mut conflicts = Vec::new();
+        for (i, (action, binding)) in bindings.iter().enumerate() {
+            for (other, other_binding) in &bindings[i + 1..] {
+                let clash = if action.is_held() && other.is_held() {
+                    binding == other_binding
+                } else if action.is_held() || other.is_held() {
+                    binding.trigger == other_binding.trigger
+                } else {
+                    binding == other_binding
+                };
+                if clash && action != other {
+                    conflicts.push(Conflict { binding: *binding, actions: (*action, *other) });
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// What `trigger` does with `modifiers` held. Modifiers nothing is bound
+    /// to are ignored, so Shift+C does what C does.
+    pub fn action(&self, trigger: Trigger, modifiers: Modifiers) -> Option<Action> {
+        let exact = Binding { trigger, modifiers };
+        self.iter().find(|(_, binding)| *binding == exact)
+            .or_else(|| self.iter().find(|(_, binding)| binding.trigger == trigger && binding.modifiers == Modifiers::default()))
+            .or_else(|| self.iter().find(|(action, binding)| binding.trigger == trigger && action.is_held()))
+            .map(|(action, _)| action)
+    }
+}
@@ -14,8 +14,28 @@ impl Camera {
             self.target,
             self.up)
     }
+
+    /// World directions of the screen's right and up
+    pub fn screen_axes(&self) -> (Vec3, Vec3) {
+        let view = Mat4::look_at_lh(self.eye, self.target, self.up);
+        (view.row(0).truncate(), view.row(1).truncate())
+    }
+
+    /// World size of a pixel at the target, for a viewport `height` pixels high
+    pub fn pixel_size(&self, height: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective { fovy, .. } => 2. * self.eye.distance(self.target) * (fovy * 0.5).tan() / height,
+            Projection::Orthographic { height: world_height, .. } => world_height / height,
+        }
+    }
 }
 
+/// Clip planes the viewer opens with, until a pointcloud is fitted into view
+pub const DEFAULT_NEAR: f32 = 0.01;
+pub const DEFAULT_FAR: f32 = 1000.;
+/// Vertical field of view the viewer opens with, in radians
+pub const DEFAULT_FOVY: f32 = std::f32::consts::FRAC_PI_3;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Projection {
     Perspective {
@@ -46,22 +66,29 @@ impl Projection {
         }
     }
 
-    pub fn to_orthographic(&mut self, distance: f32) {
+    pub fn depth_range(&self) -> (f32, f32) {
         match *self {
-            Projection::Perspective { near, far, aspect, fovy } => {
-                let height  = distance * 2. / (fovy / 2.).tan(); // distance is cos, height/2 is sin, fovy/2 is the angle
+            Projection::Perspective { near, far, .. } | Projection::Orthographic { near, far, .. } => (near, far),
+        }
+    }
 
-                *self = Projection::Orthographic { near, far, width: aspect * height, height }
-            },
-            _ => (),
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self, Projection::Orthographic { .. })
+    }
+
+    /// Orthographic projection showing at `distance` what the perspective one does
+    pub fn to_orthographic(&mut self, distance: f32) {
+        if let Projection::Perspective { near, far, aspect, fovy } = *self {
+            let height = distance * 2. * (fovy / 2.).tan(); // height/2 is opposite to the fovy/2 angle, distance adjacent
+
+            *self = Projection::Orthographic { near, far, width: aspect * height, height }
         }
     }
 
+    /// Perspective projection showing at `distance` what the orthographic one does
     pub fn to_perspective(&mut self, distance: f32) {
-        match *self {
-            Projection::Orthographic { near, far, width, height } =>
-                *self = Projection::Perspective { near, far, aspect: width/height, fovy: 2. * (height/distance/2.).atan() },
-            _ => (),
+        if let Projection::Orthographic { near, far, width, height } = *self {
+            *self = Projection::Perspective { near, far, aspect: width/height, fovy: 2. * (height/distance/2.).atan() }
         }
     }
 
@@ -0,0 +1,150 @@
+use bytemuck::Zeroable;
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+pub const MAX_CLIP_BOXES: usize = 8;
+pub const MAX_CLIP_PLANES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ClipMode {
+    #[default]
+    Disabled,
+    Inside, // only the points in the clip volume are drawn
+    Outside, // the clip volume is cut away
+    Highlight, // everything is drawn, the clip volume tinted
+}
+
+impl ClipMode {
+    pub const ALL: [ClipMode; 4] = [ClipMode::Disabled, ClipMode::Inside, ClipMode::Outside, ClipMode::Highlight];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// Oriented box, in scene coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipBox {
+    pub center: Vec3,
+    pub half_size: Vec3,
+    pub rotation: Quat,
+}
+
+impl ClipBox {
+    pub fn new(center: Vec3, half_size: Vec3) -> Self {
+        Self { center, half_size, rotation: Quat::IDENTITY }
+    }
+
+    /// Maps the box to the cube from -1 to 1
+    pub fn world_to_box(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.half_size, self.rotation, self.center).inverse()
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.world_to_box().transform_point3(point).abs().max_element() <= 1.
+    }
+
+    /// The 12 edges, for the gizmo
+    pub fn edges(&self) -> Vec<(Vec3, Vec3)> {
+        let corner = |i: u32| {
+            let unit = Vec3::new(
+                if i & 1 == 0 { -1. } else { 1. },
+                if i & 2 == 0 { -1. } else { 1. },
+                if i & 4 == 0 { -1. } else { 1. },
+            );
+            self.center + self.rotation * (unit * self.half_size)
+        };
+        // Corners one bit apart share an edge
+        (0..8)
+            .flat_map(|i| [1, 2, 4].into_iter().filter(move |bit| i & bit == 0).map(move |bit| (i, i | bit)))
+            .map(|(a, b)| (corner(a), corner(b)))
+            .collect()
+    }
+}
+
+/// Half-space, the side the normal points to is outside
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipPlane {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+impl ClipPlane {
+    /// (normal, offset) with `dot(normal, p) + offset > 0` outside
+    pub fn equation(&self) -> Vec4 {
+        let normal = self.normal.normalize_or(Vec3::Z);
+        normal.extend(-normal.dot(self.point))
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.equation().dot(point.extend(1.)) <= 0.
+    }
+
+    /// Square of side `size` around `point` and a tick along the normal, for the gizmo
+    pub fn outline(&self, size: f32) -> Vec<(Vec3, Vec3)> {
+        let normal = self.normal.normalize_or(Vec3::Z);
+        let (u, v) = normal.any_orthonormal_pair();
+        let half = size * 0.5;
+        let corners = [u + v, u - v, -u - v, -u + v].map(|offset| self.point + offset * half);
+
+        let mut lines: Vec<_> = (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect();
+        lines.push((self.point, self.point + normal * half));
+        lines
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClipSettings {
+    pub mode: ClipMode,
+    pub boxes: Vec<ClipBox>, // only the first MAX_CLIP_BOXES are used
+    pub planes: Vec<ClipPlane>, // only the first MAX_CLIP_PLANES are used
+}
+
+impl ClipSettings {
+    /// Inside any of the boxes, if there are some, and inside every plane
+    pub fn in_volume(&self, point: Vec3) -> bool {
+        let boxes = &self.boxes[..self.boxes.len().min(MAX_CLIP_BOXES)];
+        let planes = &self.planes[..self.planes.len().min(MAX_CLIP_PLANES)];
+        (boxes.is_empty() || boxes.iter().any(|clip_box| clip_box.contains(point)))
+            && planes.iter().all(|plane| plane.contains(point))
+    }
+
+    /// Whether `point` is drawn, the same test as the shaders
+    pub fn is_visible(&self, point: Vec3) -> bool {
+        if self.boxes.is_empty() && self.planes.is_empty() {
+            return true;
+        }
+        match self.mode {
+            ClipMode::Disabled | ClipMode::Highlight => true,
+            ClipMode::Inside => self.in_volume(point),
+            ClipMode::Outside => !self.in_volume(point),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ClipUniformData {
+    boxes: [Mat4; MAX_CLIP_BOXES], // world to box
+    planes: [Vec4; MAX_CLIP_PLANES],
+    box_count: u32,
+    plane_count: u32,
+    mode: u32,
+    _padding: u32,
+}
+
+impl From<&ClipSettings> for ClipUniformData {
+    fn from(settings: &ClipSettings) -> Self {
+        let mut data = Self::zeroed();
+        for (slot, clip_box) in data.boxes.iter_mut().zip(&settings.boxes) {
+            *slot = clip_box.world_to_box();
+        }
+        for (slot, plane) in data.planes.iter_mut().zip(&settings.planes) {
+            *slot = plane.equation();
+        }
+        data.box_count = settings.boxes.len().min(MAX_CLIP_BOXES) as u32;
+        data.plane_count = settings.planes.len().min(MAX_CLIP_PLANES) as u32;
+        data.mode = settings.mode as u32;
+
+        data
+    }
+}
@@ -1,34 +1,112 @@
 
-use std::{iter, sync::Arc};
+use std::{collections::HashMap, future::Future, pin::Pin, rc::Rc, sync::Arc, task::{Context, Poll, Waker}};
 use dotenvy_macro::dotenv;
-use glam::Vec3;
+use glam::{Vec3, Vec4Swizzles};
 use winit::{
-    event::*, window::Window
+    dpi::PhysicalPosition, event::*, keyboard::{ModifiersState, PhysicalKey}, window::Window
 };
 
-use super::{input::*, pipeline::PointcloudPipeline, *};
-use crate::pointcloud::Pointcloud;
+use super::{gizmo::ClipGizmos, input::*, renderer::Renderer, *};
+use crate::{
+    api::{Channel, Command, Event},
+    bookmarks::Bookmarks,
+    controls::{Action, Bindings, Trigger},
+    flythrough::{CameraPath, Keyframe, Playback, PATH_SPEED},
+    measure::{MeasureKind, Measurements}, permalink::Permalink, platform, pointcloud::Pointcloud, profile::{self, Profile, ProfilePoint},
+};
+
+const POINT_SIZE_STEP: f32 = 1.25;
+// Pixels the cursor can move between press and release for it to still be a click
+const CLICK_TOLERANCE: f64 = 3.;
+const MEASURE_COLOR: [f32; 4] = [1., 0.85, 0., 1.];
+// Fraction of the scene a clip gizmo moves per key press
+const GIZMO_STEP: f32 = 0.02;
+const PROFILE_COLOR: [f32; 4] = [0., 0.9, 1., 1.];
+// Initial corridor width, as a fraction of the scene
+const PROFILE_WIDTH: f64 = 0.02;
+const PROFILE_WIDTH_STEP: f64 = 1.25;
+const FILTER_PRESETS: usize = 5;
+// Longest step the camera takes, so it doesn't jump after a stall
+const MAX_FRAME_TIME: f32 = 0.1;
+// Seconds to fly to a bookmark
+const FLIGHT_TIME: f32 = 1.5;
+const PATH_COLOR: [f32; 4] = [1., 0.3, 0.8, 1.];
+// Length of the view direction drawn at each keyframe, as a fraction of the scene
+const KEYFRAME_TICK: f32 = 0.03;
+// Seconds between the clicks of a double click
+const DOUBLE_CLICK_TIME: f64 = 0.4;
+// Seconds without scrolling after which zoom looks for the point under the cursor again
+const SCROLL_PAUSE: f64 = 0.3;
+// Seconds between updates of the page URL, browsers limit how often it can change
+const PERMALINK_INTERVAL: f64 = 1.;
 
+/// Pointcloud being downloaded, polled each update
+type Loading = Pin<Box<dyn Future<Output = Result<Pointcloud, String>>>>;
+
+/// What the pick in flight is for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PickPurpose {
+    Select,
+    ZoomAnchor,
+    Recentre,
+}
 
 pub struct Engine {
     surface: wgpu::Surface<'static>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     window: Arc<Window>,
+    channel: Rc<Channel>, // to and from the page's `Viewer`
     
-    uniform: Uniform,
-    pointcloud: Pointcloud,
-    pointcloud_pipeline: PointcloudPipeline,
-    depth_view: wgpu::TextureView,
-    depth_texture: wgpu::Texture,
+    renderer: Renderer,
 
     camera: Camera,
     input_server: InputServer,
+
+    cursor: PhysicalPosition<f64>,
+    click_start: Option<PhysicalPosition<f64>>,
+    picked: Option<PickedPoint>,
+    pick_purpose: PickPurpose,
+    queued_pick: Option<(PickPurpose, PhysicalPosition<f64>)>, // asked for while another was in flight
+    last_click: Option<(f64, PhysicalPosition<f64>)>, // time and place, for double clicks
+    zoom_anchor_at: Option<(f64, PhysicalPosition<f64>)>, // last scroll and where the anchor was picked
+    measurements: Measurements,
+    gizmos: ClipGizmos,
+    modifiers: ModifiersState,
+    held: HashMap<Trigger, Action>, // what each held key or button went down as
+    bindings: Bindings,
+    gamepads: Gamepads,
+    gamepad: GamepadSettings,
+    gamepad_focus: bool, // only one viewer on a page follows the gamepads
+    bookmarks: Bookmarks,
+    bookmark: Option<String>, // last flown to or saved
+    camera_path: CameraPath,
+    playback: Option<Playback>,
+    path_preview: bool,
+    path_speed: f32, // meters per second
+    profile: Option<Profile>,
+    profile_drawing: bool, // picks extend the path until it is extracted
+    profile_points: Vec<ProfilePoint>,
+    labels: Vec<(f32, f32, String)>, // last shown, in pixels
+    filter_preset: usize,
+    dataset: String, // URL of the LAS file
+    loading: Option<(String, Loading)>, // URL and download
+    after_load: Option<Permalink>, // shown once the download is done
+    follow_url: bool, // only one viewer on a page keeps the URL
+    permalink: String, // page URL fragment last written or read
+    permalink_at: f64,
+    last_update: f64, // seconds, from platform::now
 }
 
 impl Engine {
-    pub async fn new(window: Arc<Window>) -> Self {
+    /// Opens the LAS file at `dataset`, or the default one, unless following
+    /// the page URL and it links to another. It loads in the background.
+    pub(crate) async fn new(
+        window: Arc<Window>,
+        backend: RasterBackend,
+        channel: Rc<Channel>,
+        dataset: Option<String>,
+        follow_url: bool,
+    ) -> Self {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
@@ -54,7 +132,10 @@ impl Engine {
                 &wgpu::DeviceDescriptor {
                     label: None,
                     trace: wgpu::Trace::Off, // Trace path
-                    required_features: wgpu::Features::empty(),
+                    required_features: match backend {
+                        RasterBackend::Compute => adapter.features() & INT64_ATOMICS,
+                        RasterBackend::Pipeline => wgpu::Features::empty(),
+                    },
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
                     required_limits: wgpu::Limits::default(),
@@ -87,73 +168,76 @@ impl Engine {
             target: (0.0, 0.0, 0.0).into(),
             up: Vec3::Z, // will be overriden by input_server.update
             projection: Projection::perspective(
-                0.01, 
-                1000., 
+                DEFAULT_NEAR,
+                DEFAULT_FAR,
                 config.width as f32, 
                 config.height as f32, 
-                60f32.to_radians()
+                DEFAULT_FOVY
             )
         };
         let mut input_server = InputServer::new();
-        input_server.update(&mut camera); // setting camera according to initial state
+        input_server.resize(config.height);
+        input_server.update(&mut camera, 0.); // setting camera according to initial state
         dbg!(&camera);
 	dbg!(size);
 
-        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }
-            ],
-            label: Some("uniform_bind_group_layout"),
-        });
-        let uniform = Uniform::new(&queue, &device, &uniform_bind_group_layout, &camera, config.width, config.height);
-
-
-        let pointcloud = Pointcloud::from_las(
-            &device,
-            &queue,
-            dotenv!("POINTCLOUD_URL"),
-        ).await.unwrap();
-        let pointcloud_pipeline = PointcloudPipeline::new(&device, &uniform_bind_group_layout, config.format);
-
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("depth texture"),
-            size: wgpu::Extent3d {
-                width: config.width.max(500),
-                height: config.height.max(500),
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[wgpu::TextureFormat::Depth32Float],
+        let renderer = Renderer::with_backend(device, queue, config.format, &camera, config.width, config.height, backend);
+
+        // A link can open another dataset and view
+        let hash = platform::url_hash().filter(|_| follow_url).unwrap_or_default();
+        let permalink = Permalink::from_hash(&hash).unwrap_or_else(|e| {
+            log::warn!("Ignoring the link: {e}");
+            Permalink::default()
         });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let dataset = permalink.dataset.clone()
+            .or(dataset)
+            .unwrap_or_else(|| dotenv!("POINTCLOUD_URL").to_owned());
 
-        Self {
+        let mut engine = Self {
             surface,
-            device,
-            queue,
             config,
             window,
-            uniform,
+            channel,
+            renderer,
             camera,
-            pointcloud,
-            pointcloud_pipeline,
-            depth_view,
-            depth_texture,
             input_server,
-        }
+            cursor: PhysicalPosition::default(),
+            click_start: None,
+            picked: None,
+            pick_purpose: PickPurpose::Select,
+            queued_pick: None,
+            last_click: None,
+            zoom_anchor_at: None,
+            measurements: Measurements::default(),
+            gizmos: ClipGizmos::default(),
+            modifiers: ModifiersState::empty(),
+            held: HashMap::new(),
+            bindings: Bindings::default(),
+            gamepads: Gamepads::new(),
+            gamepad: GamepadSettings::default(),
+            gamepad_focus: false,
+            bookmarks: Bookmarks::default(),
+            bookmark: None,
+            camera_path: CameraPath::default(),
+            playback: None,
+            path_preview: true,
+            path_speed: PATH_SPEED,
+            profile: None,
+            profile_drawing: false,
+            profile_points: Vec::new(),
+            labels: Vec::new(),
+            filter_preset: 0,
+            dataset: dataset.clone(),
+            loading: None,
+            after_load: Some(permalink),
+            follow_url,
+            permalink: hash,
+            permalink_at: platform::now(),
+            last_update: platform::now(),
+        };
+        engine.load(&dataset);
+
+        engine
     }
 
     pub fn window(&self) -> &Window {
@@ -170,38 +254,978 @@ impl Engine {
             );
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("depth texture"),
-                size: wgpu::Extent3d {
-                    width: self.config.width.max(1),
-                    height: self.config.height.max(1),
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[wgpu::TextureFormat::Depth32Float],
-            });
-            self.depth_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-            self.uniform.camera_uniform.width = self.config.width as f32;
-            self.uniform.camera_uniform.height = self.config.height as f32;
+            self.input_server.resize(new_size.height);
+            self.surface.configure(self.renderer.device(), &self.config);
+            self.renderer.resize(self.config.width, self.config.height);
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
-        self.input_server.window_input(event)
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor = *position,
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::MouseWheel { .. } | WindowEvent::PinchGesture { .. } => self.anchor_zoom(),
+            _ => (),
+        }
+
+        let (trigger, state, repeat) = match event {
+            WindowEvent::KeyboardInput { event: KeyEvent {
+                physical_key: PhysicalKey::Code(key),
+                state,
+                repeat,
+                ..
+            }, ..} => (Trigger::Key(*key), *state, *repeat),
+            WindowEvent::MouseInput { button, state, .. } => (Trigger::Mouse(*button), *state, false),
+            _ => {
+                let used = self.input_server.window_input(event);
+                if let Some(gesture) = self.input_server.take_gesture() {
+                    self.gesture(gesture);
+                }
+                return used;
+            }
+        };
+        self.press(trigger, state, repeat)
+    }
+
+    /// Acts on a key or button going down or up, returns whether it is bound
+    fn press(&mut self, trigger: Trigger, state: ElementState, repeat: bool) -> bool {
+        // Released as what it was pressed as, whatever the modifiers are now
+        let held = match state {
+            ElementState::Released => self.held.remove(&trigger),
+            ElementState::Pressed => None,
+        };
+        let Some(mut action) = held.or_else(|| self.bindings.action(trigger, self.modifiers.into())) else {
+            return false;
+        };
+        if action.is_held() {
+            if action == Action::MoveGizmo && self.gizmos.selected.is_none() {
+                action = Action::Rotate;
+            }
+            if state == ElementState::Pressed {
+                self.held.insert(trigger, action);
+            }
+            match action {
+                Action::MoveGizmo => return true,
+                Action::Rotate => self.click(state),
+                _ => (),
+            }
+            return self.input_server.process_action(action, state);
+        }
+        if state == ElementState::Pressed && !repeat {
+            self.trigger(action);
+        }
+        true
+    }
+
+    /// Picks on a click of the rotate button that didn't drag, recentres on
+    /// a double click
+    fn click(&mut self, state: ElementState) {
+        if state == ElementState::Pressed {
+            self.click_start = Some(self.cursor);
+            return;
+        }
+        let near = |a: PhysicalPosition<f64>, b: PhysicalPosition<f64>| (a.x - b.x).hypot(a.y - b.y) <= CLICK_TOLERANCE;
+        if self.click_start.take().is_some_and(|start| near(start, self.cursor)) {
+            let now = platform::now();
+            let double_click = self.last_click.take()
+                .is_some_and(|(time, position)| now - time < DOUBLE_CLICK_TIME && near(position, self.cursor));
+            if double_click {
+                self.request_pick(PickPurpose::Recentre, self.cursor);
+            } else {
+                self.last_click = Some((now, self.cursor));
+                self.pick(self.cursor.x as u32, self.cursor.y as u32);
+            }
+        }
+    }
+
+    /// Taps pick like clicks, pinches zoom towards the point between the fingers
+    fn gesture(&mut self, gesture: Gesture) {
+        match gesture {
+            Gesture::Tap(position) => {
+                self.cursor = position;
+                self.pick(position.x as u32, position.y as u32);
+            }
+            Gesture::DoubleTap(position) => {
+                self.cursor = position;
+                self.request_pick(PickPurpose::Recentre, self.cursor);
+            }
+            Gesture::Pinch(centre) => {
+                self.cursor = centre;
+                self.anchor_zoom();
+            }
+        }
+    }
+
+    /// Does what a key or button press of `action` asks for
+    fn trigger(&mut self, action: Action) {
+        match action {
+            Action::SaveBookmark => {
+                let name = format!("View {}", self.bookmarks.len() + 1);
+                self.save_bookmark(&name);
+            }
+            Action::NextBookmark => {
+                match self.bookmarks.after(self.bookmark.as_deref()).map(|bookmark| bookmark.name.clone()) {
+                    Some(name) => {
+                        self.fly_to_bookmark(&name);
+                    }
+                    None => platform::set_overlay(&self.window, "No bookmarks, save one with Shift+J"),
+                }
+            }
+            Action::AddKeyframe => {
+                self.add_keyframe();
+            }
+            Action::RemoveKeyframe => {
+                self.remove_keyframe();
+            }
+            Action::PlayPath => {
+                if self.playback.is_some() {
+                    self.stop_path();
+                } else {
+                    self.play_path();
+                }
+            }
+            Action::TogglePathPreview => {
+                self.path_preview = !self.path_preview;
+                self.update_lines();
+            }
+            Action::CycleColorMode => {
+                self.set_color_mode(self.color_mode().next());
+            }
+            Action::ToggleEdl => {
+                let edl = self.renderer.edl_mut();
+                edl.enabled = !edl.enabled;
+            }
+            Action::ToggleSsao => {
+                let ssao = self.renderer.ssao_mut();
+                ssao.enabled = !ssao.enabled;
+            }
+            Action::ToggleSplatQuality => {
+                let splats = self.renderer.splats_mut();
+                splats.mode = match splats.mode {
+                    SplatMode::Standard => SplatMode::HighQuality,
+                    SplatMode::HighQuality => SplatMode::Standard,
+                };
+            }
+            Action::CyclePointSizeMode => {
+                let point_size = self.renderer.point_size_mut();
+                point_size.mode = point_size.mode.next();
+            }
+            Action::CycleMeasureTool => {
+                self.measurements.set_tool(MeasureKind::next(self.measurements.tool()));
+                self.measurements_changed();
+            }
+            Action::Finish if self.profile_drawing => {
+                self.extract_profile();
+            }
+            Action::Finish => {
+                self.measurements.finish();
+                self.measurements_changed();
+            }
+            Action::Cancel if self.playback.is_some() => {
+                self.stop_path();
+            }
+            Action::Cancel if self.profile.is_some() => {
+                self.close_profile();
+            }
+            Action::Cancel => {
+                self.measurements.cancel();
+                self.measurements_changed();
+            }
+            Action::CycleFilter => {
+                self.filter_preset = (self.filter_preset + 1) % FILTER_PRESETS;
+                let (name, filter) = Self::filter_preset(self.filter_preset);
+                *self.renderer.filter_mut() = filter;
+                platform::set_overlay(&self.window, &format!("Showing {name}"));
+            }
+            Action::ToggleProfile => {
+                match self.profile {
+                    Some(_) => self.close_profile(),
+                    None => self.start_profile(),
+                }
+            }
+            Action::ClearMeasurements => {
+                self.measurements.clear();
+                self.measurements_changed();
+            }
+            Action::Export => {
+                self.export_measurements();
+                self.export_profile();
+                self.export_bookmarks();
+                self.export_path();
+            }
+            Action::CycleClipMode => {
+                let clipping = self.renderer.clipping_mut();
+                clipping.mode = clipping.mode.next();
+                platform::set_overlay(&self.window, &format!("Clipping {:?}", clipping.mode));
+            }
+            Action::AddClipBox => {
+                let half_size = self.scene_extent() * 0.25;
+                self.gizmos.add_box(self.renderer.clipping_mut(), ClipBox::new(self.camera.target, half_size));
+                self.update_lines();
+            }
+            Action::AddClipPlane => {
+                let plane = ClipPlane { point: self.camera.target, normal: Vec3::Z };
+                self.gizmos.add_plane(self.renderer.clipping_mut(), plane);
+                self.update_lines();
+            }
+            Action::SelectNextGizmo => {
+                self.gizmos.select_next(self.renderer.clipping());
+                self.update_lines();
+            }
+            Action::RemoveGizmo => {
+                self.gizmos.remove_selected(self.renderer.clipping_mut());
+                self.update_lines();
+            }
+            Action::RaiseGizmo | Action::LowerGizmo => {
+                let step = self.scene_extent().max_element() * GIZMO_STEP;
+                let offset = if action == Action::RaiseGizmo { step } else { -step };
+                self.gizmos.translate(self.renderer.clipping_mut(), Vec3::Z * offset);
+                self.update_lines();
+            }
+            Action::WidenProfile | Action::NarrowProfile => {
+                if let Some(profile) = &mut self.profile {
+                    profile.width *= if action == Action::WidenProfile { PROFILE_WIDTH_STEP } else { 1. / PROFILE_WIDTH_STEP };
+                    if self.profile_drawing {
+                        self.profile_changed();
+                    } else {
+                        self.extract_profile();
+                    }
+                }
+            }
+            Action::GrowGizmo | Action::ShrinkGizmo => {
+                self.gizmos.scale(self.renderer.clipping_mut(), if action == Action::GrowGizmo { 1 } else { -1 });
+                self.update_lines();
+            }
+            Action::RotateGizmoForward | Action::RotateGizmoBackward => {
+                let (right, _) = self.camera.screen_axes();
+                self.gizmos.rotate(self.renderer.clipping_mut(), if action == Action::RotateGizmoForward { 1. } else { -1. }, right);
+                self.update_lines();
+            }
+            Action::CycleCameraMode => {
+                let mode = self.input_server.mode().next();
+                self.input_server.set_mode(mode);
+                platform::set_overlay(&self.window, &format!("{mode:?} mode"));
+            }
+            Action::FitToExtent => {
+                self.fit_to_extent();
+            }
+            Action::ViewTop | Action::ViewBottom | Action::ViewFront | Action::ViewBack
+                | Action::ViewLeft | Action::ViewRight | Action::ViewIsometric => {
+                let preset = match action {
+                    Action::ViewTop => ViewPreset::Top,
+                    Action::ViewBottom => ViewPreset::Bottom,
+                    Action::ViewFront => ViewPreset::Front,
+                    Action::ViewBack => ViewPreset::Back,
+                    Action::ViewLeft => ViewPreset::Left,
+                    Action::ViewRight => ViewPreset::Right,
+                    _ => ViewPreset::Isometric,
+                };
+                self.set_view(preset);
+                platform::set_overlay(&self.window, &format!("{preset:?} view"));
+            }
+            Action::ToggleOrthographic => {
+                let orthographic = !self.camera.projection.is_orthographic();
+                self.set_orthographic(orthographic);
+                platform::set_overlay(&self.window, if orthographic { "Orthographic" } else { "Perspective" });
+            }
+            Action::GrowPoints => {
+                self.renderer.point_size_mut().scale(POINT_SIZE_STEP);
+            }
+            Action::ShrinkPoints => {
+                self.renderer.point_size_mut().scale(1. / POINT_SIZE_STEP);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Bookmarks) {
+        self.bookmarks = bookmarks;
+        self.bookmark = None;
+        self.bookmarks_changed();
+    }
+
+    /// Bookmarks the current view as `name`, replacing any called that
+    pub fn save_bookmark(&mut self, name: &str) {
+        self.bookmarks.save(name, self.input_server.view(&self.camera));
+        self.bookmark = Some(name.to_owned());
+        self.bookmarks_changed();
+        platform::set_overlay(&self.window, &format!("Saved {name}"));
+    }
+
+    /// Flies to the view bookmarked as `name`, if there is one
+    pub fn fly_to_bookmark(&mut self, name: &str) -> bool {
+        let Some(bookmark) = self.bookmarks.get(name) else {
+            return false;
+        };
+        self.input_server.fly_to(bookmark.view, FLIGHT_TIME);
+        platform::set_overlay(&self.window, name);
+        self.bookmark = Some(name.to_owned());
+        true
+    }
+
+    fn bookmarks_changed(&self) {
+        match self.bookmarks.to_json() {
+            Ok(json) => self.channel.publish_bookmarks(json),
+            Err(e) => log::error!("Couldn't serialize bookmarks: {}", e),
+        }
+    }
+
+    pub fn export_bookmarks(&self) {
+        if self.bookmarks.is_empty() {
+            return;
+        }
+        match self.bookmarks.to_json() {
+            Ok(json) => platform::save_file("bookmarks.json", json.as_bytes()),
+            Err(e) => log::error!("Couldn't serialize bookmarks: {}", e),
+        }
+    }
+
+    pub fn camera_path(&self) -> &CameraPath {
+        &self.camera_path
+    }
+
+    pub fn set_camera_path(&mut self, path: CameraPath) {
+        self.stop_path();
+        self.camera_path = path;
+        self.path_changed();
+    }
+
+    /// Adds where the camera is to the end of the path
+    pub fn add_keyframe(&mut self) {
+        self.camera_path.push(Keyframe::from_camera(&self.camera));
+        self.path_changed();
+        platform::set_overlay(&self.window, &format!("Keyframe {}, T plays the path", self.camera_path.len()));
+    }
+
+    pub fn remove_keyframe(&mut self) {
+        if self.camera_path.pop().is_some() {
+            self.path_changed();
+            platform::set_overlay(&self.window, &format!("{} keyframes left", self.camera_path.len()));
+        }
+    }
+
+    /// Flies the camera along the path at `path_speed`, returns whether it
+    /// had two keyframes to go between
+    pub fn play_path(&mut self) -> bool {
+        if self.camera_path.len() < 2 {
+            platform::set_overlay(&self.window, "Add at least two keyframes with I");
+            return false;
+        }
+        let meter = self.renderer.pointcloud().map_or(1., |pointcloud| pointcloud.transform().scale.z as f32);
+        self.playback = Some(Playback::new(self.camera_path.clone(), self.path_speed * meter));
+        self.update_lines();
+        platform::set_overlay(&self.window, "Playing the path, Esc stops");
+        true
+    }
+
+    /// Hands the camera back to the controls where playback left it
+    pub fn stop_path(&mut self) {
+        if self.playback.take().is_some() {
+            self.input_server.attach(&self.camera);
+            self.update_lines();
+        }
+    }
+
+    pub fn is_playing_path(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Meters per second, from the next `play_path`
+    pub fn set_path_speed(&mut self, speed: f32) {
+        self.path_speed = speed;
+    }
+
+    fn path_changed(&mut self) {
+        self.update_lines();
+        match self.camera_path.to_json() {
+            Ok(json) => self.channel.publish_path(json),
+            Err(e) => log::error!("Couldn't serialize the camera path: {}", e),
+        }
+    }
+
+    pub fn export_path(&self) {
+        if self.camera_path.is_empty() {
+            return;
+        }
+        match self.camera_path.to_json() {
+            Ok(json) => platform::save_file("camera_path.json", json.as_bytes()),
+            Err(e) => log::error!("Couldn't serialize the camera path: {}", e),
+        }
+    }
+
+    pub fn gamepad(&self) -> &GamepadSettings {
+        &self.gamepad
+    }
+
+    pub fn gamepad_mut(&mut self) -> &mut GamepadSettings {
+        &mut self.gamepad
+    }
+
+    /// What the page's `Viewer` talks to this engine through
+    pub(crate) fn channel(&self) -> &Rc<Channel> {
+        &self.channel
+    }
+
+    /// Whether the gamepads and 3D mice drive this viewer
+    pub(crate) fn set_gamepad_focus(&mut self, focus: bool) {
+        self.gamepad_focus = focus;
     }
 
     pub fn device_input(&mut self, event: &DeviceEvent) {
+        // Dragging with `MoveGizmo` moves the selected clip gizmo instead of the camera
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.held.values().any(|action| *action == Action::MoveGizmo) && self.gizmos.selected.is_some() {
+                let (right, up) = self.camera.screen_axes();
+                let pixel_size = self.camera.pixel_size(self.renderer.size().1 as f32);
+                let offset = (right * delta.0 as f32 - up * delta.1 as f32) * pixel_size;
+                self.gizmos.translate(self.renderer.clipping_mut(), offset);
+                self.update_lines();
+                return;
+            }
+        }
         self.input_server.device_input(event);
     }
 
     pub fn update(&mut self) {
-        self.input_server.update(&mut self.camera);
-        self.uniform.update(&self.camera, &self.queue);
+        for command in self.channel.take_commands() {
+            match command {
+                Command::Load(url) => self.load(&url),
+                Command::SetColorMode(mode) => self.set_color_mode(mode),
+                Command::SetPointSize(size) => self.renderer.point_size_mut().set(size),
+                Command::SetPointSizeMode(mode) => self.renderer.point_size_mut().mode = mode,
+                Command::SetCamera(view, seconds) => self.input_server.fly_to(view, seconds),
+                Command::Pick(x, y) => {
+                    let scale = self.window.scale_factor();
+                    self.pick((x * scale) as u32, (y * scale) as u32);
+                }
+                Command::SetFilter(filter) => *self.renderer.filter_mut() = filter,
+                Command::SetOrthographic(orthographic) => self.set_orthographic(orthographic),
+                Command::SetView(preset) => self.set_view(preset),
+                Command::FitToExtent => self.fit_to_extent(),
+                Command::SetBindings(bindings) => self.set_bindings(bindings),
+                Command::SaveBookmark(name) => self.save_bookmark(&name),
+                Command::FlyToBookmark(name) => {
+                    if !self.fly_to_bookmark(&name) {
+                        platform::set_overlay(&self.window, &format!("No bookmark {name:?}"));
+                    }
+                }
+                Command::RemoveBookmark(name) => {
+                    self.bookmarks.remove(&name);
+                    self.bookmarks_changed();
+                }
+                Command::SetBookmarks(bookmarks) => self.set_bookmarks(bookmarks),
+                Command::AddKeyframe => self.add_keyframe(),
+                Command::RemoveKeyframe => self.remove_keyframe(),
+                Command::SetPath(path) => self.set_camera_path(path),
+                Command::PlayPath => {
+                    self.play_path();
+                }
+                Command::StopPath => self.stop_path(),
+                Command::SetPathSpeed(speed) => self.set_path_speed(speed),
+            }
+        }
+        self.poll_loading();
+        let now = platform::now();
+        let dt = ((now - self.last_update) as f32).min(MAX_FRAME_TIME);
+        self.last_update = now;
+        let (gamepad, changes) = if self.gamepad_focus { self.gamepads.poll() } else { self.gamepads.release() };
+        for (button, state) in changes {
+            self.press(Trigger::Gamepad(button), state, false);
+        }
+        let space_mouse = self.gamepad.space_mouse_axes(&self.gamepads.space_mouse());
+        self.input_server.process_axes(self.gamepad.axes(&gamepad) + space_mouse);
+        match self.playback.as_mut().and_then(|playback| playback.advance(dt)) {
+            Some(keyframe) => keyframe.apply(&mut self.camera),
+            None => {
+                self.stop_path();
+                self.input_server.update(&mut self.camera, dt);
+            }
+        }
+        self.renderer.update(&self.camera);
+        self.channel.publish_camera(self.input_server.view(&self.camera));
+
+        if let Poll::Ready(picked) = self.renderer.poll_pick() {
+            match self.pick_purpose {
+                PickPurpose::ZoomAnchor => {
+                    let anchor = picked.map_or(ZoomAnchor::Target, |picked| ZoomAnchor::Point(picked.local));
+                    self.input_server.set_zoom_anchor(anchor);
+                }
+                PickPurpose::Recentre => {
+                    if let Some(picked) = &picked {
+                        self.input_server.recentre(&mut self.camera, picked.local);
+                    }
+                }
+                PickPurpose::Select => {
+                    match (&picked, &mut self.profile) {
+                        (Some(picked), Some(profile)) if self.profile_drawing => {
+                            profile.path.push(picked.position);
+                            self.profile_changed();
+                        }
+                        _ => self.show_picked(picked.as_ref()),
+                    }
+                    self.channel.emit(Event::Select(picked.as_ref()));
+                    self.picked = picked;
+                }
+            }
+            if let Some((purpose, position)) = self.queued_pick.take() {
+                self.request_pick(purpose, position);
+            }
+        }
+        self.update_labels();
+        if now - self.permalink_at > PERMALINK_INTERVAL && self.loading.is_none() && self.follow_url {
+            self.permalink_at = now;
+            self.sync_permalink();
+        }
+    }
+
+    /// Downloads the LAS file at `url` in the background, the pointcloud
+    /// shown until then stays
+    pub fn load(&mut self, url: &str) {
+        let (device, queue) = (self.renderer.device().clone(), self.renderer.queue().clone());
+        let (download, channel) = (url.to_owned(), self.channel.clone());
+        let loading = async move {
+            let progress = |fraction| channel.emit(Event::Progress(fraction));
+            Pointcloud::from_las_with_progress(&device, &queue, &download, progress).await.map_err(|e| e.to_string())
+        };
+        self.loading = Some((url.to_owned(), Box::pin(loading)));
+    }
+
+    /// Shows the pointcloud once downloaded
+    fn poll_loading(&mut self) {
+        let Some((url, loading)) = &mut self.loading else {
+            return;
+        };
+        // Polled again next update, so nothing needs waking
+        let Poll::Ready(result) = loading.as_mut().poll(&mut Context::from_waker(Waker::noop())) else {
+            return;
+        };
+        let url = std::mem::take(url);
+        self.loading = None;
+        match result {
+            Ok(pointcloud) => {
+                let points = pointcloud.points().len();
+                self.renderer.set_pointcloud(pointcloud);
+                self.pointcloud_loaded(url);
+                if let Some(permalink) = self.after_load.take() {
+                    self.apply_permalink(&permalink);
+                }
+                self.channel.emit(Event::Load { url: &self.dataset, points });
+            }
+            Err(e) => {
+                // The link's view was for the dataset that didn't load
+                self.after_load = None;
+                let message = format!("Couldn't load {url}: {e}");
+                log::error!("{message}");
+                platform::set_overlay(&self.window, &message);
+                self.channel.emit(Event::Error(&message));
+            }
+        }
+    }
+
+    /// What is in view, as a link would open it again
+    pub fn permalink(&self) -> Permalink {
+        let filter = self.renderer.filter();
+        Permalink {
+            dataset: Some(self.dataset.clone()),
+            view: Some(self.input_server.view(&self.camera)),
+            color_mode: Some(self.color_mode()),
+            filter: (*filter != FilterSettings::default()).then(|| filter.clone()),
+        }
+    }
+
+    /// Shows what `permalink` has, other than its dataset
+    pub fn apply_permalink(&mut self, permalink: &Permalink) {
+        if let Some(view) = permalink.view {
+            self.input_server.fly_to(view, 0.); // straight there
+        }
+        if let Some(mode) = permalink.color_mode {
+            self.set_color_mode(mode);
+        }
+        if let Some(filter) = &permalink.filter {
+            *self.renderer.filter_mut() = filter.clone();
+        }
+    }
+
+    /// Follows the page URL when edited, and otherwise keeps it up to date
+    fn sync_permalink(&mut self) {
+        let Some(hash) = platform::url_hash() else {
+            return;
+        };
+        if hash != self.permalink {
+            match Permalink::from_hash(&hash) {
+                Ok(permalink) => match permalink.dataset.clone().filter(|dataset| *dataset != self.dataset) {
+                    Some(dataset) => {
+                        self.load(&dataset);
+                        self.after_load = Some(permalink);
+                    }
+                    None => self.apply_permalink(&permalink),
+                },
+                Err(e) => log::warn!("Ignoring the link: {e}"),
+            }
+            self.permalink = hash;
+            return;
+        }
+        let hash = self.permalink().to_hash();
+        if hash != self.permalink {
+            platform::set_url_hash(&hash);
+            self.permalink = hash;
+        }
+    }
+
+    /// Picks at `position`, returns whether the pick started. One asked for
+    /// while another is in flight is started once that one is done.
+    fn request_pick(&mut self, purpose: PickPurpose, position: PhysicalPosition<f64>) -> bool {
+        let started = self.renderer.request_pick(position.x as u32, position.y as u32);
+        if started {
+            self.pick_purpose = purpose;
+            if purpose == PickPurpose::ZoomAnchor {
+                self.input_server.set_zoom_anchor(ZoomAnchor::Pending);
+                self.zoom_anchor_at = Some((platform::now(), position));
+            }
+        } else if self.renderer.is_picking() {
+            // A zoom anchor never takes the place of a click waiting its turn
+            let click_queued = self.queued_pick.is_some_and(|(queued, _)| queued != PickPurpose::ZoomAnchor);
+            if purpose != PickPurpose::ZoomAnchor || !click_queued {
+                self.queued_pick = Some((purpose, position));
+            }
+        }
+        started
+    }
+
+    /// Makes scroll zoom head for the point under the cursor, picked again
+    /// once the cursor moved or scrolling paused
+    fn anchor_zoom(&mut self) {
+        let now = platform::now();
+        let same_gesture = self.zoom_anchor_at
+            .is_some_and(|(time, cursor)| now - time < SCROLL_PAUSE && cursor == self.cursor);
+        if same_gesture {
+            self.zoom_anchor_at = self.zoom_anchor_at.map(|(_, cursor)| (now, cursor));
+        } else if !self.request_pick(PickPurpose::ZoomAnchor, self.cursor) {
+            // Heads for the target rather than the last anchor until the
+            // pick of the new one gets its turn
+            self.input_server.set_zoom_anchor(ZoomAnchor::Target);
+            self.zoom_anchor_at = None;
+        }
+    }
+
+    /// Adds the picked point to the measurement in progress, or describes it
+    fn show_picked(&mut self, picked: Option<&PickedPoint>) {
+        match picked {
+            Some(picked) if self.measurements.add_point(picked.position) => self.measurements_changed(),
+            Some(picked) => platform::set_overlay(&self.window, &picked.to_string()),
+            None => platform::set_overlay(&self.window, "No point there"),
+        }
+    }
+
+    /// Filters cycled through with G
+    fn filter_preset(index: usize) -> (&'static str, FilterSettings) {
+        match index {
+            1 => ("all but vegetation", FilterSettings::hide_vegetation()),
+            2 => ("ground only", FilterSettings::ground()),
+            3 => ("last returns", FilterSettings::returns(ReturnFilter::Last)),
+            4 => ("first returns", FilterSettings::returns(ReturnFilter::First)),
+            _ => ("all points", FilterSettings::default()),
+        }
+    }
+
+    /// Switches projection on the next update, what is at the camera target
+    /// keeps its size on screen
+    pub fn set_orthographic(&mut self, orthographic: bool) {
+        self.input_server.set_orthographic(orthographic);
+    }
+
+    /// Frames the new pointcloud from `url` and scales first person movement
+    /// to it. What was made on another dataset is dropped, it would be out
+    /// of place.
+    fn pointcloud_loaded(&mut self, url: String) {
+        if url != self.dataset {
+            self.dataset = url;
+            self.measurements.clear();
+            self.picked = None;
+            self.queued_pick = None;
+            let clipping = self.renderer.clipping_mut();
+            (clipping.boxes, clipping.planes) = (Vec::new(), Vec::new());
+            self.gizmos.selected = None;
+            self.profile = None;
+            self.profile_drawing = false;
+            self.profile_points.clear();
+            self.renderer.set_profile(&[]);
+            self.stop_path();
+            self.camera_path.clear();
+            self.update_lines();
+        }
+        self.fit_to_extent();
+        if let Some(pointcloud) = self.renderer.pointcloud() {
+            let meter = pointcloud.transform().scale.z as f32;
+            let ground = Ground::from_pointcloud(pointcloud);
+            self.input_server.set_scene(ground, self.scene_extent().max_element(), meter);
+        }
+    }
+
+    /// Orbits, flies or walks, from where the camera is
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.input_server.set_mode(mode);
+    }
+
+    /// Looks at the scene from `preset`, keeping the distance to the target
+    pub fn set_view(&mut self, preset: ViewPreset) {
+        self.input_server.set_view(preset);
+    }
+
+    /// Centres the camera on the pointcloud and backs off until all of it is
+    /// in view, done after each load
+    pub fn fit_to_extent(&mut self) {
+        let (min, max) = self.renderer.pointcloud().map_or((Vec3::splat(-0.5), Vec3::splat(0.5)), Pointcloud::bounds);
+        self.input_server.fit(&mut self.camera, min, max);
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn filter_mut(&mut self) -> &mut FilterSettings {
+        self.renderer.filter_mut()
+    }
+
+    pub fn measurements(&self) -> &Measurements {
+        &self.measurements
+    }
+
+    pub fn measurements_mut(&mut self) -> &mut Measurements {
+        &mut self.measurements
+    }
+
+    pub fn clipping_mut(&mut self) -> &mut ClipSettings {
+        self.renderer.clipping_mut()
+    }
+
+    fn scene_extent(&self) -> Vec3 {
+        self.renderer.pointcloud().map_or(Vec3::ONE, |pointcloud| {
+            let (min, max) = pointcloud.bounds();
+            max - min
+        })
+    }
+
+    /// Redraws measurements, clip gizmos and the camera path, to call after
+    /// changing them
+    pub fn update_lines(&mut self) {
+        let mut vertices = self.gizmos.lines(self.renderer.clipping(), self.scene_extent().max_element());
+        if let Some(transform) = self.renderer.pointcloud().map(|pointcloud| *pointcloud.transform()) {
+            vertices.extend(self.measurements
+                .iter()
+                .flat_map(|measurement| measurement.segments())
+                .flat_map(|(start, end)| [start, end])
+                .map(|point| LineVertex {
+                    position: transform.to_local(point).to_array(),
+                    color: MEASURE_COLOR,
+                }));
+            vertices.extend(self.profile
+                .iter()
+                .flat_map(|profile| profile.outline())
+                .flat_map(|(start, end)| [start, end])
+                .map(|point| LineVertex {
+                    position: transform.to_local(point).to_array(),
+                    color: PROFILE_COLOR,
+                }));
+        }
+        // Hidden while playing, as the camera is on it
+        if self.path_preview && self.playback.is_none() {
+            let tick = self.scene_extent().max_element() * KEYFRAME_TICK;
+            let curve = self.camera_path.polyline();
+            let ticks = self.camera_path.keyframes()
+                .iter()
+                .flat_map(|keyframe| [keyframe.eye, keyframe.eye + keyframe.direction() * tick]);
+            vertices.extend(curve
+                .windows(2)
+                .flat_map(|pair| [pair[0], pair[1]])
+                .chain(ticks)
+                .map(|point| LineVertex {
+                    position: point.to_array(),
+                    color: PATH_COLOR,
+                }));
+        }
+        self.renderer.set_lines(&vertices);
+    }
+
+    /// Redraws the lines and the overlay, to call after changing `measurements_mut`
+    pub fn measurements_changed(&mut self) {
+        self.update_lines();
+
+        let mut text = match self.measurements.tool() {
+            Some(tool) => format!("Measuring {tool}: click points, Enter to finish, Esc to cancel"),
+            None => "M to measure, X to export".to_owned(),
+        };
+        for measurement in self.measurements.iter() {
+            text += &format!("\n{measurement}");
+        }
+        platform::set_overlay(&self.window, &text);
+    }
+
+    /// Saves the finished measurements as JSON and CSV, in source coordinates
+    pub fn export_measurements(&self) {
+        match self.measurements.to_json() {
+            Ok(json) => platform::save_file("measurements.json", json.as_bytes()),
+            Err(e) => log::error!("Couldn't serialize measurements: {}", e),
+        }
+        platform::save_file("measurements.csv", self.measurements.to_csv().as_bytes());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Points of the last extracted profile, sorted along its path
+    pub fn profile_points(&self) -> &[ProfilePoint] {
+        &self.profile_points
+    }
+
+    /// Starts a new profile, picked points make up its path until `extract_profile`
+    pub fn start_profile(&mut self) {
+        let width = self.renderer.pointcloud().map_or(1., |pointcloud| {
+            let (min, max) = pointcloud.bounds();
+            let transform = pointcloud.transform();
+            (transform.to_source(max) - transform.to_source(min)).truncate().max_element() * PROFILE_WIDTH
+        });
+        self.profile = Some(Profile::new(width));
+        self.profile_drawing = true;
+        self.profile_points.clear();
+        self.renderer.set_profile(&[]);
+        self.profile_changed();
+    }
+
+    /// Extracts the points in the corridor of the current profile and shows them in the inset
+    pub fn extract_profile(&mut self) {
+        let (Some(profile), Some(pointcloud)) = (&self.profile, self.renderer.pointcloud()) else {
+            return;
+        };
+        if profile.path.len() < 2 {
+            platform::set_overlay(&self.window, "A profile needs at least 2 points");
+            return;
+        }
+
+        self.profile_points = profile.extract(pointcloud);
+        self.profile_drawing = false;
+        self.renderer.set_profile(&self.profile_points);
+        self.profile_changed();
+    }
+
+    pub fn close_profile(&mut self) {
+        self.profile = None;
+        self.profile_drawing = false;
+        self.profile_points.clear();
+        self.renderer.set_profile(&[]);
+        self.update_lines();
+        platform::set_overlay(&self.window, "K to draw a profile");
+    }
+
+    fn profile_changed(&mut self) {
+        self.update_lines();
+
+        let Some(profile) = &self.profile else {
+            return;
+        };
+        let text = if self.profile_drawing {
+            format!(
+                "Profile: click points along the path, Enter to extract, Esc to cancel\n{} points, width {:.2} m",
+                profile.path.len(),
+                profile.width,
+            )
+        } else {
+            format!(
+                "Profile: {} points over {:.2} m, width {:.2} m\nShift+[/] to change the width, X to export, K to close",
+                self.profile_points.len(),
+                profile.length(),
+                profile.width,
+            )
+        };
+        platform::set_overlay(&self.window, &text);
+    }
+
+    /// Saves the extracted profile as CSV and LAS, in source coordinates
+    pub fn export_profile(&self) {
+        if self.profile_points.is_empty() {
+            return;
+        }
+        platform::save_file("profile.csv", profile::to_csv(&self.profile_points).as_bytes());
+        match profile::to_las(&self.profile_points) {
+            Ok(las) => platform::save_file("profile.las", &las),
+            Err(e) => log::error!("Couldn't write profile: {}", e),
+        }
+    }
+
+    fn update_labels(&mut self) {
+        let Some(transform) = self.renderer.pointcloud().map(|pointcloud| *pointcloud.transform()) else {
+            return;
+        };
+        let view_projection = self.camera.build_view_projection_matrix();
+        let (width, height) = self.renderer.size();
+        let labels: Vec<_> = self.measurements
+            .iter()
+            .filter_map(|measurement| {
+                let clip = view_projection * transform.to_local(measurement.label_anchor()?).extend(1.);
+                if clip.w <= 0. {
+                    return None; // behind the camera
+                }
+                let ndc = clip.xy() / clip.w;
+                Some((
+                    (ndc.x + 1.) * 0.5 * width as f32,
+                    (1. - ndc.y) * 0.5 * height as f32,
+                    measurement.to_string(),
+                ))
+            })
+            .collect();
+        // Rebuilding the page's labels every frame is wasted layout work
+        if labels != self.labels {
+            platform::set_labels(&self.window, &labels);
+            self.labels = labels;
+        }
+    }
+
+    /// Picks the point under pixel `(x, y)`, the result shows up in `picked`
+    /// after a later `update`
+    pub fn pick(&mut self, x: u32, y: u32) -> bool {
+        self.request_pick(PickPurpose::Select, PhysicalPosition::new(x as f64, y as f64))
+    }
+
+    /// Last picked point, `None` if the last pick missed
+    pub fn picked(&self) -> Option<&PickedPoint> {
+        self.picked.as_ref()
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.renderer.color_mode()
+    }
+
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.renderer.set_color_mode(mode);
+    }
+
+    pub fn edl_mut(&mut self) -> &mut EdlSettings {
+        self.renderer.edl_mut()
+    }
+
+    pub fn ssao_mut(&mut self) -> &mut SsaoSettings {
+        self.renderer.ssao_mut()
+    }
+
+    pub fn point_size_mut(&mut self) -> &mut PointSize {
+        self.renderer.point_size_mut()
+    }
+
+    pub fn splats_mut(&mut self) -> &mut SplatSettings {
+        self.renderer.splats_mut()
     }
 
     pub fn render(&self) -> Result<(), wgpu::SurfaceError> {
@@ -210,46 +1234,7 @@ impl Engine {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.,
-                            g: 0.5,
-                            b: 0.5,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            self.pointcloud_pipeline.record_command(&mut render_pass);
-            self.uniform.record_command(&mut render_pass);
-            self.pointcloud.record_command(&mut render_pass);
-        }
-
-        self.queue.submit(iter::once(encoder.finish()));
+        self.renderer.render(&view);
         output.present();
 
         Ok(())
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pointcloud::{SourcePoint, SourceTransform};
+
+// ASPRS classes
+pub(crate) const GROUND: u8 = 2;
+const VEGETATION: [u8; 3] = [3, 4, 5]; // low, medium and high
+
+/// Set of the 256 classification values
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<u8>", into = "Vec<u8>")]
+pub struct ClassificationSet([u32; 8]);
+
+impl Default for ClassificationSet {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl ClassificationSet {
+    pub const ALL: Self = Self([u32::MAX; 8]);
+    pub const NONE: Self = Self([0; 8]);
+
+    pub fn contains(&self, class: u8) -> bool {
+        self.0[class as usize / 32] & 1 << (class % 32) != 0
+    }
+
+    pub fn insert(&mut self, class: u8) {
+        self.0[class as usize / 32] |= 1 << (class % 32);
+    }
+
+    pub fn remove(&mut self, class: u8) {
+        self.0[class as usize / 32] &= !(1 << (class % 32));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(|class| self.contains(*class))
+    }
+}
+
+impl FromIterator<u8> for ClassificationSet {
+    fn from_iter<T: IntoIterator<Item = u8>>(classes: T) -> Self {
+        let mut set = Self::NONE;
+        for class in classes {
+            set.insert(class);
+        }
+        set
+    }
+}
+
+impl From<Vec<u8>> for ClassificationSet {
+    fn from(classes: Vec<u8>) -> Self {
+        classes.into_iter().collect()
+    }
+}
+
+impl From<ClassificationSet> for Vec<u8> {
+    fn from(set: ClassificationSet) -> Self {
+        set.iter().collect()
+    }
+}
+
+/// Which returns of each pulse are kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnFilter {
+    #[default]
+    All,
+    First,
+    Last,
+    Intermediate, // neither first nor last
+    Single, // pulses with a single return
+}
+
+impl ReturnFilter {
+    pub fn accepts(self, return_number: u8, number_of_returns: u8) -> bool {
+        match self {
+            ReturnFilter::All => true,
+            ReturnFilter::First => return_number == 1,
+            ReturnFilter::Last => return_number == number_of_returns,
+            ReturnFilter::Intermediate => return_number > 1 && return_number < number_of_returns,
+            ReturnFilter::Single => number_of_returns == 1,
+        }
+    }
+}
+
+/// Points hidden by their attributes. Ranges are inclusive and in source
+/// units, `None` lets everything through.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    pub classifications: ClassificationSet,
+    pub returns: ReturnFilter,
+    pub number_of_returns: Option<(u8, u8)>,
+    pub intensity: Option<(f32, f32)>,
+    pub elevation: Option<(f64, f64)>,
+    pub gps_time: Option<(f64, f64)>, // only applies to points that have one
+}
+
+fn in_range<T: PartialOrd>(value: T, range: Option<(T, T)>) -> bool {
+    range.is_none_or(|(low, high)| low <= value && value <= high)
+}
+
+impl FilterSettings {
+    pub fn hide_vegetation() -> Self {
+        let mut classifications = ClassificationSet::ALL;
+        for class in VEGETATION {
+            classifications.remove(class);
+        }
+        Self { classifications, ..Default::default() }
+    }
+
+    pub fn ground() -> Self {
+        Self { classifications: [GROUND].into_iter().collect(), ..Default::default() }
+    }
+
+    pub fn returns(returns: ReturnFilter) -> Self {
+        Self { returns, ..Default::default() }
+    }
+
+    /// Whether `point` is drawn, the same test as the shaders
+    pub fn accepts(&self, point: &SourcePoint) -> bool {
+        self.classifications.contains(point.classification)
+            && self.returns.accepts(point.return_number, point.number_of_returns)
+            && in_range(point.number_of_returns, self.number_of_returns)
+            && in_range(point.intensity as f32, self.intensity)
+            && in_range(point.position.z, self.elevation)
+            && point.gps_time.is_none_or(|time| in_range(time, self.gps_time))
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FilterUniformData {
+    classifications: [u32; 8],
+    intensity: [f32; 2],
+    elevation: [f32; 2], // scene coordinates
+    gps_time: [f32; 2], // relative to the cloud's gps origin
+    number_of_returns: [u32; 2],
+    returns: u32,
+    _padding: [u32; 3],
+}
+
+impl FilterUniformData {
+    /// Ranges are converted to what the shaders see: `transform` maps
+    /// elevations, gps times are relative to `gps_origin`
+    pub fn new(settings: &FilterSettings, transform: &SourceTransform, gps_origin: f64) -> Self {
+        let range = |range: Option<(f32, f32)>| range.map_or([f32::MIN, f32::MAX], |(low, high)| [low, high]);
+        let to_local = |z: f64| ((z - transform.offset.z) * transform.scale.z) as f32;
+
+        Self {
+            classifications: settings.classifications.0,
+            intensity: range(settings.intensity),
+            elevation: range(settings.elevation.map(|(low, high)| (to_local(low), to_local(high)))),
+            gps_time: range(settings.gps_time.map(|(low, high)| ((low - gps_origin) as f32, (high - gps_origin) as f32))),
+            number_of_returns: settings.number_of_returns.map_or([0, u8::MAX as u32], |(low, high)| [low as u32, high as u32]),
+            returns: settings.returns as u32,
+            _padding: [0; 3],
+        }
+    }
+}
+
+impl Default for FilterUniformData {
+    fn default() -> Self {
+        Self::new(&FilterSettings::default(), &SourceTransform::default(), 0.)
+    }
+}
@@ -0,0 +1,229 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use winit::event::{ElementState, MouseScrollDelta};
+
+use crate::controls::Action;
+
+use super::{gamepad::Axes, input::Input, motion::Inertia, Camera, Ground};
+
+// Speed multipliers while Shift or Ctrl is held
+const FAST: f32 = 4.;
+const SLOW: f32 = 0.25;
+// Speed multiplier per scroll line
+const SPEED_STEP: f32 = 1.25;
+// Keeps the view direction away from the up axis
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+// Meters per second
+const WALK_SPEED: f32 = 2.;
+// Per second, how quickly movement reaches its speed and stops
+const MOVE_RATE: f32 = 8.;
+// Meters above the ground
+const EYE_HEIGHT: f32 = 1.7;
+
+/// Free flight: the mouse looks around, the move actions (WASD by default) go
+/// along the view or up and down, faster and slower with Shift and Ctrl,
+/// scrolling changes the speed
+pub struct FlyController {
+    pub speed: f32, // scene units per second
+    pub angular_speed: f32, // rads per pixel
+    position: Vec3,
+    yaw: f32, // around Z, 0 looks towards +X
+    pitch: f32, // above the horizon
+    focus: f32, // distance to the camera target, which sets the scale for measuring on screen
+    inertia: Inertia<Vec3>,
+    axes: Axes,
+    forward_pressed: bool,
+    backward_pressed: bool,
+    left_pressed: bool,
+    right_pressed: bool,
+    up_pressed: bool,
+    down_pressed: bool,
+    fast_pressed: bool,
+    slow_pressed: bool,
+}
+
+impl FlyController {
+    pub const fn new(speed: f32, angular_speed: f32) -> Self {
+        Self {
+            speed,
+            angular_speed,
+            position: Vec3::ZERO,
+            yaw: 0.,
+            pitch: 0.,
+            focus: 1.,
+            inertia: Inertia::new(Vec3::ZERO, MOVE_RATE),
+            axes: Axes { movement: Vec2::ZERO, look: Vec2::ZERO, lift: 0. },
+            forward_pressed: false,
+            backward_pressed: false,
+            left_pressed: false,
+            right_pressed: false,
+            up_pressed: false,
+            down_pressed: false,
+            fast_pressed: false,
+            slow_pressed: false,
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        Vec3::new(pitch_cos * yaw_cos, pitch_cos * yaw_sin, pitch_sin)
+    }
+
+    /// Velocity the keys and sticks ask for, kept level when `horizontal`
+    fn velocity(&self, horizontal: bool) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let forward = if horizontal { Vec3::new(yaw_cos, yaw_sin, 0.) } else { self.direction() };
+        let right = Vec3::new(yaw_sin, -yaw_cos, 0.);
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+
+        let mut movement = forward * axis(self.forward_pressed, self.backward_pressed)
+            + right * axis(self.right_pressed, self.left_pressed);
+        let mut stick = forward * self.axes.movement.y + right * self.axes.movement.x;
+        if !horizontal {
+            movement += Vec3::Z * axis(self.up_pressed, self.down_pressed);
+            stick += Vec3::Z * self.axes.lift;
+        }
+        let modifier = if self.fast_pressed { FAST } else if self.slow_pressed { SLOW } else { 1. };
+
+        (movement.normalize_or_zero() + stick).clamp_length_max(1.) * self.speed * modifier
+    }
+
+    /// Turns as the right stick asks for over `dt` seconds
+    fn look(&mut self, dt: f32) {
+        let turn = self.axes.look * dt;
+        self.yaw -= turn.x;
+        self.pitch = (self.pitch + turn.y).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.position;
+        camera.target = self.position + self.direction() * self.focus;
+        camera.up = Vec3::Z;
+    }
+}
+
+impl Input for FlyController {
+    fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        let is_pressed = state == ElementState::Pressed;
+        let pressed = match action {
+            Action::MoveForward => &mut self.forward_pressed,
+            Action::MoveBackward => &mut self.backward_pressed,
+            Action::MoveLeft => &mut self.left_pressed,
+            Action::MoveRight => &mut self.right_pressed,
+            Action::MoveUp => &mut self.up_pressed,
+            Action::MoveDown => &mut self.down_pressed,
+            Action::Faster => &mut self.fast_pressed,
+            Action::Slower => &mut self.slow_pressed,
+            _ => return false,
+        };
+        *pressed = is_pressed;
+        true
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.yaw -= mouse_dx as f32 * self.angular_speed;
+        self.pitch = (self.pitch - mouse_dy as f32 * self.angular_speed).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        let lines = match *delta {
+            MouseScrollDelta::LineDelta(_, dy) => dy,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 * 0.1,
+        };
+        self.speed *= SPEED_STEP.powf(lines);
+    }
+
+    fn process_axes(&mut self, axes: Axes) {
+        self.axes = axes;
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        self.look(dt);
+        self.position += self.inertia.step(self.velocity(false), dt);
+        self.apply(camera);
+    }
+
+    fn attach(&mut self, camera: &Camera) {
+        let direction = (camera.target - camera.eye).normalize_or(Vec3::X);
+        self.position = camera.eye;
+        self.yaw = direction.y.atan2(direction.x);
+        self.pitch = direction.z.clamp(-1., 1.).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        self.focus = camera.eye.distance(camera.target).max(f32::EPSILON);
+        self.inertia.velocity = Vec3::ZERO;
+        self.axes = Axes::default();
+    }
+}
+
+/// Walks at eye height over the ground, moving level whatever the pitch. Keys
+/// and mouse as when flying, without up and down.
+pub struct WalkController {
+    fly: FlyController,
+    ground: Option<Ground>,
+    eye_height: f32, // scene units
+}
+
+impl WalkController {
+    pub const fn new(speed: f32, angular_speed: f32, eye_height: f32) -> Self {
+        Self {
+            fly: FlyController::new(speed, angular_speed),
+            ground: None,
+            eye_height,
+        }
+    }
+
+    /// Walks on `ground`, in a scene where a meter is `meter` units
+    pub fn set_ground(&mut self, ground: Ground, meter: f32) {
+        self.ground = Some(ground);
+        self.fly.speed = WALK_SPEED * meter;
+        self.eye_height = EYE_HEIGHT * meter;
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.fly.position
+    }
+
+    /// Puts the eye above the ground, keeps its elevation away from the cloud
+    fn stand(&mut self) {
+        let position = &mut self.fly.position;
+        if let Some(height) = self.ground.as_ref().and_then(|ground| ground.height(position.xy())) {
+            position.z = height + self.eye_height;
+        }
+    }
+}
+
+impl Input for WalkController {
+    fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        self.fly.process_action(action, state)
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.fly.process_mouse(mouse_dx, mouse_dy);
+    }
+
+    fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        self.fly.process_scroll(delta);
+    }
+
+    fn process_axes(&mut self, axes: Axes) {
+        self.fly.process_axes(axes);
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        self.fly.look(dt);
+        let velocity = self.fly.velocity(true);
+        self.fly.position += self.fly.inertia.step(velocity, dt);
+        self.stand();
+        self.fly.apply(camera);
+    }
+
+    fn attach(&mut self, camera: &Camera) {
+        self.fly.attach(camera);
+        self.stand();
+    }
+}
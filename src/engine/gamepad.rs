@@ -0,0 +1,333 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Vec2, Vec3};
+use winit::event::ElementState;
+
+/// Buttons of a standard gamepad, named by where they sit: South is A on an
+/// Xbox pad and cross on a PlayStation one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 14] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// Where the sticks and triggers of a gamepad are, sticks from -1 to 1 with
+/// up and right positive, triggers from 0 to 1
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    pub left_stick: Vec2,
+    pub right_stick: Vec2,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub buttons: Vec<GamepadButton>, // held down
+}
+
+/// How far the cap of a 3D mouse is pushed and twisted, -1 to 1 along and
+/// around the axes HID reports it on: x right, y towards the user, z down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpaceMouseState {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+}
+
+// 3Dconnexion's USB vendor, older 3D mice have Logitech's and are told by name
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+const SPACE_MOUSE_VENDOR: u16 = 0x256f;
+
+/// Whether a device named `name` by the OS or browser is a 3D mouse rather
+/// than a gamepad
+pub fn is_space_mouse(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["3dconnexion", "spacemouse", "spacenavigator", "space navigator", "spacepilot", "spaceexplorer", "256f"]
+        .iter()
+        .any(|model| name.contains(model))
+}
+
+/// Analogue input for the camera controllers, held until the next one
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Axes {
+    pub movement: Vec2, // fraction of the controller's speed, right and forward
+    pub look: Vec2, // rads per second, right and up
+    pub lift: f32, // fraction of the controller's speed up, zooms in when orbiting
+}
+
+impl std::ops::Add for Axes {
+    type Output = Axes;
+
+    fn add(self, other: Axes) -> Axes {
+        Axes {
+            movement: self.movement + other.movement,
+            look: self.look + other.look,
+            lift: self.lift + other.lift,
+        }
+    }
+}
+
+/// How sticks, triggers and 3D mice turn into `Axes`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadSettings {
+    pub dead_zone: f32, // deflection ignored around rest, 0 to 1
+    pub exponent: f32, // 1 responds linearly, higher gives finer control near the centre
+    pub look_speed: f32, // rads per second with the right stick all the way
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            exponent: 2.,
+            look_speed: FRAC_PI_2,
+        }
+    }
+}
+
+impl GamepadSettings {
+    /// Left stick moves, right stick looks, right and left triggers go up and down
+    pub fn axes(&self, state: &GamepadState) -> Axes {
+        Axes {
+            movement: self.stick(state.left_stick),
+            look: self.stick(state.right_stick) * self.look_speed,
+            lift: self.curve(state.right_trigger) - self.curve(state.left_trigger),
+        }
+    }
+
+    /// Pushing the cap moves and lifting it goes up, twisting it clockwise
+    /// turns right and tilting it back looks up
+    pub fn space_mouse_axes(&self, state: &SpaceMouseState) -> Axes {
+        let (translation, rotation) = (state.translation, state.rotation);
+        Axes {
+            movement: self.stick(Vec2::new(translation.x, -translation.y)),
+            look: Vec2::new(self.curve(rotation.z), self.curve(rotation.x)) * self.look_speed,
+            lift: self.curve(-translation.z),
+        }
+    }
+
+    /// Deflection past the dead zone rescaled to 0 to 1 and bent by the exponent
+    pub fn curve(&self, value: f32) -> f32 {
+        let live = ((value.abs() - self.dead_zone) / (1. - self.dead_zone).max(f32::EPSILON)).clamp(0., 1.);
+        live.powf(self.exponent).copysign(value)
+    }
+
+    /// As `curve` along the direction of the stick, so diagonals aren't favoured
+    pub fn stick(&self, value: Vec2) -> Vec2 {
+        let length = value.length();
+        if length == 0. {
+            return Vec2::ZERO;
+        }
+        value / length * self.curve(length.min(1.))
+    }
+}
+
+/// The first gamepad and the first 3D mouse connected. Natively it needs the
+/// `gamepad` feature, the web reads the browser's Gamepad API, which lists 3D
+/// mice along with gamepads.
+pub struct Gamepads {
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    gilrs: Option<gilrs::Gilrs>,
+    pressed: Vec<GamepadButton>,
+    space_mouse: SpaceMouseState,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+            gilrs: gilrs::Gilrs::new()
+                .inspect_err(|e| log::warn!("No gamepads: {e}"))
+                .ok(),
+            pressed: Vec::new(),
+            space_mouse: SpaceMouseState::default(),
+        }
+    }
+
+    /// Where everything is now, at rest without a gamepad, and the buttons
+    /// pressed or released since the last poll
+    pub fn poll(&mut self) -> (GamepadState, Vec<(GamepadButton, ElementState)>) {
+        let (state, space_mouse) = self.read();
+        let state = state.unwrap_or_default();
+        self.space_mouse = space_mouse.unwrap_or_default();
+        let released = self.pressed.iter()
+            .filter(|button| !state.buttons.contains(button))
+            .map(|button| (*button, ElementState::Released));
+        let pressed = state.buttons.iter()
+            .filter(|button| !self.pressed.contains(button))
+            .map(|button| (*button, ElementState::Pressed));
+        let changes = released.chain(pressed).collect();
+        self.pressed.clone_from(&state.buttons);
+
+        (state, changes)
+    }
+
+    /// As `poll` with everything let go, for when the input goes to another
+    /// viewer
+    pub fn release(&mut self) -> (GamepadState, Vec<(GamepadButton, ElementState)>) {
+        self.space_mouse = SpaceMouseState::default();
+        let changes = self.pressed.drain(..).map(|button| (button, ElementState::Released)).collect();
+        (GamepadState::default(), changes)
+    }
+
+    /// The 3D mouse as of the last `poll`, at rest without one. Its buttons
+    /// are left alone.
+    pub fn space_mouse(&self) -> SpaceMouseState {
+        self.space_mouse
+    }
+
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    fn read(&mut self) -> (Option<GamepadState>, Option<SpaceMouseState>) {
+        use gilrs::Axis;
+
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return (None, None);
+        };
+        while gilrs.next_event().is_some() {}
+        let (space_mice, mut gamepads): (Vec<_>, Vec<_>) = gilrs.gamepads()
+            .map(|(_, gamepad)| gamepad)
+            .partition(|gamepad| gamepad.vendor_id() == Some(SPACE_MOUSE_VENDOR) || is_space_mouse(gamepad.name()));
+        // Without a known mapping the six axes come through as both sticks and Z
+        let space_mouse = space_mice.first().map(|mouse| SpaceMouseState {
+            translation: Vec3::new(mouse.value(Axis::LeftStickX), mouse.value(Axis::LeftStickY), mouse.value(Axis::LeftZ)),
+            rotation: Vec3::new(mouse.value(Axis::RightStickX), mouse.value(Axis::RightStickY), mouse.value(Axis::RightZ)),
+        });
+        let gamepad = (!gamepads.is_empty()).then(|| Self::read_gamepad(gamepads.swap_remove(0)));
+
+        (gamepad, space_mouse)
+    }
+
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    fn read_gamepad(gamepad: gilrs::Gamepad) -> GamepadState {
+        use gilrs::{Axis, Button};
+
+        let trigger = |button| gamepad.button_data(button).map_or(0., |data| data.value());
+        let buttons = GamepadButton::ALL.into_iter()
+            .filter(|button| gamepad.is_pressed(match button {
+                GamepadButton::South => Button::South,
+                GamepadButton::East => Button::East,
+                GamepadButton::West => Button::West,
+                GamepadButton::North => Button::North,
+                GamepadButton::LeftShoulder => Button::LeftTrigger,
+                GamepadButton::RightShoulder => Button::RightTrigger,
+                GamepadButton::Select => Button::Select,
+                GamepadButton::Start => Button::Start,
+                GamepadButton::LeftStick => Button::LeftThumb,
+                GamepadButton::RightStick => Button::RightThumb,
+                GamepadButton::DPadUp => Button::DPadUp,
+                GamepadButton::DPadDown => Button::DPadDown,
+                GamepadButton::DPadLeft => Button::DPadLeft,
+                GamepadButton::DPadRight => Button::DPadRight,
+            }))
+            .collect();
+
+        GamepadState {
+            left_stick: Vec2::new(gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY)),
+            right_stick: Vec2::new(gamepad.value(Axis::RightStickX), gamepad.value(Axis::RightStickY)),
+            left_trigger: trigger(Button::LeftTrigger2),
+            right_trigger: trigger(Button::RightTrigger2),
+            buttons,
+        }
+    }
+
+    #[cfg(all(not(feature = "gamepad"), not(target_arch = "wasm32")))]
+    fn read(&mut self) -> (Option<GamepadState>, Option<SpaceMouseState>) {
+        (None, None)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read(&mut self) -> (Option<GamepadState>, Option<SpaceMouseState>) {
+        use wasm_bindgen::JsCast;
+
+        let Some(gamepads) = web_sys::window().and_then(|window| window.navigator().get_gamepads().ok()) else {
+            return (None, None);
+        };
+        let (space_mice, gamepads): (Vec<_>, Vec<_>) = gamepads.iter()
+            .filter_map(|gamepad| gamepad.dyn_into::<web_sys::Gamepad>().ok())
+            .partition(|gamepad| is_space_mouse(&gamepad.id()));
+        // Its six axes in HID order, already scaled to -1 to 1
+        let space_mouse = space_mice.first().map(|mouse| {
+            let axes = mouse.axes();
+            let axis = |index| axes.get(index).as_f64().unwrap_or_default() as f32;
+            SpaceMouseState {
+                translation: Vec3::new(axis(0), axis(1), axis(2)),
+                rotation: Vec3::new(axis(3), axis(4), axis(5)),
+            }
+        });
+
+        (gamepads.first().map(Self::read_gamepad), space_mouse)
+    }
+
+    /// Reads the browser's standard mapping, where stick axes point down
+    #[cfg(target_arch = "wasm32")]
+    fn read_gamepad(gamepad: &web_sys::Gamepad) -> GamepadState {
+        use wasm_bindgen::JsCast;
+
+        let (axes, buttons) = (gamepad.axes(), gamepad.buttons());
+        let axis = |index| axes.get(index).as_f64().unwrap_or_default() as f32;
+        let button = |index| buttons.get(index).dyn_into::<web_sys::GamepadButton>().ok();
+        let pressed = GamepadButton::ALL.into_iter()
+            .filter(|pad_button| {
+                let index = match pad_button {
+                    GamepadButton::South => 0,
+                    GamepadButton::East => 1,
+                    GamepadButton::West => 2,
+                    GamepadButton::North => 3,
+                    GamepadButton::LeftShoulder => 4,
+                    GamepadButton::RightShoulder => 5,
+                    GamepadButton::Select => 8,
+                    GamepadButton::Start => 9,
+                    GamepadButton::LeftStick => 10,
+                    GamepadButton::RightStick => 11,
+                    GamepadButton::DPadUp => 12,
+                    GamepadButton::DPadDown => 13,
+                    GamepadButton::DPadLeft => 14,
+                    GamepadButton::DPadRight => 15,
+                };
+                button(index).is_some_and(|button| button.pressed())
+            })
+            .collect();
+        let trigger = |index| button(index).map_or(0., |button| button.value() as f32);
+
+        GamepadState {
+            left_stick: Vec2::new(axis(0), -axis(1)),
+            right_stick: Vec2::new(axis(2), -axis(3)),
+            left_trigger: trigger(6),
+            right_trigger: trigger(7),
+            buttons: pressed,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use glam::{Quat, Vec3};
+
+use super::{ClipBox, ClipPlane, ClipSettings, LineVertex};
+
+const GIZMO_COLOR: [f32; 4] = [0.2, 0.6, 1., 1.];
+const SELECTED_COLOR: [f32; 4] = [1., 1., 1., 1.];
+const ROTATION_STEP: f32 = std::f32::consts::PI / 36.;
+const SCALE_STEP: f32 = 1.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipHandle {
+    Box(usize),
+    Plane(usize),
+}
+
+/// Wireframes of the clip boxes and planes, and editing of the selected one
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClipGizmos {
+    pub selected: Option<ClipHandle>,
+}
+
+impl ClipGizmos {
+    /// `plane_size` is the side of the square drawn for planes
+    pub fn lines(&self, clipping: &ClipSettings, plane_size: f32) -> Vec<LineVertex> {
+        let color = |handle| if self.selected == Some(handle) { SELECTED_COLOR } else { GIZMO_COLOR };
+        let boxes = clipping.boxes.iter().enumerate().map(|(i, clip_box)| (clip_box.edges(), color(ClipHandle::Box(i))));
+        let planes = clipping.planes.iter().enumerate().map(|(i, plane)| (plane.outline(plane_size), color(ClipHandle::Plane(i))));
+
+        boxes
+            .chain(planes)
+            .flat_map(|(segments, color)| {
+                segments.into_iter().flat_map(move |(start, end)| [
+                    LineVertex { position: start.to_array(), color },
+                    LineVertex { position: end.to_array(), color },
+                ])
+            })
+            .collect()
+    }
+
+    pub fn add_box(&mut self, clipping: &mut ClipSettings, clip_box: ClipBox) {
+        clipping.boxes.push(clip_box);
+        self.selected = Some(ClipHandle::Box(clipping.boxes.len() - 1));
+    }
+
+    pub fn add_plane(&mut self, clipping: &mut ClipSettings, plane: ClipPlane) {
+        clipping.planes.push(plane);
+        self.selected = Some(ClipHandle::Plane(clipping.planes.len() - 1));
+    }
+
+    /// Boxes first, then planes, wrapping around
+    pub fn select_next(&mut self, clipping: &ClipSettings) {
+        let handles: Vec<_> = (0..clipping.boxes.len())
+            .map(ClipHandle::Box)
+            .chain((0..clipping.planes.len()).map(ClipHandle::Plane))
+            .collect();
+        self.selected = match self.selected.and_then(|selected| handles.iter().position(|h| *h == selected)) {
+            Some(i) => handles.get((i + 1) % handles.len()).copied(),
+            None => handles.first().copied(),
+        };
+    }
+
+    pub fn remove_selected(&mut self, clipping: &mut ClipSettings) {
+        match self.selected.take() {
+            Some(ClipHandle::Box(i)) if i < clipping.boxes.len() => {
+                clipping.boxes.remove(i);
+            }
+            Some(ClipHandle::Plane(i)) if i < clipping.planes.len() => {
+                clipping.planes.remove(i);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn translate(&self, clipping: &mut ClipSettings, offset: Vec3) {
+        match self.selected {
+            Some(ClipHandle::Box(i)) => if let Some(clip_box) = clipping.boxes.get_mut(i) {
+                clip_box.center += offset;
+            },
+            Some(ClipHandle::Plane(i)) => if let Some(plane) = clipping.planes.get_mut(i) {
+                plane.point += offset;
+            },
+            None => (),
+        }
+    }
+
+    /// Boxes turn around the vertical, planes tilt around `axis`
+    pub fn rotate(&self, clipping: &mut ClipSettings, steps: f32, axis: Vec3) {
+        match self.selected {
+            Some(ClipHandle::Box(i)) => if let Some(clip_box) = clipping.boxes.get_mut(i) {
+                clip_box.rotation = Quat::from_rotation_z(steps * ROTATION_STEP) * clip_box.rotation;
+            },
+            Some(ClipHandle::Plane(i)) => if let Some(plane) = clipping.planes.get_mut(i) {
+                plane.normal = Quat::from_axis_angle(axis.normalize_or(Vec3::X), steps * ROTATION_STEP) * plane.normal;
+            },
+            None => (),
+        }
+    }
+
+    /// Grows the selected box by `SCALE_STEP` per step, planes have no size
+    pub fn scale(&self, clipping: &mut ClipSettings, steps: i32) {
+        if let Some(ClipHandle::Box(i)) = self.selected {
+            if let Some(clip_box) = clipping.boxes.get_mut(i) {
+                clip_box.half_size *= SCALE_STEP.powi(steps);
+            }
+        }
+    }
+}
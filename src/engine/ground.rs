@@ -0,0 +1,72 @@
+use glam::{IVec2, Vec2, Vec3, Vec3Swizzles};
+
+use crate::pointcloud::{Pointcloud, POINTS_PER_CELL};
+
+use super::filter::GROUND;
+
+// Rings of cells searched around an empty one
+const SEARCH_RINGS: i32 = 3;
+
+/// Lowest elevation over a horizontal grid, in scene coordinates, what walking
+/// stands on
+#[derive(Clone, Debug)]
+pub struct Ground {
+    min: Vec2,
+    cell: f32,
+    size: IVec2, // cells along x and y
+    heights: Vec<f32>, // row by row, NaN where there are no points
+}
+
+impl Ground {
+    pub fn new(points: &[Vec3]) -> Self {
+        let (min, max) = points.iter().fold(
+            (Vec2::INFINITY, Vec2::NEG_INFINITY),
+            |(min, max), p| (min.min(p.xy()), max.max(p.xy())),
+        );
+        if points.is_empty() {
+            return Self { min: Vec2::ZERO, cell: 1., size: IVec2::ZERO, heights: Vec::new() };
+        }
+
+        let extent = (max - min).max(Vec2::splat(f32::EPSILON));
+        let cell = (extent.x * extent.y * POINTS_PER_CELL / points.len() as f32).sqrt().max(extent.max_element() * 1e-3);
+        let size = (extent / cell).floor().as_ivec2() + 1;
+        let mut ground = Self { min, cell, size, heights: vec![f32::NAN; (size.x * size.y) as usize] };
+        for p in points {
+            let index = ground.index(ground.key(p.xy())).unwrap();
+            ground.heights[index] = ground.heights[index].min(p.z); // min ignores NaN
+        }
+
+        ground
+    }
+
+    /// Ground classified points if the cloud has any, all of them otherwise
+    pub fn from_pointcloud(pointcloud: &Pointcloud) -> Self {
+        let ground: Vec<_> = pointcloud.source().iter()
+            .zip(pointcloud.points())
+            .filter_map(|(source, point)| (source.classification == GROUND).then_some(*point))
+            .collect();
+        Self::new(if ground.is_empty() { pointcloud.points() } else { &ground })
+    }
+
+    fn key(&self, xy: Vec2) -> IVec2 {
+        ((xy - self.min) / self.cell).floor().as_ivec2()
+    }
+
+    fn index(&self, key: IVec2) -> Option<usize> {
+        (key.cmpge(IVec2::ZERO).all() && key.cmplt(self.size).all()).then(|| (key.y * self.size.x + key.x) as usize)
+    }
+
+    /// Elevation of the ground at `xy`, the lowest point of the nearest cells
+    /// that have any. `None` away from the cloud.
+    pub fn height(&self, xy: Vec2) -> Option<f32> {
+        let center = self.key(xy);
+        (0..=SEARCH_RINGS).find_map(|ring| {
+            (-ring..=ring)
+                .flat_map(|y| (-ring..=ring).map(move |x| IVec2::new(x, y)))
+                .filter(|offset| offset.abs().max_element() == ring)
+                .filter_map(|offset| self.heights.get(self.index(center + offset)?).copied())
+                .filter(|height| !height.is_nan())
+                .reduce(f32::min)
+        })
+    }
+}
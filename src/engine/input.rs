@@ -1,70 +1,397 @@
-use glam::{Mat2, Vec2, Vec3, Vec3Swizzles};
-use winit::{event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent}, keyboard::{KeyCode, PhysicalKey}};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, PI};
 
-use super::Camera;
+use glam::{Mat2, Vec2, Vec2Swizzles, Vec3, Vec3Swizzles};
+use winit::{dpi::PhysicalPosition, event::{DeviceEvent, ElementState, MouseScrollDelta, Touch, TouchPhase, WindowEvent}};
+
+use crate::{bookmarks::CameraView, controls::Action, platform};
+
+use super::{gamepad::Axes, first_person::{FlyController, WalkController}, motion::{ease, Inertia}, Camera, Ground, Projection};
 
 pub trait Input {
-    fn process_keyboard(&mut self, _key: KeyCode, _state: ElementState) -> bool { false }
+    /// Held action pressed or released, returns whether it was used
+    fn process_action(&mut self, _action: Action, _state: ElementState) -> bool { false }
     fn process_mouse(&mut self, _mouse_dx: f64, _mouse_dy: f64) {}
     fn process_scroll(&mut self, _delta: &MouseScrollDelta) {}
-    //fn process_keyboard(&self);
+    /// Drag with the panning buttons, in pixels
+    fn process_pan(&mut self, _mouse_dx: f64, _mouse_dy: f64) {}
+    /// Pinch, `factor` times closer
+    fn process_zoom(&mut self, _factor: f32) {}
+    /// Sticks and triggers, held until the next call
+    fn process_axes(&mut self, _axes: Axes) {}
+    /// Moves `camera` according to what was input, `dt` seconds after the last update
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32);
+    /// Takes over `camera` from another controller, without moving it
+    fn attach(&mut self, _camera: &Camera) {}
+}
+
+/// Which controller drives the camera
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    #[default]
+    Orbit,
+    Fly,
+    Walk,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Orbit => CameraMode::Fly,
+            CameraMode::Fly => CameraMode::Walk,
+            CameraMode::Walk => CameraMode::Orbit,
+        }
+    }
+}
+
+/// Touch gestures for the engine to act on, where they happened on the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    Tap(PhysicalPosition<f64>),
+    DoubleTap(PhysicalPosition<f64>),
+    Pinch(PhysicalPosition<f64>), // second finger down, zooming about the centre
 }
+
+// Pixels a finger can move for it to still be a tap
+const TAP_TOLERANCE: f64 = 10.;
+// Seconds between the taps of a double tap
+const DOUBLE_TAP_TIME: f64 = 0.3;
+
 pub struct InputServer {
     camera_controller: CameraController,
+    fly_controller: FlyController,
+    walk_controller: WalkController,
+    mode: CameraMode,
+    next_mode: Option<CameraMode>, // applied on the next update
     mouse_pressed: bool,
+    pan_pressed: bool,
+    touches: Vec<(u64, PhysicalPosition<f64>)>, // fingers down, first to touch first
+    tap: Option<PhysicalPosition<f64>>, // where the only finger down touched, while it hasn't moved off
+    last_tap: Option<(f64, PhysicalPosition<f64>)>,
+    gesture: Option<Gesture>,
+    flight: Option<(CameraView, f32)>, // handed to the orbit controller on the next update
+}
+
+impl Default for InputServer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InputServer {
     pub const fn new() -> Self {
         Self { 
             mouse_pressed: false,
-            camera_controller: CameraController::new(0.05, 2., std::f32::consts::PI / 300., 0.1)
+            camera_controller: CameraController::new(3., 2., PI / 300., 0.1),
+            fly_controller: FlyController::new(3., PI / 600.),
+            walk_controller: WalkController::new(3., PI / 600., 1.7),
+            mode: CameraMode::Orbit,
+            next_mode: None,
+            pan_pressed: false,
+            touches: Vec::new(),
+            tap: None,
+            last_tap: None,
+            gesture: None,
+            flight: None,
+        }
+    }
+
+    fn controller(&mut self) -> &mut dyn Input {
+        match self.mode {
+            CameraMode::Orbit => &mut self.camera_controller,
+            CameraMode::Fly => &mut self.fly_controller,
+            CameraMode::Walk => &mut self.walk_controller,
         }
     }
+
     pub fn window_input(&mut self, event: &WindowEvent)-> bool {
         match event {
-            WindowEvent::KeyboardInput { event: KeyEvent {
-                physical_key: PhysicalKey::Code(key),
-                state,
-                ..
-            }, ..} => self.camera_controller.process_keyboard(*key, *state),
             WindowEvent::MouseWheel { delta, .. } => {
-                self.camera_controller.process_scroll(delta);
+                self.controller().process_scroll(delta);
+                true
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                self.controller().process_zoom(1. + *delta as f32);
                 true
             }
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state,
-                ..
-            } => {
-                self.mouse_pressed = *state == ElementState::Pressed;
+            WindowEvent::Touch(touch) => {
+                self.touch(touch);
                 true
             }
             _ => false
         }
     }
 
+    /// One finger orbits, two pan and pinch
+    fn touch(&mut self, touch: &Touch) {
+        let near = |a: PhysicalPosition<f64>, b: PhysicalPosition<f64>| (a.x - b.x).hypot(a.y - b.y) <= TAP_TOLERANCE;
+        let index = self.touches.iter().position(|(id, _)| *id == touch.id);
+        match (touch.phase, index) {
+            (TouchPhase::Started, None) => {
+                self.touches.push((touch.id, touch.location));
+                self.tap = (self.touches.len() == 1).then_some(touch.location);
+                if self.touches.len() == 2 {
+                    self.gesture = Some(Gesture::Pinch(centre(self.touches[0].1, self.touches[1].1)));
+                }
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                let before = self.touches.clone();
+                self.touches[index].1 = touch.location;
+                match *before.as_slice() {
+                    [(_, from)] => {
+                        self.tap = self.tap.filter(|start| near(*start, touch.location));
+                        self.controller().process_mouse(touch.location.x - from.x, touch.location.y - from.y);
+                    }
+                    [(_, a), (_, b), ..] if index < 2 => {
+                        let (from, to) = (centre(a, b), centre(self.touches[0].1, self.touches[1].1));
+                        let spread = |a: PhysicalPosition<f64>, b: PhysicalPosition<f64>| (a.x - b.x).hypot(a.y - b.y);
+                        let factor = spread(self.touches[0].1, self.touches[1].1) / spread(a, b).max(1.);
+                        self.controller().process_pan(to.x - from.x, to.y - from.y);
+                        self.controller().process_zoom(factor as f32);
+                    }
+                    _ => (),
+                }
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, Some(index)) => {
+                self.touches.remove(index);
+                if let Some(start) = self.tap.take().filter(|_| touch.phase == TouchPhase::Ended) {
+                    let now = platform::now();
+                    let double_tap = self.last_tap.take()
+                        .is_some_and(|(time, position)| now - time < DOUBLE_TAP_TIME && near(position, start));
+                    self.gesture = Some(if double_tap {
+                        Gesture::DoubleTap(start)
+                    } else {
+                        self.last_tap = Some((now, start));
+                        Gesture::Tap(start)
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Sticks and triggers of a gamepad for the controller in use
+    pub fn process_axes(&mut self, axes: Axes) {
+        self.controller().process_axes(axes);
+    }
+
+    /// Gesture made since the last call
+    pub fn take_gesture(&mut self) -> Option<Gesture> {
+        self.gesture.take()
+    }
+
+    /// Held action pressed or released, dragging with `Rotate` and `Pan`
+    pub fn process_action(&mut self, action: Action, state: ElementState) -> bool {
+        let is_pressed = state == ElementState::Pressed;
+        match action {
+            Action::Rotate => self.mouse_pressed = is_pressed,
+            Action::Pan => self.pan_pressed = is_pressed,
+            _ => return self.controller().process_action(action, state),
+        }
+        true
+    }
+
     pub fn device_input(&mut self, event: &DeviceEvent) {
-        match event {
-            DeviceEvent::MouseMotion { delta } => if self.mouse_pressed {
-                self.camera_controller.process_mouse(delta.0, delta.1);
-            },
-            _ => ()
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.pan_pressed {
+                self.controller().process_pan(delta.0, delta.1);
+            } else if self.mouse_pressed {
+                self.controller().process_mouse(delta.0, delta.1);
+            }
+        }
+    }
+
+    /// Moves the camera `dt` seconds on from the last update
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        if let Some(mode) = self.next_mode.take().filter(|mode| *mode != self.mode) {
+            // First person modes only make sense in perspective
+            if mode != CameraMode::Orbit && camera.projection.is_orthographic() {
+                self.camera_controller.set_orthographic(false);
+                self.camera_controller.update_camera(camera, 0.);
+            }
+            self.mode = mode;
+            self.controller().attach(camera);
+        }
+        if let Some((view, duration)) = self.flight.take() {
+            self.camera_controller.fly_to(view, duration);
+        }
+        self.controller().update_camera(camera, dt);
+    }
+
+    /// Carries on from wherever the camera was moved to meanwhile
+    pub fn attach(&mut self, camera: &Camera) {
+        self.flight = None;
+        self.controller().attach(camera);
+    }
+
+    /// The mode the camera is in, or switches to on the next `update`
+    pub fn mode(&self) -> CameraMode {
+        self.next_mode.unwrap_or(self.mode)
+    }
+
+    /// Switches controller on the next `update`, from where the camera is then
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.next_mode = Some(mode);
+    }
+
+    /// Switches projection on the next `update`, keeping the scale at the
+    /// target. Orbits, as first person modes are perspective only.
+    pub fn set_orthographic(&mut self, orthographic: bool) {
+        self.set_mode(CameraMode::Orbit);
+        self.camera_controller.set_orthographic(orthographic);
+    }
+
+    /// Orbits from `preset` on the next `update`
+    pub fn set_view(&mut self, preset: ViewPreset) {
+        self.set_mode(CameraMode::Orbit);
+        self.flight = None;
+        self.camera_controller.set_view(preset);
+    }
+
+    /// Where the camera is, as a bookmark would keep it
+    pub fn view(&self, camera: &Camera) -> CameraView {
+        self.camera_controller.view(camera)
+    }
+
+    /// Orbits, flying to `view` over `duration` seconds
+    pub fn fly_to(&mut self, view: CameraView, duration: f32) {
+        self.set_mode(CameraMode::Orbit);
+        self.flight = Some((view, duration));
+    }
+
+    /// Orbits around the box from `min` to `max`, all of it in view
+    pub fn fit(&mut self, camera: &mut Camera, min: Vec3, max: Vec3) {
+        if self.mode != CameraMode::Orbit {
+            self.mode = CameraMode::Orbit;
+            self.camera_controller.attach(camera);
+        }
+        self.next_mode = None;
+        self.flight = None;
+        self.camera_controller.fit(camera, min, max);
+    }
+
+    /// Height of the view in pixels, for panning to follow the cursor
+    pub fn resize(&mut self, height: u32) {
+        self.camera_controller.viewport_height = height as f32;
+    }
+
+    /// Where scroll zoom heads for, see `ZoomAnchor`
+    pub fn set_zoom_anchor(&mut self, anchor: ZoomAnchor) {
+        self.camera_controller.set_zoom_anchor(anchor);
+    }
+
+    /// Orbits around `point`, turning to it from where the eye is
+    pub fn recentre(&mut self, camera: &mut Camera, point: Vec3) {
+        self.mode = CameraMode::Orbit;
+        self.next_mode = None;
+        self.flight = None;
+        camera.target = point;
+        self.camera_controller.attach(camera);
+        self.camera_controller.update_camera(camera, 0.);
+    }
+
+    /// Scales first person movement to a scene `extent` across, in which a
+    /// meter is `meter` units, and walks on `ground`
+    pub fn set_scene(&mut self, ground: Ground, extent: f32, meter: f32) {
+        self.fly_controller.speed = extent * FLY_SPEED;
+        self.walk_controller.set_ground(ground, meter);
+    }
+}
+
+// Fraction of the scene flown per second
+const FLY_SPEED: f32 = 0.12;
+
+fn centre(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> PhysicalPosition<f64> {
+    PhysicalPosition::new((a.x + b.x) / 2., (a.y + b.y) / 2.)
+}
+
+/// Standard directions to look at the scene from, front looking north
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    Top,
+    Bottom,
+    Front,
+    Back,
+    Left,
+    Right,
+    Isometric, // from the front right, above
+}
+
+impl std::str::FromStr for ViewPreset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "top" => Ok(ViewPreset::Top),
+            "bottom" => Ok(ViewPreset::Bottom),
+            "front" => Ok(ViewPreset::Front),
+            "back" => Ok(ViewPreset::Back),
+            "left" => Ok(ViewPreset::Left),
+            "right" => Ok(ViewPreset::Right),
+            "isometric" => Ok(ViewPreset::Isometric),
+            _ => Err(format!("Unknown view {name:?}")),
         }
     }
+}
 
-    pub fn update(&mut self, camera: &mut Camera) {
-        self.camera_controller.update_camera(camera);
+impl ViewPreset {
+    /// `(theta, phi)` of the eye around the target, as in `CameraController`
+    fn angles(self) -> (f32, f32) {
+        match self {
+            ViewPreset::Top => (0., -FRAC_PI_2),
+            ViewPreset::Bottom => (PI, -FRAC_PI_2),
+            ViewPreset::Front => (FRAC_PI_2, -FRAC_PI_2),
+            ViewPreset::Back => (FRAC_PI_2, FRAC_PI_2),
+            ViewPreset::Left => (FRAC_PI_2, PI),
+            ViewPreset::Right => (FRAC_PI_2, 0.),
+            ViewPreset::Isometric => ((1. / 3f32.sqrt()).acos(), -FRAC_PI_4),
+        }
     }
 }
 
+// Per second, how quickly panning reaches its speed and stops, and how quickly
+// rotation and zoom catch up with the input
+const PAN_RATE: f32 = 10.;
+const ROTATION_RATE: f32 = 20.;
+const ZOOM_RATE: f32 = 15.;
+// Times closer per second with a trigger all the way
+const STICK_ZOOM: f32 = 2.;
+
+/// Point scroll zoom moves towards, or away from, which stays put on screen
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ZoomAnchor {
+    #[default]
+    Target,
+    Pending, // zoom is held until the point under the cursor is known
+    Point(Vec3),
+}
+
+/// Eased move from wherever the camera is to `to`
+#[derive(Clone, Copy, Debug)]
+struct Flight {
+    from: Option<CameraView>, // taken on the first update
+    to: CameraView,
+    elapsed: f32, // seconds
+    duration: f32,
+}
+
 pub struct CameraController {
-    pub speed: f32, // meters
-    pub angular_speed: f32, // rads
-    pub zoom_speed: f32, // fraction
+    pub speed: f32, // meters per second
+    pub angular_speed: f32, // rads per pixel
+    pub zoom_speed: f32, // fraction per scroll line
+    pub viewport_height: f32, // pixels
     theta: f32, // verticality
     phi: f32, // XY plane, 0 means looking towards (-1, 0)
     farness: f32, // meters
+    fovy: f32, // of the perspective projection, to restore it when leaving orthographic
+    pan: Inertia<Vec2>, // of the target
+    rotation: Vec2, // theta and phi still to turn
+    zoom: f32, // factor still to apply
+    zoom_anchor: ZoomAnchor,
+    axes: Axes,
+    drag: Vec2, // pixels still to pan
+    orthographic: Option<bool>, // projection switch applied on the next update
+    view: Option<ViewPreset>, // same
+    flight: Option<Flight>,
     forward_pressed: bool,
     backward_pressed: bool,
     left_pressed: bool,
@@ -77,9 +404,20 @@ impl CameraController {
             speed,
             angular_speed,
             zoom_speed,
-            theta: std::f32::consts::FRAC_PI_2,
+            viewport_height: 600.,
+            theta: FRAC_PI_2,
             phi: 0.,
             farness,
+            fovy: FRAC_PI_3,
+            pan: Inertia::new(Vec2::ZERO, PAN_RATE),
+            rotation: Vec2::ZERO,
+            zoom: 1.,
+            zoom_anchor: ZoomAnchor::Target,
+            axes: Axes { movement: Vec2::ZERO, look: Vec2::ZERO, lift: 0. },
+            drag: Vec2::ZERO,
+            orthographic: None,
+            view: None,
+            flight: None,
             forward_pressed: false,
             backward_pressed: false,
             left_pressed: false,
@@ -87,9 +425,165 @@ impl CameraController {
         }
     }
 
-    pub fn update_camera(&self, camera: &mut Camera) {
-        let mut looking_at = camera.target - camera.eye;
-        looking_at.z = 0.; // UP = 0
+    pub fn farness(&self) -> f32 {
+        self.farness
+    }
+
+    pub fn set_orthographic(&mut self, orthographic: bool) {
+        self.orthographic = Some(orthographic);
+    }
+
+    pub fn set_zoom_anchor(&mut self, anchor: ZoomAnchor) {
+        self.zoom_anchor = anchor;
+    }
+
+    /// Looks from `preset` on the next update
+    pub fn set_view(&mut self, preset: ViewPreset) {
+        self.view = Some(preset);
+        self.flight = None;
+    }
+
+    /// Where the camera is, as a bookmark would keep it
+    pub fn view(&self, camera: &Camera) -> CameraView {
+        CameraView::from_camera(camera, self.fovy)
+    }
+
+    /// Moves to `view` over `duration` seconds, easing in and out. Input on
+    /// the way stops it there.
+    pub fn fly_to(&mut self, view: CameraView, duration: f32) {
+        self.flight = Some(Flight { from: None, to: view, elapsed: 0., duration });
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.flight.is_some()
+    }
+
+    /// Puts the camera at `view`, sizing orthographic projections from its farness
+    fn apply_view(&mut self, camera: &mut Camera, view: &CameraView) {
+        camera.target = view.target;
+        (self.theta, self.phi, self.farness) = (view.theta, view.phi, view.farness);
+        if view.orthographic != camera.projection.is_orthographic() {
+            self.orthographic = Some(view.orthographic);
+            self.update_projection(camera, 0.);
+        }
+        if let Projection::Orthographic { width, height, .. } = &mut camera.projection {
+            let aspect = *width / *height;
+            *height = 2. * view.farness * (self.fovy * 0.5).tan();
+            *width = aspect * *height;
+        }
+    }
+
+    /// Advances the flight, if any, by `dt` seconds
+    fn fly(&mut self, camera: &mut Camera, dt: f32) {
+        let Some(flight) = &mut self.flight else {
+            return;
+        };
+        let from = *flight.from.get_or_insert_with(|| CameraView::from_camera(camera, self.fovy));
+        flight.elapsed += dt;
+        let t = (flight.elapsed / flight.duration.max(f32::EPSILON)).min(1.);
+        let view = from.lerp(&flight.to, t * t * (3. - 2. * t));
+        if t >= 1. {
+            self.flight = None;
+        }
+        self.rotation = Vec2::ZERO;
+        self.zoom = 1.;
+        self.pan.velocity = Vec2::ZERO;
+        self.apply_view(camera, &view);
+    }
+
+    /// Centres the target on the box from `min` to `max` and backs off until
+    /// all of it is in view, whatever the direction
+    pub fn fit(&mut self, camera: &mut Camera, min: Vec3, max: Vec3) {
+        let radius = ((max - min).length() * 0.5).max(f32::EPSILON);
+        camera.target = (min + max) * 0.5;
+        self.flight = None;
+        self.zoom = 1.;
+        self.zoom_anchor = ZoomAnchor::Target;
+
+        match &mut camera.projection {
+            Projection::Perspective { aspect, fovy, near, far } => {
+                // The narrowest of the vertical and horizontal fields of view
+                let half_fov = ((*fovy * 0.5).tan() * aspect.min(1.)).atan();
+                self.farness = radius / half_fov.sin();
+                (*near, *far) = (self.farness * FIT_NEAR, (self.farness + radius) * FIT_FAR);
+            }
+            Projection::Orthographic { width, height, near, far } => {
+                let aspect = *width / *height;
+                *height = 2. * radius / aspect.min(1.);
+                *width = *height * aspect;
+                self.farness = radius / (self.fovy * 0.5).sin();
+                (*near, *far) = (self.farness * FIT_NEAR, (self.farness + radius) * FIT_FAR);
+            }
+        }
+    }
+
+    /// Switches projection and zooms as requested, easing into the zoom. The
+    /// target keeps its apparent size, orthographic zoom scales the extents.
+    /// Zooming scales the whole view about the anchor, which doesn't move, and
+    /// waits while the anchor is pending.
+    fn update_projection(&mut self, camera: &mut Camera, dt: f32) {
+        let projection = &mut camera.projection;
+        match (self.orthographic.take(), *projection) {
+            (Some(true), Projection::Perspective { fovy, .. }) => {
+                self.fovy = fovy;
+                projection.to_orthographic(self.farness);
+            }
+            (Some(false), Projection::Orthographic { height, .. }) => {
+                self.farness = height * 0.5 / (self.fovy * 0.5).tan();
+                projection.to_perspective(self.farness);
+            }
+            _ => (),
+        }
+
+        if self.zoom_anchor == ZoomAnchor::Pending {
+            return;
+        }
+        let zoom = self.zoom.powf(ease(ZOOM_RATE, dt));
+        self.zoom /= zoom;
+        if let ZoomAnchor::Point(anchor) = self.zoom_anchor {
+            camera.target = anchor + (camera.target - anchor) * zoom;
+        }
+        match &mut camera.projection {
+            Projection::Orthographic { width, height, .. } => {
+                *width *= zoom;
+                *height *= zoom;
+            }
+            Projection::Perspective { .. } => self.farness *= zoom,
+        }
+    }
+}
+
+const PIXEL_TO_LINE_FRAC: f32 = 1. / 10.;
+// Depth range after a fit, relative to the distance to the scene, leaving room to zoom out
+const FIT_NEAR: f32 = 1e-3;
+const FIT_FAR: f32 = 100.;
+impl Input for CameraController {
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        if let Some(preset) = self.view.take() {
+            (self.theta, self.phi) = preset.angles();
+            self.rotation = Vec2::ZERO;
+        }
+        self.zoom /= STICK_ZOOM.powf(self.axes.lift * dt);
+        self.update_projection(camera, dt);
+        self.fly(camera, dt);
+
+        // Follows the cursor at the depth of the target
+        let drag = std::mem::take(&mut self.drag);
+        if drag != Vec2::ZERO {
+            let (right, up) = camera.screen_axes();
+            let drag = drag * camera.pixel_size(self.viewport_height);
+            camera.target += up * drag.y - right * drag.x;
+        }
+
+        let rotation = self.rotation * ease(ROTATION_RATE, dt);
+        self.rotation -= rotation;
+        self.theta += rotation.x;
+        self.phi += rotation.y;
+
+        // Sticks turn steadily, as dragging would
+        let turn = self.axes.look * dt;
+        self.theta = (self.theta + turn.y).clamp(0., PI);
+        self.phi += turn.x;
 
         let theta_cos = self.theta.cos();
         let theta_sin = self.theta.sin();
@@ -100,12 +594,11 @@ impl CameraController {
             Into::<f32>::into(self.backward_pressed) - Into::<f32>::into(self.forward_pressed),
             Into::<f32>::into(self.left_pressed) - Into::<f32>::into(self.right_pressed),
         );
+        let stick = -self.axes.movement.yx();
 
-        if  move_to.x != 0. || move_to.y != 0. {
-            let norm = move_to.normalize();
-            let rotation = Mat2::from_cols_array(&[phi_cos, phi_sin, -phi_sin, phi_cos]);
-            camera.target = camera.target.with_xy(camera.target.xy() + rotation * norm * self.speed);
-        } 
+        let rotation = Mat2::from_cols_array(&[phi_cos, phi_sin, -phi_sin, phi_cos]);
+        let velocity = rotation * (move_to.normalize_or_zero() + stick).clamp_length_max(1.) * self.speed;
+        camera.target = camera.target.with_xy(camera.target.xy() + self.pan.step(velocity, dt));
 
         camera.eye = (
             camera.target.x + theta_sin * phi_cos * self.farness,
@@ -119,26 +612,45 @@ impl CameraController {
             theta_sin,
         ).normalize();
     }
-}
 
-const PIXEL_TO_LINE_FRAC: f32 = 1. / 10.;
-impl Input for CameraController {
-    fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> bool {
+    fn attach(&mut self, camera: &Camera) {
+        let offset = camera.eye - camera.target;
+        self.farness = offset.length().max(f32::EPSILON);
+        self.theta = (offset.z / self.farness).clamp(-1., 1.).acos();
+        self.phi = offset.y.atan2(offset.x);
+        self.pan.velocity = Vec2::ZERO;
+        self.rotation = Vec2::ZERO;
+        self.zoom = 1.;
+        self.zoom_anchor = ZoomAnchor::Target;
+        self.drag = Vec2::ZERO;
+        self.axes = Axes::default();
+        self.flight = None;
+    }
+
+    fn process_pan(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.flight = None;
+        self.drag += Vec2::new(mouse_dx as f32, mouse_dy as f32);
+    }
+
+    fn process_action(&mut self, action: Action, state: ElementState) -> bool {
         let is_pressed = state == ElementState::Pressed;
-        match key {
-            KeyCode::KeyW | KeyCode::ArrowUp => {
+        if is_pressed {
+            self.flight = None;
+        }
+        match action {
+            Action::MoveForward => {
                 self.forward_pressed = is_pressed;
                 true
             }
-            KeyCode::KeyA | KeyCode::ArrowLeft => {
+            Action::MoveLeft => {
                 self.left_pressed = is_pressed;
                 true
             }
-            KeyCode::KeyS | KeyCode::ArrowDown => {
+            Action::MoveBackward => {
                 self.backward_pressed = is_pressed;
                 true
             }
-            KeyCode::KeyD | KeyCode::ArrowRight => {
+            Action::MoveRight => {
                 self.right_pressed = is_pressed;
                 true
             }
@@ -147,17 +659,27 @@ impl Input for CameraController {
     }
 
     fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-        self.phi += mouse_dx as f32 * self.angular_speed;
-        self.theta -= mouse_dy as f32 * self.angular_speed;
+        self.flight = None;
+        // Where theta ends up stays in range
+        let theta = (self.theta + self.rotation.x - mouse_dy as f32 * self.angular_speed).clamp(0., PI);
+        self.rotation.x = theta - self.theta;
+        self.rotation.y += mouse_dx as f32 * self.angular_speed;
+    }
+
+    fn process_zoom(&mut self, factor: f32) {
+        self.flight = None;
+        self.zoom /= factor.max(f32::EPSILON);
+    }
 
-        if self.theta > std::f32::consts::PI {
-            self.theta = std::f32::consts::PI;
-        } else if self.theta < 0. {
-            self.theta = 0.;
+    fn process_axes(&mut self, axes: Axes) {
+        if axes != Axes::default() {
+            self.flight = None;
         }
+        self.axes = axes;
     }
 
     fn process_scroll(&mut self, delta: &winit::event::MouseScrollDelta) {
+        self.flight = None;
         match *delta {
             MouseScrollDelta::LineDelta(_dx, dy) => {
                 let zoom_factor = 1. + (dy.abs() * self.zoom_speed);
@@ -167,7 +689,7 @@ impl Input for CameraController {
                     zoom_factor
                 };
 
-                self.farness *= zoom_factor;
+                self.zoom *= zoom_factor;
             }
             MouseScrollDelta::PixelDelta(physical_position) => {
                 let dy = physical_position.cast::<f32>().y * PIXEL_TO_LINE_FRAC;
@@ -178,7 +700,7 @@ impl Input for CameraController {
                     zoom_factor
                 };
 
-                self.farness *= zoom_factor;
+                self.zoom *= zoom_factor;
             }
         }
     }
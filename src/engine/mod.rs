@@ -1,12 +1,53 @@
+/// Source of shaders in src/shaders that use the uniforms and helpers of
+/// common.wgsl, which is prepended to them, in order
+macro_rules! common_shader {
+    ($($file:literal),+) => {
+        concat!(include_str!("../shaders/common.wgsl"), $(include_str!(concat!("../shaders/", $file))),+)
+    };
+}
+pub(crate) use common_shader;
+
+#[allow(clippy::module_inception)]
 mod engine;
 mod camera;
 mod input;
 mod uniform;
 mod pipeline;
+mod renderer;
+mod offscreen;
+mod postprocess;
+mod ssao;
+mod splat;
+mod raster;
+mod picking;
+mod lines;
+mod clipping;
+mod filter;
+mod gizmo;
+mod profile_view;
+mod first_person;
+mod ground;
+mod motion;
+mod gamepad;
 
 pub use engine::Engine;
 pub use camera::*;
 pub use uniform::*;
+pub use renderer::Renderer;
+pub use offscreen::*;
+pub use postprocess::EdlSettings;
+pub use ssao::{SsaoSettings, MAX_SSAO_SAMPLES};
+pub use splat::{SplatMode, SplatSettings};
+pub use raster::{RasterBackend, INT64_ATOMICS};
+pub use picking::PickedPoint;
+pub use lines::LineVertex;
+pub use clipping::{ClipBox, ClipMode, ClipPlane, ClipSettings, MAX_CLIP_BOXES, MAX_CLIP_PLANES};
+pub use filter::{ClassificationSet, FilterSettings, ReturnFilter};
+pub use input::{CameraController, CameraMode, Gesture, Input, InputServer, ViewPreset, ZoomAnchor};
+pub use first_person::{FlyController, WalkController};
+pub use ground::Ground;
+pub use motion::{ease, Inertia};
+pub use gamepad::{is_space_mouse, Axes, GamepadButton, GamepadSettings, GamepadState, Gamepads, SpaceMouseState};
 
 use wgpu::util::RenderEncoder;
 
@@ -0,0 +1,34 @@
+use std::ops::{Add, Mul, Sub};
+
+/// Velocity easing towards what the input asks for: it accelerates towards
+/// the goal and damps exponentially once the goal is zero. Steps are
+/// integrated exactly, so any frame rate follows the same trajectory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Inertia<T> {
+    pub velocity: T, // per second
+    pub rate: f32, // per second, how fast the velocity reaches the goal
+}
+
+impl<T> Inertia<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    pub const fn new(velocity: T, rate: f32) -> Self {
+        Self { velocity, rate }
+    }
+
+    /// Displacement over `dt` seconds while heading for `goal` velocity
+    pub fn step(&mut self, goal: T, dt: f32) -> T {
+        let decay = (-self.rate * dt).exp();
+        let displacement = goal * dt + (self.velocity - goal) * ((1. - decay) / self.rate);
+        self.velocity = goal + (self.velocity - goal) * decay;
+
+        displacement
+    }
+}
+
+/// Fraction of what is left to go covered in `dt` seconds when easing out at
+/// `rate` per second. Two steps of `dt` cover as much as one of `2 * dt`.
+pub fn ease(rate: f32, dt: f32) -> f32 {
+    1. - (-rate * dt).exp()
+}
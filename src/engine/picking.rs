@@ -0,0 +1,317 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::Poll,
+};
+
+use glam::{DVec3, Vec3};
+use serde::Serialize;
+
+use super::{pipeline::POINT_BUFFERS, Encode, Uniform};
+use crate::pointcloud::{Pointcloud, SourcePoint};
+
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+// Pixels around the cursor searched for a point, so thin splats are still easy to hit
+const PICK_RADIUS: u32 = 4;
+const PICK_SIZE: u32 = PICK_RADIUS * 2 + 1;
+const PADDED_ROW: u32 = (PICK_SIZE * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PickedPoint {
+    pub index: usize,
+    pub local: Vec3, // scene coordinates
+    pub position: DVec3, // source coordinates, exact if the cloud has source points
+    pub intensity: f32,
+    pub source: Option<SourcePoint>,
+}
+
+impl PickedPoint {
+    pub fn new(pointcloud: &Pointcloud, index: usize) -> Self {
+        let local = pointcloud.points()[index];
+        let source = pointcloud.source().get(index).copied();
+
+        Self {
+            index,
+            local,
+            position: source.map_or_else(|| pointcloud.transform().to_source(local), |source| source.position),
+            intensity: pointcloud.intensities()[index],
+            source,
+        }
+    }
+}
+
+impl fmt::Display for PickedPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} ({:.3}, {:.3}, {:.3})", self.index, self.position.x, self.position.y, self.position.z)?;
+        match &self.source {
+            Some(source) => {
+                write!(
+                    f,
+                    "\nintensity {}, return {}/{}, class {}, scan angle {}, user data {}, source id {}",
+                    source.intensity,
+                    source.return_number,
+                    source.number_of_returns,
+                    source.classification,
+                    source.scan_angle,
+                    source.user_data,
+                    source.point_source_id,
+                )?;
+                if let Some(gps_time) = source.gps_time {
+                    write!(f, ", gps time {gps_time}")?;
+                }
+                if let Some([red, green, blue]) = source.color {
+                    write!(f, ", color ({red}, {green}, {blue})")?;
+                }
+                Ok(())
+            }
+            None => write!(f, "\nintensity {}", self.intensity),
+        }
+    }
+}
+
+struct PendingPick {
+    ready: Arc<AtomicBool>,
+    cursor: (u32, u32), // inside the readback region
+    region: (u32, u32),
+}
+
+/// Renders point indices around the cursor into an R32Uint target and reads
+/// them back without blocking: `request` starts a pick, `poll` returns it once
+/// the GPU is done.
+pub struct Picker {
+    pipeline: wgpu::RenderPipeline,
+    id_texture: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+    readback_buffer: wgpu::Buffer,
+    pending: Option<PendingPick>,
+}
+
+impl Picker {
+    pub fn new(device: &wgpu::Device, uniform_bind_group_layout: &wgpu::BindGroupLayout, width: u32, height: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pointcloud Shader"),
+            source: wgpu::ShaderSource::Wgsl(super::common_shader!("intensity.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pick Pipeline Layout"),
+            bind_group_layouts: &[uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Pick Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: POINT_BUFFERS,
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_pick"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: ID_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pick Readback Buffer"),
+            size: (PADDED_ROW * PICK_SIZE) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let (id_texture, depth_view) = Self::create_targets(device, width, height);
+
+        Self {
+            pipeline,
+            id_texture,
+            depth_view,
+            readback_buffer,
+            pending: None,
+        }
+    }
+
+    fn create_targets(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let create_texture = |label, format, usage| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+        let id_texture = create_texture(
+            "pick id texture",
+            ID_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let depth_view = create_texture(
+            "pick depth texture",
+            wgpu::TextureFormat::Depth32Float,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        ).create_view(&wgpu::TextureViewDescriptor::default());
+
+        (id_texture, depth_view)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.id_texture, self.depth_view) = Self::create_targets(device, width, height);
+    }
+
+    /// Whether a pick was started and its result not polled yet
+    pub fn in_flight(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Starts picking at pixel `(x, y)`. Ignored while a previous pick is
+    /// still in flight, returns whether it was started.
+    pub fn request(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uniform: &Uniform,
+        pointcloud: &Pointcloud,
+        x: u32,
+        y: u32,
+    ) -> bool {
+        let size = self.id_texture.size();
+        if self.pending.is_some() || x >= size.width || y >= size.height {
+            return false;
+        }
+        // Region around the cursor, shifted to stay inside the texture
+        let origin_x = x.saturating_sub(PICK_RADIUS).min(size.width.saturating_sub(PICK_SIZE));
+        let origin_y = y.saturating_sub(PICK_RADIUS).min(size.height.saturating_sub(PICK_SIZE));
+        let region_width = PICK_SIZE.min(size.width);
+        let region_height = PICK_SIZE.min(size.height);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Pick Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Pick Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.id_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_scissor_rect(origin_x, origin_y, region_width, region_height);
+            render_pass.set_pipeline(&self.pipeline);
+            uniform.record_command(&mut render_pass);
+            pointcloud.record_command(&mut render_pass);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.id_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: origin_x, y: origin_y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(PADDED_ROW),
+                    rows_per_image: Some(region_height),
+                },
+            },
+            wgpu::Extent3d {
+                width: region_width,
+                height: region_height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let ready = Arc::new(AtomicBool::new(false));
+        let flag = ready.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            result.expect("Couldn't map pick buffer");
+            flag.store(true, Ordering::Release);
+        });
+        self.pending = Some(PendingPick {
+            ready,
+            cursor: (x - origin_x, y - origin_y),
+            region: (region_width, region_height),
+        });
+
+        true
+    }
+
+    /// Index of the point closest to the cursor of the last request, `None`
+    /// if there was only background around it
+    pub fn poll(&mut self, device: &wgpu::Device) -> Poll<Option<u32>> {
+        let Some(pending) = &self.pending else {
+            return Poll::Pending;
+        };
+        // Drives the map callback on native, the browser does it on its own
+        let _ = device.poll(wgpu::PollType::Poll);
+        if !pending.ready.load(Ordering::Acquire) {
+            return Poll::Pending;
+        }
+
+        let (cursor_x, cursor_y) = pending.cursor;
+        let (region_width, region_height) = pending.region;
+        let closest = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            data.chunks(PADDED_ROW as usize)
+                .take(region_height as usize)
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row[..(region_width * 4) as usize]
+                        .chunks(4)
+                        .enumerate()
+                        .map(move |(x, id)| (x as i64, y as i64, u32::from_ne_bytes(id.try_into().unwrap())))
+                })
+                .filter(|(_, _, id)| *id != 0)
+                .min_by_key(|(x, y, _)| (x - cursor_x as i64).pow(2) + (y - cursor_y as i64).pow(2))
+                .map(|(_, _, id)| id - 1)
+        };
+        self.readback_buffer.unmap();
+        self.pending = None;
+
+        Poll::Ready(closest)
+    }
+}
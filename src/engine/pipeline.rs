@@ -1,5 +1,57 @@
 use super::Encode;
 
+/// Per instance attributes of a `Pointcloud`, shared by every pipeline that draws points
+pub const POINT_BUFFERS: &[wgpu::VertexBufferLayout<'static>] = &[
+    wgpu::VertexBufferLayout { // Point
+        attributes: &[
+            wgpu::VertexAttribute {
+                shader_location: 0,
+                offset: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            }
+        ],
+        array_stride: 12,
+        step_mode: wgpu::VertexStepMode::Instance,
+    },
+    wgpu::VertexBufferLayout { // Intensity
+        attributes: &[
+            wgpu::VertexAttribute {
+                shader_location: 1,
+                offset: 0,
+                format: wgpu::VertexFormat::Float32,
+            }
+        ],
+        array_stride: 4,
+        step_mode: wgpu::VertexStepMode::Instance,
+    },
+    wgpu::VertexBufferLayout { // Spacing
+        attributes: &[
+            wgpu::VertexAttribute {
+                shader_location: 2,
+                offset: 0,
+                format: wgpu::VertexFormat::Float32,
+            }
+        ],
+        array_stride: 4,
+        step_mode: wgpu::VertexStepMode::Instance,
+    },
+    wgpu::VertexBufferLayout { // Packed attributes and gps time
+        attributes: &[
+            wgpu::VertexAttribute {
+                shader_location: 3,
+                offset: 0,
+                format: wgpu::VertexFormat::Uint32,
+            },
+            wgpu::VertexAttribute {
+                shader_location: 4,
+                offset: 4,
+                format: wgpu::VertexFormat::Float32,
+            },
+        ],
+        array_stride: 8,
+        step_mode: wgpu::VertexStepMode::Instance,
+    },
+];
 
 pub struct PointcloudPipeline {
     render_pipeline: wgpu::RenderPipeline,
@@ -14,11 +66,11 @@ impl PointcloudPipeline {
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Pointcloud Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/intensity.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(super::common_shader!("intensity.wgsl").into()),
         });
         let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render PointcloudPipeline Layout"),
-            bind_group_layouts: &[ &uniform_bind_group_layout ],
+            bind_group_layouts: &[ uniform_bind_group_layout ],
             push_constant_ranges: &[],
         });
 
@@ -28,30 +80,7 @@ impl PointcloudPipeline {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main".into(),
-                buffers: &[
-                    wgpu::VertexBufferLayout { // Point
-                        attributes: &[
-                            wgpu::VertexAttribute {
-                                shader_location: 0,
-                                offset: 0,
-                                format: wgpu::VertexFormat::Float32x3,
-                            }
-                        ],
-                        array_stride: 12,
-                        step_mode: wgpu::VertexStepMode::Instance,
-                    },
-                    wgpu::VertexBufferLayout { // Intensity
-                        attributes: &[
-                            wgpu::VertexAttribute {
-                                shader_location: 1,
-                                offset: 0,
-                                format: wgpu::VertexFormat::Float32,
-                            }
-                        ],
-                        array_stride: 4,
-                        step_mode: wgpu::VertexStepMode::Instance,
-                    },
-                ],
+                buffers: POINT_BUFFERS,
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
@@ -0,0 +1,264 @@
+use super::{ssao::{SsaoPass, SsaoSettings}, Projection};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdlSettings {
+    pub enabled: bool,
+    pub strength: f32,
+    pub radius: f32, // pixels
+}
+
+impl Default for EdlSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strength: 1.,
+            radius: 1.4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeUniformData {
+    edl_strength: f32,
+    edl_radius: f32,
+    near: f32,
+    far: f32,
+    orthographic: u32,
+    edl_enabled: u32,
+    ssao_enabled: u32,
+    _padding: u32,
+}
+
+/// Screen space effects applied after the main pass. The main pass renders
+/// into `scene_view` and `record_command` composites it into the output.
+pub struct PostProcess {
+    pub edl: EdlSettings,
+    pub ssao: SsaoSettings,
+    ssao_pass: SsaoPass,
+    scene_view: wgpu::TextureView,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl PostProcess {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        depth_view: &wgpu::TextureView,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("postprocess_bind_group_layout"),
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Composite Uniform Buffer"),
+            size: std::mem::size_of::<CompositeUniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Composite Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/composite.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PostProcess Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("PostProcess Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main".into(),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main".into(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let ssao_pass = SsaoPass::new(device, width, height, depth_view);
+        let scene_view = Self::create_scene_view(device, format, width, height);
+        let bind_group = Self::create_bind_group(
+            device, &bind_group_layout, &uniform_buffer, &scene_view, depth_view, ssao_pass.output_view(),
+        );
+
+        Self {
+            edl: EdlSettings::default(),
+            ssao: SsaoSettings::default(),
+            ssao_pass,
+            scene_view,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+            format,
+        }
+    }
+
+    fn create_scene_view(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> wgpu::TextureView {
+        let scene_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("scene texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        scene_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        scene_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        ao_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(ao_view),
+                },
+            ],
+            label: Some("postprocess_bind_group"),
+        })
+    }
+
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene_view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32, depth_view: &wgpu::TextureView) {
+        self.ssao_pass.resize(device, width, height, depth_view);
+        self.scene_view = Self::create_scene_view(device, self.format, width, height);
+        self.bind_group = Self::create_bind_group(
+            device, &self.bind_group_layout, &self.uniform_buffer, &self.scene_view, depth_view, self.ssao_pass.output_view(),
+        );
+    }
+
+    pub fn update(&self, projection: &Projection, queue: &wgpu::Queue) {
+        let (near, far) = projection.depth_range();
+        let data = CompositeUniformData {
+            edl_strength: self.edl.strength,
+            edl_radius: self.edl.radius,
+            near,
+            far,
+            orthographic: projection.is_orthographic() as u32,
+            edl_enabled: self.edl.enabled as u32,
+            ssao_enabled: self.ssao.enabled as u32,
+            _padding: 0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[data]));
+        self.ssao_pass.update(&self.ssao, projection, queue);
+    }
+
+    pub fn record_command(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        if self.ssao.enabled {
+            self.ssao_pass.record_command(encoder);
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("PostProcess Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
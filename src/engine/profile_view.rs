@@ -0,0 +1,213 @@
+use glam::{DVec3, Vec2, Vec3};
+
+use super::{pipeline::PointcloudPipeline, *};
+use crate::{pointcloud::Pointcloud, profile::ProfilePoint};
+
+// Pixels between the inset and the edges of the frame
+const MARGIN: f32 = 10.;
+// Fraction of the frame height taken by the inset
+const INSET_HEIGHT: f32 = 0.3;
+// Room left around the profile in the inset
+const FIT_MARGIN: f32 = 1.1;
+
+/// Orthographic side view of an extracted profile, drawn as an inset at the
+/// bottom of the frame: distance along the path to the right, elevation up.
+pub struct ProfileView {
+    background_pipeline: wgpu::RenderPipeline,
+    uniform: Uniform,
+    camera: Camera,
+    pointcloud: Option<Pointcloud>, // unrolled, centered on the profile
+    extent: Vec3, // of the unrolled points
+    width: u32,
+    height: u32,
+}
+
+impl ProfileView {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Profile Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/profile.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Profile Background Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let background_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Profile Background Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_background"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_background"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let extent = Vec3::ONE;
+        let (_, _, inset_width, inset_height) = Self::viewport(width, height);
+        let camera = Self::fit_camera(extent, inset_width, inset_height);
+        let uniform = Uniform::new(queue, device, uniform_bind_group_layout, &camera, inset_width as u32, inset_height as u32);
+
+        let mut profile_view = Self {
+            background_pipeline,
+            uniform,
+            camera,
+            pointcloud: None,
+            extent,
+            width,
+            height,
+        };
+        profile_view.resize(width, height);
+
+        profile_view
+    }
+
+    /// `(x, y, width, height)` of the inset in a frame of `width` by `height` pixels
+    fn viewport(width: u32, height: u32) -> (f32, f32, f32, f32) {
+        let inset_width = (width as f32 - 2. * MARGIN).max(1.);
+        let inset_height = (height as f32 * INSET_HEIGHT).max(1.);
+        (MARGIN, (height as f32 - MARGIN - inset_height).max(0.), inset_width, inset_height)
+    }
+
+    /// Looks at the unrolled points from their left, the whole profile at the same scale on both axes
+    fn fit_camera(extent: Vec3, inset_width: f32, inset_height: f32) -> Camera {
+        let world_per_pixel = (extent.x / inset_width).max(extent.z / inset_height).max(f32::EPSILON) * FIT_MARGIN;
+        let distance = extent.y + 1.;
+        Camera {
+            eye: Vec3::new(0., distance, 0.),
+            target: Vec3::ZERO,
+            up: Vec3::Z,
+            projection: Projection::Orthographic {
+                near: 0.01,
+                far: distance * 2.,
+                width: world_per_pixel * inset_width,
+                height: world_per_pixel * inset_height,
+            },
+        }
+    }
+
+    /// Replaces the profile, an empty one hides the inset
+    pub fn set_profile(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, points: &[ProfilePoint]) {
+        if points.is_empty() {
+            self.pointcloud = None;
+            return;
+        }
+
+        let (min, max) = points.iter().fold(
+            (DVec3::INFINITY, DVec3::NEG_INFINITY),
+            |(min, max), point| (min.min(point.unrolled()), max.max(point.unrolled())),
+        );
+        let center = (min + max) * 0.5;
+        let unrolled = points.iter().map(|point| (point.unrolled() - center).as_vec3()).collect();
+        let intensities = points.iter().map(|point| point.intensity).collect();
+        let pointcloud = Pointcloud::from_points(device, queue, unrolled, intensities);
+
+        let (low, high) = pointcloud.bounds();
+        let (intensity_min, intensity_max) = pointcloud.intensity_range();
+        let data = &mut self.uniform.camera_uniform;
+        data.elevation_min = low.z;
+        data.elevation_max = high.z;
+        data.intensity_min = intensity_min;
+        data.intensity_max = intensity_max;
+
+        self.extent = (max - min).as_vec3();
+        self.pointcloud = Some(pointcloud);
+        self.resize(self.width, self.height);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        let (x, y, inset_width, inset_height) = Self::viewport(width, height);
+        self.camera = Self::fit_camera(self.extent, inset_width, inset_height);
+        let data = &mut self.uniform.camera_uniform;
+        data.width = inset_width;
+        data.height = inset_height;
+        data.viewport_origin = Vec2::new(x, y);
+    }
+
+    /// Follows the colour mode of the main view, points always sized in pixels
+    pub fn update(&mut self, point_size: &PointSize, color_mode: ColorMode, queue: &wgpu::Queue) {
+        let point_size = PointSize { mode: PointSizeMode::Fixed, ..*point_size };
+        self.uniform.camera_uniform.set_point_size(&point_size);
+        self.uniform.camera_uniform.set_color_mode(color_mode);
+        self.uniform.update(&self.camera, queue);
+    }
+
+    /// Draws the inset over `output`, reusing `depth_view` once the frame is done with it
+    pub fn record_command(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pointcloud_pipeline: &PointcloudPipeline,
+        output: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let Some(pointcloud) = &self.pointcloud else {
+            return;
+        };
+        let (x, y, width, height) = Self::viewport(self.width, self.height);
+        if x + width > self.width as f32 || y + height > self.height as f32 {
+            return; // frame too small for an inset
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Profile Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_viewport(x, y, width, height, 0., 1.);
+        render_pass.set_scissor_rect(x as u32, y as u32, width as u32, height as u32);
+
+        render_pass.set_pipeline(&self.background_pipeline);
+        render_pass.draw(0..3, 0..1);
+
+        pointcloud_pipeline.record_command(&mut render_pass);
+        self.uniform.record_command(&mut render_pass);
+        pointcloud.record_command(&mut render_pass);
+    }
+}
@@ -0,0 +1,271 @@
+use super::{renderer::BACKGROUND, Encode, Uniform};
+use crate::pointcloud::Pointcloud;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Features the compute backend uses to pack depth and color in a single
+/// atomic, it falls back to two 32-bit passes without them
+pub const INT64_ATOMICS: wgpu::Features = wgpu::Features::SHADER_INT64.union(wgpu::Features::SHADER_INT64_ATOMIC_MIN_MAX);
+
+/// How points get into the scene texture, picked when the renderer is created
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RasterBackend {
+    #[default]
+    Pipeline, // instanced quads through the hardware rasteriser
+    Compute, // one thread per point writing to a storage buffer with atomics
+}
+
+/// Point rasterisation in compute shaders. Every pixel of the framebuffer
+/// holds a depth key and a color, the closest point wins through atomicMax.
+/// A resolve pass then writes them to the scene and depth textures.
+pub struct ComputeRasterizer {
+    raster_pipelines: Vec<wgpu::ComputePipeline>,
+    resolve_pipeline: wgpu::RenderPipeline,
+    points_layout: wgpu::BindGroupLayout,
+    framebuffer_layout: wgpu::BindGroupLayout,
+    framebuffer: wgpu::Buffer,
+    framebuffer_bind_group: wgpu::BindGroup,
+    points_bind_group: Option<wgpu::BindGroup>,
+    point_count: u32,
+    max_workgroups: u32,
+}
+
+impl ComputeRasterizer {
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let storage_entry = |binding, visibility, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let points_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage_entry(0, wgpu::ShaderStages::COMPUTE, true),
+                storage_entry(1, wgpu::ShaderStages::COMPUTE, true),
+                storage_entry(2, wgpu::ShaderStages::COMPUTE, true),
+                storage_entry(3, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(4, wgpu::ShaderStages::COMPUTE, true),
+            ],
+            label: Some("raster_points_bind_group_layout"),
+        });
+        let framebuffer_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[storage_entry(0, wgpu::ShaderStages::FRAGMENT, true)],
+            label: Some("raster_framebuffer_bind_group_layout"),
+        });
+
+        let (source, entry_points): (&str, &[&str]) = if device.features().contains(INT64_ATOMICS) {
+            (super::common_shader!("raster_common.wgsl", "raster64.wgsl"), &["cs_main"])
+        } else {
+            (super::common_shader!("raster_common.wgsl", "raster.wgsl"), &["cs_depth", "cs_color"])
+        };
+        let raster_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Raster Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let raster_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Raster Pipeline Layout"),
+            bind_group_layouts: &[uniform_bind_group_layout, &points_layout],
+            push_constant_ranges: &[],
+        });
+        let raster_pipelines = entry_points
+            .iter()
+            .map(|entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Raster Pipeline"),
+                layout: Some(&raster_layout),
+                module: &raster_shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            }))
+            .collect();
+
+        let resolve_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Resolve Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/resolve.wgsl").into()),
+        });
+        let resolve_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Resolve Pipeline Layout"),
+            bind_group_layouts: &[uniform_bind_group_layout, &framebuffer_layout],
+            push_constant_ranges: &[],
+        });
+        let resolve_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Resolve Pipeline"),
+            layout: Some(&resolve_layout),
+            vertex: wgpu::VertexState {
+                module: &resolve_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &resolve_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let (framebuffer, framebuffer_bind_group) = Self::create_framebuffer(device, &framebuffer_layout, width, height);
+
+        Self {
+            raster_pipelines,
+            resolve_pipeline,
+            points_layout,
+            framebuffer_layout,
+            framebuffer,
+            framebuffer_bind_group,
+            points_bind_group: None,
+            point_count: 0,
+            max_workgroups: device.limits().max_compute_workgroups_per_dimension,
+        }
+    }
+
+    fn create_framebuffer(
+        device: &wgpu::Device,
+        framebuffer_layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let framebuffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Raster Framebuffer"),
+            size: width.max(1) as u64 * height.max(1) as u64 * 8, // color and depth key
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: framebuffer_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: framebuffer.as_entire_binding(),
+                },
+            ],
+            label: Some("raster_framebuffer_bind_group"),
+        });
+
+        (framebuffer, bind_group)
+    }
+
+    /// Binds the buffers of `pointcloud`, has to be called again after a resize
+    pub fn set_pointcloud(&mut self, device: &wgpu::Device, pointcloud: &Pointcloud) {
+        self.point_count = pointcloud.len() as u32;
+        if pointcloud.is_empty() {
+            self.points_bind_group = None; // empty buffers can't be bound
+            return;
+        }
+
+        let (points, intensities, spacing, attributes) = pointcloud.buffers();
+        self.points_bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.points_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: points.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: intensities.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: spacing.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.framebuffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: attributes.as_entire_binding(),
+                },
+            ],
+            label: Some("raster_points_bind_group"),
+        }));
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32, pointcloud: Option<&Pointcloud>) {
+        (self.framebuffer, self.framebuffer_bind_group) = Self::create_framebuffer(device, &self.framebuffer_layout, width, height);
+        if let Some(pointcloud) = pointcloud {
+            self.set_pointcloud(device, pointcloud);
+        }
+    }
+
+    pub fn record_command(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        uniform: &Uniform,
+        scene_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        encoder.clear_buffer(&self.framebuffer, 0, None);
+
+        if let Some(points_bind_group) = &self.points_bind_group {
+            // Spread over y when there are more workgroups than a dimension allows
+            let workgroups = self.point_count.div_ceil(WORKGROUP_SIZE);
+            let x = workgroups.min(self.max_workgroups);
+            let y = workgroups.div_ceil(x);
+
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Raster Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, uniform.bind_group(), &[]);
+            compute_pass.set_bind_group(1, points_bind_group, &[]);
+            for pipeline in &self.raster_pipelines {
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.dispatch_workgroups(x, y, 1);
+            }
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Resolve Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: scene_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(BACKGROUND),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.resolve_pipeline);
+        uniform.record_command(&mut render_pass);
+        render_pass.set_bind_group(1, &self.framebuffer_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
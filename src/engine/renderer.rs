@@ -0,0 +1,370 @@
+use std::{iter, task::Poll};
+
+use super::{
+    filter::FilterUniformData, lines::LinePipeline, picking::Picker, pipeline::PointcloudPipeline, postprocess::PostProcess, profile_view::ProfileView,
+    raster::ComputeRasterizer, splat::HighQualitySplats, *,
+};
+use crate::{pointcloud::Pointcloud, profile::ProfilePoint};
+
+pub const BACKGROUND: wgpu::Color = wgpu::Color {
+    r: 0.,
+    g: 0.5,
+    b: 0.5,
+    a: 1.0,
+};
+
+/// Everything needed to draw a frame that doesn't depend on a window, so the
+/// same code path can target a surface or an offscreen texture.
+pub struct Renderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    width: u32,
+    height: u32,
+
+    uniform: Uniform,
+    point_size: PointSize,
+    pointcloud: Option<Pointcloud>,
+    pointcloud_pipeline: PointcloudPipeline,
+    compute_rasterizer: Option<ComputeRasterizer>, // only built for RasterBackend::Compute
+    splats: SplatSettings,
+    clipping: ClipSettings,
+    filter: FilterSettings,
+    high_quality_splats: HighQualitySplats,
+    depth_view: wgpu::TextureView,
+    depth_texture: wgpu::Texture,
+    post_process: PostProcess,
+    picker: Picker,
+    lines: LinePipeline,
+    profile_view: ProfileView,
+}
+
+impl Renderer {
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self::with_backend(device, queue, format, camera, width, height, RasterBackend::Pipeline)
+    }
+
+    pub fn with_backend(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+        backend: RasterBackend,
+    ) -> Self {
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("uniform_bind_group_layout"),
+        });
+        let uniform = Uniform::new(&queue, &device, &uniform_bind_group_layout, camera, width, height);
+        let pointcloud_pipeline = PointcloudPipeline::new(&device, &uniform_bind_group_layout, format);
+        let compute_rasterizer = (backend == RasterBackend::Compute)
+            .then(|| ComputeRasterizer::new(&device, &uniform_bind_group_layout, format, width, height));
+        let high_quality_splats = HighQualitySplats::new(&device, &uniform_bind_group_layout, format, width, height);
+        let (depth_texture, depth_view) = Self::create_depth_texture(&device, width, height);
+        let post_process = PostProcess::new(&device, format, width, height, &depth_view);
+        let picker = Picker::new(&device, &uniform_bind_group_layout, width, height);
+        let lines = LinePipeline::new(&device, &uniform_bind_group_layout, format);
+        let profile_view = ProfileView::new(&device, &queue, &uniform_bind_group_layout, format, width, height);
+
+        Self {
+            device,
+            queue,
+            width,
+            height,
+            uniform,
+            point_size: PointSize::default(),
+            pointcloud: None,
+            pointcloud_pipeline,
+            compute_rasterizer,
+            splats: SplatSettings::default(),
+            clipping: ClipSettings::default(),
+            filter: FilterSettings::default(),
+            high_quality_splats,
+            depth_view,
+            depth_texture,
+            post_process,
+            picker,
+            lines,
+            profile_view,
+        }
+    }
+
+    fn create_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[wgpu::TextureFormat::Depth32Float],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (depth_texture, depth_view)
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn backend(&self) -> RasterBackend {
+        match self.compute_rasterizer {
+            Some(_) => RasterBackend::Compute,
+            None => RasterBackend::Pipeline,
+        }
+    }
+
+    pub fn pointcloud(&self) -> Option<&Pointcloud> {
+        self.pointcloud.as_ref()
+    }
+
+    pub fn set_pointcloud(&mut self, pointcloud: Pointcloud) {
+        let (min, max) = pointcloud.bounds();
+        let (intensity_min, intensity_max) = pointcloud.intensity_range();
+        let data = &mut self.uniform.camera_uniform;
+        data.elevation_min = min.z;
+        data.elevation_max = max.z;
+        data.intensity_min = intensity_min;
+        data.intensity_max = intensity_max;
+
+        if let Some(compute_rasterizer) = &mut self.compute_rasterizer {
+            compute_rasterizer.set_pointcloud(&self.device, &pointcloud);
+        }
+        self.pointcloud = Some(pointcloud);
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.uniform.camera_uniform.color_mode()
+    }
+
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.uniform.camera_uniform.set_color_mode(mode);
+    }
+
+    pub fn point_size(&self) -> &PointSize {
+        &self.point_size
+    }
+
+    pub fn point_size_mut(&mut self) -> &mut PointSize {
+        &mut self.point_size
+    }
+
+    pub fn splats(&self) -> &SplatSettings {
+        &self.splats
+    }
+
+    pub fn splats_mut(&mut self) -> &mut SplatSettings {
+        &mut self.splats
+    }
+
+    pub fn clipping(&self) -> &ClipSettings {
+        &self.clipping
+    }
+
+    pub fn clipping_mut(&mut self) -> &mut ClipSettings {
+        &mut self.clipping
+    }
+
+    pub fn filter(&self) -> &FilterSettings {
+        &self.filter
+    }
+
+    pub fn filter_mut(&mut self) -> &mut FilterSettings {
+        &mut self.filter
+    }
+
+    pub fn edl(&self) -> &EdlSettings {
+        &self.post_process.edl
+    }
+
+    pub fn edl_mut(&mut self) -> &mut EdlSettings {
+        &mut self.post_process.edl
+    }
+
+    pub fn ssao(&self) -> &SsaoSettings {
+        &self.post_process.ssao
+    }
+
+    pub fn ssao_mut(&mut self) -> &mut SsaoSettings {
+        &mut self.post_process.ssao
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        (self.depth_texture, self.depth_view) = Self::create_depth_texture(&self.device, width, height);
+        self.post_process.resize(&self.device, width, height, &self.depth_view);
+        self.high_quality_splats.resize(&self.device, width, height);
+        self.picker.resize(&self.device, width, height);
+        self.profile_view.resize(width, height);
+        if let Some(compute_rasterizer) = &mut self.compute_rasterizer {
+            compute_rasterizer.resize(&self.device, width, height, self.pointcloud.as_ref());
+        }
+        self.uniform.camera_uniform.width = width as f32;
+        self.uniform.camera_uniform.height = height as f32;
+    }
+
+    /// Replaces the lines drawn over the frame, two vertices per segment
+    pub fn set_lines(&mut self, vertices: &[LineVertex]) {
+        self.lines.set_lines(&self.device, &self.queue, vertices);
+    }
+
+    /// Shows `points` unrolled in an inset at the bottom of the frame, none hides it
+    pub fn set_profile(&mut self, points: &[ProfilePoint]) {
+        self.profile_view.set_profile(&self.device, &self.queue, points);
+    }
+
+    /// Starts picking the point under pixel `(x, y)`, with the camera of the
+    /// last `update`. Returns false if there is nothing to pick or a pick is
+    /// already in flight.
+    pub fn request_pick(&mut self, x: u32, y: u32) -> bool {
+        match &self.pointcloud {
+            Some(pointcloud) if !pointcloud.is_empty() => {
+                self.picker.request(&self.device, &self.queue, &self.uniform, pointcloud, x, y)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the last `request_pick` is still waiting on the GPU
+    pub fn is_picking(&self) -> bool {
+        self.picker.in_flight()
+    }
+
+    /// Result of the last `request_pick`, ready once the GPU is done with it.
+    /// `Ready(None)` means there was no point under the cursor.
+    pub fn poll_pick(&mut self) -> Poll<Option<PickedPoint>> {
+        self.picker.poll(&self.device).map(|index| {
+            let pointcloud = self.pointcloud.as_ref()?;
+            let index = index? as usize;
+            (index < pointcloud.len()).then(|| PickedPoint::new(pointcloud, index))
+        })
+    }
+
+    pub fn update(&mut self, camera: &Camera) {
+        self.uniform.camera_uniform.set_point_size(&self.point_size);
+        self.uniform.camera_uniform.depth_epsilon = self.splats.depth_epsilon;
+        self.uniform.update(camera, &self.queue);
+        self.uniform.update_clipping(&self.clipping, &self.queue);
+        let filter = match &self.pointcloud {
+            Some(pointcloud) => FilterUniformData::new(&self.filter, pointcloud.transform(), pointcloud.gps_origin()),
+            None => FilterUniformData::default(),
+        };
+        self.uniform.update_filter(&filter, &self.queue);
+        self.post_process.update(&camera.projection, &self.queue);
+        self.profile_view.update(&self.point_size, self.color_mode(), &self.queue);
+    }
+
+    pub fn render(&self, view: &wgpu::TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        // Splat modes only apply to the hardware rasteriser
+        match (&self.compute_rasterizer, &self.pointcloud, self.splats.mode) {
+            (Some(compute_rasterizer), _, _) => compute_rasterizer.record_command(
+                &mut encoder,
+                &self.uniform,
+                self.post_process.scene_view(),
+                &self.depth_view,
+            ),
+            (None, Some(pointcloud), SplatMode::HighQuality) => self.high_quality_splats.record_command(
+                &mut encoder,
+                &self.uniform,
+                pointcloud,
+                self.post_process.scene_view(),
+                &self.depth_view,
+            ),
+            (None, pointcloud, _) => {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.post_process.scene_view(),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(BACKGROUND),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &self.depth_view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Store,
+                        }),
+                        stencil_ops: None,
+                    }),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+
+                if let Some(pointcloud) = pointcloud {
+                    self.pointcloud_pipeline.record_command(&mut render_pass);
+                    self.uniform.record_command(&mut render_pass);
+                    pointcloud.record_command(&mut render_pass);
+                }
+            }
+        }
+
+        self.post_process.record_command(&mut encoder, view);
+        self.lines.record_command(&mut encoder, &self.uniform, view);
+        self.profile_view.record_command(&mut encoder, &self.pointcloud_pipeline, view, &self.depth_view);
+
+        self.queue.submit(iter::once(encoder.finish()));
+    }
+}
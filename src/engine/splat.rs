@@ -0,0 +1,284 @@
+use super::{pipeline::POINT_BUFFERS, renderer::BACKGROUND, Encode, Uniform};
+use crate::pointcloud::Pointcloud;
+
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SplatMode {
+    #[default]
+    Standard, // opaque splats with a hard depth test
+    HighQuality, // depth pre-pass, then weighted blending of the splats in front
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SplatSettings {
+    pub mode: SplatMode,
+    pub depth_epsilon: f32, // world units, splats this close to the front one get blended
+}
+
+impl Default for SplatSettings {
+    fn default() -> Self {
+        Self {
+            mode: SplatMode::Standard,
+            depth_epsilon: 0.01,
+        }
+    }
+}
+
+/// Potree's high quality splats: depth pre-pass, additive accumulation of
+/// weighted colours and a normalisation pass into the scene texture.
+pub struct HighQualitySplats {
+    depth_pipeline: wgpu::RenderPipeline,
+    accumulate_pipeline: wgpu::RenderPipeline,
+    normalize_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    accumulation_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl HighQualitySplats {
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let point_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pointcloud Shader"),
+            source: wgpu::ShaderSource::Wgsl(super::common_shader!("intensity.wgsl").into()),
+        });
+        let point_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Splat Pipeline Layout"),
+            bind_group_layouts: &[uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_point_pipeline = |label, vertex_entry, fragment_entry, targets: &[Option<wgpu::ColorTargetState>], depth_write_enabled, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&point_layout),
+                vertex: wgpu::VertexState {
+                    module: &point_shader,
+                    entry_point: Some(vertex_entry),
+                    buffers: POINT_BUFFERS,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &point_shader,
+                    entry_point: Some(fragment_entry),
+                    targets,
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let depth_pipeline = create_point_pipeline(
+            "Splat Depth Pipeline", "vs_depth", "fs_depth", &[], true, wgpu::CompareFunction::Less,
+        );
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let accumulate_pipeline = create_point_pipeline(
+            "Splat Accumulate Pipeline",
+            "vs_main",
+            "fs_accumulate",
+            &[Some(wgpu::ColorTargetState {
+                format: ACCUMULATION_FORMAT,
+                blend: Some(wgpu::BlendState { color: additive, alpha: additive }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            false,
+            wgpu::CompareFunction::LessEqual,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("splat_bind_group_layout"),
+        });
+        let normalize_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Splat Normalize Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/splat.wgsl").into()),
+        });
+        let normalize_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Splat Normalize Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let normalize_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Splat Normalize Pipeline"),
+            layout: Some(&normalize_layout),
+            vertex: wgpu::VertexState {
+                module: &normalize_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &normalize_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let (accumulation_view, bind_group) = Self::create_targets(device, &bind_group_layout, width, height);
+
+        Self {
+            depth_pipeline,
+            accumulate_pipeline,
+            normalize_pipeline,
+            bind_group_layout,
+            accumulation_view,
+            bind_group,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::TextureView, wgpu::BindGroup) {
+        let accumulation_view = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("splat accumulation texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ACCUMULATION_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&accumulation_view),
+                },
+            ],
+            label: Some("splat_bind_group"),
+        });
+
+        (accumulation_view, bind_group)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        (self.accumulation_view, self.bind_group) = Self::create_targets(device, &self.bind_group_layout, width, height);
+    }
+
+    pub fn record_command(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        uniform: &Uniform,
+        pointcloud: &Pointcloud,
+        scene_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Splat Depth Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.depth_pipeline);
+            uniform.record_command(&mut render_pass);
+            pointcloud.record_command(&mut render_pass);
+        }
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Splat Accumulate Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.accumulation_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.accumulate_pipeline);
+            uniform.record_command(&mut render_pass);
+            pointcloud.record_command(&mut render_pass);
+        }
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Splat Normalize Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(BACKGROUND),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.normalize_pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use glam::Mat4;
+
+use super::Projection;
+
+pub const MAX_SSAO_SAMPLES: u32 = 64;
+const AO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    pub samples: u32, // clamped to MAX_SSAO_SAMPLES
+    pub radius: f32, // world units
+    pub strength: f32,
+    pub bias: f32, // cosine below which a sample doesn't occlude
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            samples: 16,
+            radius: 0.05,
+            strength: 2.,
+            bias: 0.1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniformData {
+    projection: Mat4,
+    inverse_projection: Mat4,
+    radius: f32,
+    strength: f32,
+    bias: f32,
+    samples: u32,
+}
+
+/// Ambient occlusion from the depth buffer, followed by a bilateral blur.
+/// The blurred result is read by the composite in `PostProcess`.
+pub struct SsaoPass {
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    ao_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    raw_view: wgpu::TextureView,
+    blurred_view: wgpu::TextureView,
+    ao_bind_group: wgpu::BindGroup,
+    blur_bind_group: wgpu::BindGroup,
+}
+
+impl SsaoPass {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, depth_view: &wgpu::TextureView) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(2),
+            ],
+            label: Some("ssao_bind_group_layout"),
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SSAO Uniform Buffer"),
+            size: std::mem::size_of::<SsaoUniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SSAO Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/ssao.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SSAO Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, entry_point| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main".into(),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: AO_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let ao_pipeline = create_pipeline("SSAO Pipeline", "fs_ao");
+        let blur_pipeline = create_pipeline("SSAO Blur Pipeline", "fs_blur");
+
+        let (raw_view, blurred_view, ao_bind_group, blur_bind_group) = Self::create_targets(
+            device, &bind_group_layout, &uniform_buffer, width, height, depth_view,
+        );
+
+        Self {
+            uniform_buffer,
+            bind_group_layout,
+            ao_pipeline,
+            blur_pipeline,
+            raw_view,
+            blurred_view,
+            ao_bind_group,
+            blur_bind_group,
+        }
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+        depth_view: &wgpu::TextureView,
+    ) -> (wgpu::TextureView, wgpu::TextureView, wgpu::BindGroup, wgpu::BindGroup) {
+        let create_view = |label| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: AO_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default());
+        let raw_view = create_view("ssao texture");
+        let blurred_view = create_view("ssao blurred texture");
+
+        // The AO pass doesn't read binding 2, but it has to be bound to something
+        // other than its own target
+        let create_bind_group = |label, ao_view| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(ao_view),
+                },
+            ],
+            label: Some(label),
+        });
+        let ao_bind_group = create_bind_group("ssao_bind_group", &blurred_view);
+        let blur_bind_group = create_bind_group("ssao_blur_bind_group", &raw_view);
+
+        (raw_view, blurred_view, ao_bind_group, blur_bind_group)
+    }
+
+    /// Blurred ambient occlusion, 1 means unoccluded
+    pub fn output_view(&self) -> &wgpu::TextureView {
+        &self.blurred_view
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32, depth_view: &wgpu::TextureView) {
+        (self.raw_view, self.blurred_view, self.ao_bind_group, self.blur_bind_group) = Self::create_targets(
+            device, &self.bind_group_layout, &self.uniform_buffer, width, height, depth_view,
+        );
+    }
+
+    pub fn update(&self, settings: &SsaoSettings, projection: &Projection, queue: &wgpu::Queue) {
+        let matrix = projection.make_matrix();
+        let data = SsaoUniformData {
+            projection: matrix,
+            inverse_projection: matrix.inverse(),
+            radius: settings.radius,
+            strength: settings.strength,
+            bias: settings.bias,
+            samples: settings.samples.min(MAX_SSAO_SAMPLES),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[data]));
+    }
+
+    pub fn record_command(&self, encoder: &mut wgpu::CommandEncoder) {
+        for (label, pipeline, bind_group, target) in [
+            ("SSAO Pass", &self.ao_pipeline, &self.ao_bind_group, &self.raw_view),
+            ("SSAO Blur Pass", &self.blur_pipeline, &self.blur_bind_group, &self.blurred_view),
+        ] {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
@@ -1,5 +1,123 @@
-use super::*;
-use glam::Mat4;
+use super::{clipping::ClipUniformData, filter::FilterUniformData, *};
+use glam::{Mat4, Vec2, Vec4};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Intensity,
+    Elevation,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 2] = [ColorMode::Intensity, ColorMode::Elevation];
+
+    pub fn next(self) -> Self {
+        match self {
+            ColorMode::Intensity => ColorMode::Elevation,
+            ColorMode::Elevation => ColorMode::Intensity,
+        }
+    }
+}
+
+impl std::fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ColorMode::Intensity => "intensity",
+            ColorMode::Elevation => "elevation",
+        })
+    }
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ColorMode::ALL.into_iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown colour mode {name:?}"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PointSizeMode {
+    #[default]
+    Fixed, // pixels
+    Attenuated, // world units, shrinks with distance
+    Adaptive, // follows the local point spacing
+}
+
+impl PointSizeMode {
+    pub fn next(self) -> Self {
+        match self {
+            PointSizeMode::Fixed => PointSizeMode::Attenuated,
+            PointSizeMode::Attenuated => PointSizeMode::Adaptive,
+            PointSizeMode::Adaptive => PointSizeMode::Fixed,
+        }
+    }
+}
+
+impl std::fmt::Display for PointSizeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PointSizeMode::Fixed => "fixed",
+            PointSizeMode::Attenuated => "attenuated",
+            PointSizeMode::Adaptive => "adaptive",
+        })
+    }
+}
+
+impl std::str::FromStr for PointSizeMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [PointSizeMode::Fixed, PointSizeMode::Attenuated, PointSizeMode::Adaptive].into_iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown point size mode {name:?}"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointSize {
+    pub mode: PointSizeMode,
+    pub pixels: f32,
+    pub world_size: f32,
+    pub adaptive_scale: f32, // multiple of the local spacing
+    pub min_pixels: f32,
+    pub max_pixels: f32,
+}
+
+impl Default for PointSize {
+    fn default() -> Self {
+        Self {
+            mode: PointSizeMode::Fixed,
+            pixels: 10.,
+            world_size: 0.02,
+            adaptive_scale: 1.,
+            min_pixels: 1.,
+            max_pixels: 64.,
+        }
+    }
+}
+
+impl PointSize {
+    /// Sets the size used by the current mode
+    pub fn set(&mut self, size: f32) {
+        match self.mode {
+            PointSizeMode::Fixed => self.pixels = size.clamp(self.min_pixels, self.max_pixels),
+            PointSizeMode::Attenuated => self.world_size = size,
+            PointSizeMode::Adaptive => self.adaptive_scale = size,
+        }
+    }
+
+    /// Grows or shrinks the size used by the current mode
+    pub fn scale(&mut self, factor: f32) {
+        match self.mode {
+            PointSizeMode::Fixed => self.pixels = (self.pixels * factor).clamp(self.min_pixels, self.max_pixels),
+            PointSizeMode::Attenuated => self.world_size *= factor,
+            PointSizeMode::Adaptive => self.adaptive_scale *= factor,
+        }
+    }
+}
 
 // We need this for Rust to store our data correctly for the shaders
 #[repr(C)]
@@ -12,6 +130,21 @@ pub struct UniformData {
     pub pixels: f32,
     model: Mat4,
     view_proj: Mat4,
+    color_mode: u32,
+    pub intensity_min: f32,
+    pub intensity_max: f32,
+    pub elevation_min: f32,
+    pub elevation_max: f32,
+    point_size_mode: u32,
+    world_size: f32,
+    adaptive_scale: f32,
+    min_pixels: f32,
+    max_pixels: f32,
+    projection_scale: f32, // pixels covered by one world unit at distance 1
+    pub depth_epsilon: f32, // world units the high quality splats' depth pre-pass is pushed back
+    projection_z: Vec4,
+    pub viewport_origin: Vec2, // pixels, for views drawn in part of the target
+    _padding: Vec2,
 }
 
 impl UniformData {
@@ -23,16 +156,53 @@ impl UniformData {
             pixels: 10.,
             view_proj: Mat4::IDENTITY,
             model: Mat4::IDENTITY,
+            color_mode: ColorMode::default() as u32,
+            intensity_min: 0.,
+            intensity_max: 1.,
+            elevation_min: 0.,
+            elevation_max: 1.,
+            point_size_mode: PointSizeMode::default() as u32,
+            world_size: 0.,
+            adaptive_scale: 1.,
+            min_pixels: 0.,
+            max_pixels: f32::MAX,
+            projection_scale: 1.,
+            depth_epsilon: 0.,
+            projection_z: Vec4::ZERO,
+            viewport_origin: Vec2::ZERO,
+            _padding: Vec2::ZERO,
         }
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
         self.view_proj = camera.build_view_projection_matrix();
+        let projection = camera.projection.make_matrix();
+        self.projection_scale = projection.y_axis.y * self.height * 0.5;
+        self.projection_z = projection.z_axis;
+    }
+
+    pub fn set_point_size(&mut self, point_size: &PointSize) {
+        self.point_size_mode = point_size.mode as u32;
+        self.pixels = point_size.pixels;
+        self.world_size = point_size.world_size;
+        self.adaptive_scale = point_size.adaptive_scale;
+        self.min_pixels = point_size.min_pixels;
+        self.max_pixels = point_size.max_pixels;
+    }
+
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        self.color_mode = mode as u32;
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        ColorMode::ALL[self.color_mode as usize]
     }
 }
 
 pub struct Uniform {
     uniform_buffer: wgpu::Buffer,
+    clip_buffer: wgpu::Buffer,
+    filter_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
     pub camera_uniform: UniformData,
 }
@@ -59,13 +229,41 @@ impl Uniform {
         );
         queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
 
+        let clip_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Clip Buffer"),
+                size: (std::mem::size_of::<ClipUniformData>()) as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        queue.write_buffer(&clip_buffer, 0, bytemuck::cast_slice(&[ClipUniformData::from(&ClipSettings::default())]));
+
+        let filter_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Filter Buffer"),
+                size: (std::mem::size_of::<FilterUniformData>()) as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        queue.write_buffer(&filter_buffer, 0, bytemuck::cast_slice(&[FilterUniformData::default()]));
+
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: uniform_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
                     resource: uniform_buffer.as_entire_binding(),
-                }
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: clip_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: filter_buffer.as_entire_binding(),
+                },
             ],
             label: Some("uniform_bind_group"),
         });
@@ -73,6 +271,8 @@ impl Uniform {
         Uniform { 
             camera_uniform, 
             uniform_buffer,
+            clip_buffer,
+            filter_buffer,
             uniform_bind_group
         }
     }
@@ -81,6 +281,19 @@ impl Uniform {
         self.camera_uniform.update_view_proj(camera);
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
     }
+
+    pub fn update_clipping(&self, clipping: &ClipSettings, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.clip_buffer, 0, bytemuck::cast_slice(&[ClipUniformData::from(clipping)]));
+    }
+
+    pub fn update_filter(&self, filter: &FilterUniformData, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.filter_buffer, 0, bytemuck::cast_slice(&[*filter]));
+    }
+
+    /// For passes that aren't render passes, `Encode` covers the others
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.uniform_bind_group
+    }
 }
 
 impl<'a> Encode<'a> for Uniform {
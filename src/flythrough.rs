@@ -0,0 +1,188 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Camera;
+
+// Samples per segment when measuring the path and drawing it
+const SAMPLES: usize = 32;
+/// Meters per second along a path, unless told otherwise
+pub const PATH_SPEED: f32 = 5.;
+/// Radians per second the camera turns at most along a path, a quarter turn,
+/// so turning on the spot takes time too
+pub const TURN_SPEED: f32 = FRAC_PI_2;
+
+/// A camera pose on a path, in scene coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub eye: Vec3,
+    pub rotation: Quat, // turns -Z to the view direction and Y to up
+    pub focus: f32, // distance to the target
+}
+
+impl Keyframe {
+    pub fn from_camera(camera: &Camera) -> Self {
+        let offset = camera.target - camera.eye;
+        let forward = offset.normalize_or(Vec3::NEG_Z);
+        let right = forward.cross(camera.up).normalize_or(forward.any_orthonormal_vector());
+        let up = right.cross(forward);
+        Self {
+            eye: camera.eye,
+            rotation: Quat::from_mat3(&Mat3::from_cols(right, up, -forward)).normalize(),
+            focus: offset.length().max(f32::EPSILON),
+        }
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.eye;
+        camera.target = self.eye + self.direction() * self.focus;
+        camera.up = self.rotation * Vec3::Y;
+    }
+}
+
+/// Keyframes the camera passes through, smoothly: a Catmull-Rom spline for
+/// the eye, slerp for the rotation
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn push(&mut self, keyframe: Keyframe) {
+        self.keyframes.push(keyframe);
+    }
+
+    pub fn pop(&mut self) -> Option<Keyframe> {
+        self.keyframes.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Pose at `t`, from 0 at the first keyframe to one less than their count
+    /// at the last
+    pub fn sample(&self, t: f32) -> Option<Keyframe> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let t = t.clamp(0., last as f32);
+        let segment = (t.floor() as usize).min(last.saturating_sub(1));
+        let (a, b) = (self.keyframes[segment], self.keyframes[(segment + 1).min(last)]);
+        let local = t - segment as f32;
+
+        // Ends continue as far again, so the curve leaves them heading for their neighbour
+        let before = segment.checked_sub(1).map_or(2. * a.eye - b.eye, |index| self.keyframes[index].eye);
+        let after = self.keyframes.get(segment + 2).map_or(2. * b.eye - a.eye, |keyframe| keyframe.eye);
+        Some(Keyframe {
+            eye: catmull_rom(before, a.eye, b.eye, after, local),
+            rotation: a.rotation.slerp(b.rotation, local),
+            focus: a.focus + (b.focus - a.focus) * local,
+        })
+    }
+
+    /// Eye positions along the curve, for drawing it
+    pub fn polyline(&self) -> Vec<Vec3> {
+        self.samples().iter().map(|keyframe| keyframe.eye).collect()
+    }
+
+    fn samples(&self) -> Vec<Keyframe> {
+        let count = self.keyframes.len().saturating_sub(1) * SAMPLES + 1;
+        (0..count).filter_map(|i| self.sample(i as f32 / SAMPLES as f32)).collect()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2. * p1
+        + (p2 - p0) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (3. * p1 - p0 - 3. * p2 + p3) * t3)
+}
+
+/// Goes along a path at a steady speed, however far apart its keyframes are,
+/// and no faster than `TURN_SPEED` where the camera turns more than it moves
+#[derive(Clone, Debug)]
+pub struct Playback {
+    path: CameraPath,
+    times: Vec<f32>, // seconds to each of the polyline's points
+    elapsed: f32, // seconds so far
+}
+
+impl Playback {
+    /// Plays `path` at `speed` scene units per second
+    pub fn new(path: CameraPath, speed: f32) -> Self {
+        let samples = path.samples();
+        let mut times = vec![0.];
+        for pair in samples.windows(2) {
+            let moving = pair[0].eye.distance(pair[1].eye) / speed.max(f32::EPSILON);
+            // Not acos of the dot product, which loses the small angles between samples
+            let turn = pair[0].rotation.inverse() * pair[1].rotation;
+            let turning = 2. * turn.xyz().length().atan2(turn.w.abs()) / TURN_SPEED;
+            times.push(times[times.len() - 1] + moving.max(turning));
+        }
+        Self { path, times, elapsed: 0. }
+    }
+
+    pub fn path(&self) -> &CameraPath {
+        &self.path
+    }
+
+    /// Seconds from end to end
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or_default()
+    }
+
+    /// Pose `seconds` into the playback
+    pub fn at(&self, seconds: f32) -> Option<Keyframe> {
+        let index = self.times.partition_point(|time| *time <= seconds);
+        let t = match index {
+            0 => 0.,
+            index if index >= self.times.len() => (self.times.len() - 1) as f32,
+            index => {
+                let (start, end) = (self.times[index - 1], self.times[index]);
+                (index - 1) as f32 + (seconds - start) / (end - start).max(f32::EPSILON)
+            }
+        };
+        self.path.sample(t / SAMPLES as f32)
+    }
+
+    /// Pose `dt` seconds further on, `None` once past the end
+    pub fn advance(&mut self, dt: f32) -> Option<Keyframe> {
+        if self.elapsed > self.duration() {
+            return None;
+        }
+        let keyframe = self.at(self.elapsed);
+        // Lands on the end before stopping
+        self.elapsed = if self.elapsed < self.duration() {
+            (self.elapsed + dt).min(self.duration())
+        } else {
+            f32::INFINITY
+        };
+        keyframe
+    }
+}
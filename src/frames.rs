@@ -0,0 +1,202 @@
+//! Renders a camera path offscreen frame by frame, at a fixed timestep
+//! however long each frame takes, for turning flythroughs into videos.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use dotenvy_macro::dotenv;
+use glam::Vec3;
+
+use crate::{
+    engine::{headless_device, Camera, Offscreen, Projection, RasterBackend, Renderer, DEFAULT_FAR, DEFAULT_FOVY, DEFAULT_NEAR},
+    flythrough::{CameraPath, Keyframe, Playback, PATH_SPEED},
+    pointcloud::Pointcloud,
+};
+
+/// Where the frames go
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameOutput {
+    /// Numbered PNGs in this directory
+    Png(PathBuf),
+    /// Raw RGBA8 frames, top row first, to the standard input of this shell
+    /// command, such as `ffmpeg -f rawvideo -pix_fmt rgba -s 1920x1080 -r 30 -i - out.mp4`
+    Pipe(String),
+}
+
+/// Frames of a camera path at a fixed size and frame rate
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameExport {
+    pub path: CameraPath,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f32,
+    pub speed: f32, // meters per second
+    pub near: f32, // clip planes, in scene units
+    pub far: f32,
+    pub fovy: f32, // radians
+    pub output: FrameOutput,
+    pub backend: RasterBackend,
+}
+
+impl FrameExport {
+    /// Export asked for on the command line with `--frames camera_path.json`,
+    /// and optionally `--size 1920x1080`, `--fps 30`, `--speed 5` in meters
+    /// per second, `--near 0.01`, `--far 1000`, `--fov 60` in degrees as the
+    /// viewer opens with, `--compute`, then `--out frames` or `--pipe "command"`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let value = |flag: &str| args.iter().skip_while(|arg| *arg != flag).nth(1).map(String::as_str);
+        let Some(file) = value("--frames") else {
+            return Ok(None);
+        };
+        let json = std::fs::read_to_string(file).map_err(|e| format!("Couldn't read {file}: {e}"))?;
+        let path = CameraPath::from_json(&json).map_err(|e| format!("Invalid camera path in {file}: {e}"))?;
+        if path.len() < 2 {
+            return Err(format!("{file} needs at least two keyframes"));
+        }
+
+        let (width, height) = match value("--size") {
+            Some(size) => size.split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                .filter(|(width, height)| *width > 0 && *height > 0)
+                .ok_or_else(|| format!("Invalid size {size:?}, expected such as 1920x1080"))?,
+            None => (1920, 1080),
+        };
+        let positive = |flag: &str, default: f32| match value(flag) {
+            Some(text) => text.parse::<f32>().ok()
+                .filter(|value| value.is_finite() && *value > 0.)
+                .ok_or_else(|| format!("Invalid {flag} {text:?}")),
+            None => Ok(default),
+        };
+        let (near, far) = (positive("--near", DEFAULT_NEAR)?, positive("--far", DEFAULT_FAR)?);
+        if near >= far {
+            return Err(format!("The near plane at {near} needs to be closer than the far one at {far}"));
+        }
+        let fovy = positive("--fov", DEFAULT_FOVY.to_degrees())?;
+        if fovy >= 180. {
+            return Err(format!("Invalid --fov {fovy}, expected less than 180 degrees"));
+        }
+        let output = match (value("--pipe"), value("--out")) {
+            (Some(command), _) => FrameOutput::Pipe(command.to_owned()),
+            (None, out) => FrameOutput::Png(PathBuf::from(out.unwrap_or("frames"))),
+        };
+
+        Ok(Some(Self {
+            path,
+            width,
+            height,
+            fps: positive("--fps", 30.)?,
+            speed: positive("--speed", PATH_SPEED)?,
+            near,
+            far,
+            fovy: fovy.to_radians(),
+            output,
+            backend: if args.iter().any(|arg| arg == "--compute") { RasterBackend::Compute } else { RasterBackend::Pipeline },
+        }))
+    }
+
+    /// Camera pose of every frame, `meter` scene units being a meter
+    pub fn poses(&self, meter: f32) -> Vec<Keyframe> {
+        let playback = Playback::new(self.path.clone(), self.speed * meter);
+        let step = 1. / self.fps;
+        // Counted rather than accumulated, so rounding doesn't add a frame at the end
+        let steps = (playback.duration() / step - 1e-3).ceil().max(0.) as usize;
+        (0..=steps)
+            .filter_map(|frame| playback.at((frame as f32 * step).min(playback.duration())))
+            .collect()
+    }
+
+    /// Loads the pointcloud the viewer shows and renders it along the path,
+    /// returns how many frames were written
+    pub async fn run(&self) -> Result<usize, Box<dyn Error>> {
+        let (device, queue) = headless_device().await.ok_or("No GPU adapter available")?;
+        let pointcloud = Pointcloud::from_las(&device, &queue, dotenv!("POINTCLOUD_URL")).await?;
+        self.render(device, queue, pointcloud)
+    }
+
+    /// Renders `pointcloud` along the path, returns how many frames were written
+    pub fn render(&self, device: wgpu::Device, queue: wgpu::Queue, pointcloud: Pointcloud) -> Result<usize, Box<dyn Error>> {
+        let poses = self.poses(pointcloud.transform().scale.z as f32);
+        let mut camera = Camera {
+            eye: Vec3::X, // set by each pose
+            target: Vec3::ZERO,
+            up: Vec3::Z,
+            projection: Projection::perspective(self.near, self.far, self.width as f32, self.height as f32, self.fovy),
+        };
+        let target = Offscreen::new(&device, self.width, self.height);
+        let mut renderer = Renderer::with_backend(device, queue, Offscreen::FORMAT, &camera, self.width, self.height, self.backend);
+        renderer.set_pointcloud(pointcloud);
+
+        let mut sink = Sink::open(&self.output)?;
+        for (index, pose) in poses.iter().enumerate() {
+            pose.apply(&mut camera);
+            renderer.update(&camera);
+            renderer.render(target.view());
+            let pixels = target.read_pixels(renderer.device(), renderer.queue());
+            sink.write(index, self.width, self.height, &pixels)?;
+            log::info!("Frame {} of {}", index + 1, poses.len());
+        }
+        sink.close()?;
+
+        Ok(poses.len())
+    }
+}
+
+/// Name of the PNG of frame `index`, numbered from 1
+pub fn frame_name(index: usize) -> String {
+    format!("frame_{:05}.png", index + 1)
+}
+
+enum Sink {
+    Png(PathBuf),
+    Pipe(Child),
+}
+
+impl Sink {
+    fn open(output: &FrameOutput) -> Result<Self, Box<dyn Error>> {
+        Ok(match output {
+            FrameOutput::Png(directory) => {
+                std::fs::create_dir_all(directory)?;
+                Sink::Png(directory.clone())
+            }
+            FrameOutput::Pipe(command) => {
+                let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+                Sink::Pipe(Command::new(shell).args([flag, command]).stdin(Stdio::piped()).spawn()?)
+            }
+        })
+    }
+
+    fn write(&mut self, index: usize, width: u32, height: u32, pixels: &[u8]) -> Result<(), Box<dyn Error>> {
+        match self {
+            Sink::Png(directory) => write_png(&directory.join(frame_name(index)), width, height, pixels),
+            Sink::Pipe(child) => {
+                child.stdin.as_mut().ok_or("Encoder has no input")?.write_all(pixels)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Waits for the encoder to finish the video
+    fn close(self) -> Result<(), Box<dyn Error>> {
+        if let Sink::Pipe(mut child) = self {
+            drop(child.stdin.take());
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(format!("Encoder failed with {status}").into());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
@@ -1,36 +1,107 @@
-mod points;
-mod engine;
-mod pointcloud;
+pub mod points;
+pub mod engine;
+pub mod pointcloud;
+pub mod measure;
+pub mod profile;
+pub mod api;
+pub mod bookmarks;
+pub mod controls;
+pub mod flythrough;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod frames;
+pub mod permalink;
 mod app;
 mod platform;
 
 use app::App;
 use winit::event_loop::EventLoop;
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
-
 
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
+/// Opens the viewer on the default dataset, or renders the frames of a camera
+/// path when asked to on the command line. The web opens viewers with
+/// `new Viewer(element)` instead.
+#[cfg(not(target_arch = "wasm32"))]
 pub async fn run() {
-    #[cfg(target_arch = "wasm32")]  {
-        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-        console_log::init_with_level(log::Level::Warn).expect("Could't initialize logger");
-    }
-    #[cfg(not(target_arch = "wasm32"))]
     env_logger::init();
 
-    let event_loop = EventLoop::new().unwrap();
-
-    // State::new uses async code, so we're going to wait for it to finish
+    let args: Vec<String> = std::env::args().collect();
+    match frames::FrameExport::from_args(&args) {
+        Ok(Some(export)) => {
+            match export.run().await {
+                Ok(count) => platform::print(&format!("Rendered {count} frames")),
+                Err(e) => log::error!("Couldn't render frames: {e}"),
+            }
+            return;
+        }
+        Ok(None) => (),
+        Err(e) => {
+            log::error!("{e}");
+            return;
+        }
+    }
 
-    let mut app = App::new();
+    let backend = if args.iter().any(|arg| arg == "--compute") {
+        engine::RasterBackend::Compute
+    } else {
+        engine::RasterBackend::Pipeline
+    };
 
-    #[cfg(target_arch = "wasm32")]{
-        use winit::platform::web::EventLoopExtWebSys;
-        app.make_state(&event_loop).await;
-        let _ = event_loop.spawn_app(app);    
+    let channel = std::rc::Rc::new(api::Channel::default());
+    if let Some(path) = args.iter().skip_while(|arg| *arg != "--bindings").nth(1) {
+        match controls::Bindings::from_file(path.as_ref()) {
+            Ok(bindings) => channel.push(api::Command::SetBindings(bindings)),
+            Err(e) => log::error!("{e}"),
+        }
+    }
+    if let Some(path) = args.iter().skip_while(|arg| *arg != "--bookmarks").nth(1) {
+        let bookmarks = std::fs::read_to_string(path).map_err(|e| e.to_string())
+            .and_then(|json| bookmarks::Bookmarks::from_json(&json).map_err(|e| e.to_string()));
+        match bookmarks {
+            Ok(bookmarks) => channel.push(api::Command::SetBookmarks(bookmarks)),
+            Err(e) => log::error!("Couldn't load bookmarks from {path}: {e}"),
+        }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    let event_loop = EventLoop::new().unwrap();
+    let mut app = App::new(backend, app::ViewerOptions {
+        channel,
+        dataset: None,
+    });
     let _ = event_loop.run_app(&mut app);
 }
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    // Only one event loop can run on a page, every viewer shares it
+    static EVENT_LOOP: std::cell::RefCell<Option<winit::event_loop::EventLoopProxy<app::UserEvent>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Opens a viewer in the page's event loop, started with the first
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn open(options: app::ViewerOptions) -> Result<(), String> {
+    use winit::platform::web::EventLoopExtWebSys;
+
+    let proxy = EVENT_LOOP.with_borrow_mut(|proxy| -> Result<_, String> {
+        if let Some(proxy) = proxy {
+            return Ok(proxy.clone());
+        }
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init_with_level(log::Level::Warn).expect("Could't initialize logger");
+
+        let event_loop = EventLoop::<app::UserEvent>::with_user_event().build().map_err(|e| e.to_string())?;
+        let created = event_loop.create_proxy();
+        event_loop.spawn_app(App::new(engine::RasterBackend::Pipeline, created.clone()));
+        Ok(proxy.insert(created).clone())
+    })?;
+    proxy.send_event(app::UserEvent::Open(options)).map_err(|_| "The viewers' event loop has stopped".to_owned())
+}
+
+/// Has the page's event loop drop the engines of closed viewers
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn wake() {
+    EVENT_LOOP.with_borrow(|proxy| {
+        if let Some(proxy) = proxy {
+            let _ = proxy.send_event(app::UserEvent::Closed);
+        }
+    });
+}
@@ -0,0 +1,257 @@
+use std::fmt::{self, Write};
+
+use glam::DVec3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasureKind {
+    Distance, // between two points
+    Polyline, // length of an open path
+    Area, // of a closed, planar polygon
+    Height, // vertical difference between two points
+    Angle, // at the second of three points
+}
+
+impl MeasureKind {
+    pub const ALL: [MeasureKind; 5] = [
+        MeasureKind::Distance,
+        MeasureKind::Polyline,
+        MeasureKind::Area,
+        MeasureKind::Height,
+        MeasureKind::Angle,
+    ];
+
+    /// Points after which the measurement is done, `None` if it has to be finished by hand
+    pub fn point_count(self) -> Option<usize> {
+        match self {
+            MeasureKind::Distance | MeasureKind::Height => Some(2),
+            MeasureKind::Angle => Some(3),
+            MeasureKind::Polyline | MeasureKind::Area => None,
+        }
+    }
+
+    pub fn min_points(self) -> usize {
+        match self {
+            MeasureKind::Area | MeasureKind::Angle => 3,
+            _ => 2,
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            MeasureKind::Area => "m²",
+            MeasureKind::Angle => "°",
+            _ => "m",
+        }
+    }
+
+    /// Cycles through the tools, `None` being no tool at all
+    pub fn next(tool: Option<MeasureKind>) -> Option<MeasureKind> {
+        match tool {
+            None => Some(Self::ALL[0]),
+            Some(kind) => Self::ALL.get(Self::ALL.iter().position(|k| *k == kind)? + 1).copied(),
+        }
+    }
+}
+
+impl fmt::Display for MeasureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MeasureKind::Distance => "distance",
+            MeasureKind::Polyline => "polyline",
+            MeasureKind::Area => "area",
+            MeasureKind::Height => "height",
+            MeasureKind::Angle => "angle",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Points picked for a measurement, in source coordinates
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub kind: MeasureKind,
+    pub points: Vec<DVec3>,
+}
+
+impl Measurement {
+    pub fn new(kind: MeasureKind) -> Self {
+        Self { kind, points: Vec::new() }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.kind.point_count().is_some_and(|count| self.points.len() >= count)
+    }
+
+    /// In meters, square meters or degrees, `None` until there are enough points
+    pub fn value(&self) -> Option<f64> {
+        let points = &self.points;
+        if points.len() < self.kind.min_points() {
+            return None;
+        }
+
+        let value = match self.kind {
+            MeasureKind::Distance => points[0].distance(points[1]),
+            MeasureKind::Polyline => points.windows(2).map(|pair| pair[0].distance(pair[1])).sum(),
+            MeasureKind::Area => {
+                // Half the norm of the vector area, around the first point to keep the cross products small
+                let origin = points[0];
+                let normal: DVec3 = points[1..]
+                    .windows(2)
+                    .map(|pair| (pair[0] - origin).cross(pair[1] - origin))
+                    .sum();
+                normal.length() * 0.5
+            }
+            MeasureKind::Height => points[1].z - points[0].z,
+            MeasureKind::Angle => (points[0] - points[1]).angle_between(points[2] - points[1]).to_degrees(),
+        };
+
+        Some(value)
+    }
+
+    /// Pairs of points to draw, closing the polygon of areas
+    pub fn segments(&self) -> Vec<(DVec3, DVec3)> {
+        let mut segments: Vec<_> = self.points.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        match self.kind {
+            MeasureKind::Area if self.points.len() >= 3 => {
+                segments.push((self.points[self.points.len() - 1], self.points[0]));
+            }
+            MeasureKind::Height if self.points.len() == 2 => {
+                // Vertical and horizontal legs instead of the straight line
+                let corner = DVec3::new(self.points[1].x, self.points[1].y, self.points[0].z);
+                segments = vec![(self.points[0], corner), (corner, self.points[1])];
+            }
+            _ => (),
+        }
+
+        segments
+    }
+
+    /// Where the value is shown, the middle of the points
+    pub fn label_anchor(&self) -> Option<DVec3> {
+        match self.kind {
+            MeasureKind::Angle if self.points.len() >= 2 => Some(self.points[1]),
+            _ if !self.points.is_empty() => Some(self.points.iter().sum::<DVec3>() / self.points.len() as f64),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value() {
+            Some(value) => write!(f, "{} {:.3} {}", self.kind, value, self.kind.unit()),
+            None => write!(f, "{} ({} points)", self.kind, self.points.len()),
+        }
+    }
+}
+
+/// Interactive state of the measurement tools: the measurement being
+/// picked and the finished ones
+#[derive(Clone, Debug, Default)]
+pub struct Measurements {
+    tool: Option<MeasureKind>,
+    current: Option<Measurement>,
+    finished: Vec<Measurement>,
+}
+
+impl Measurements {
+    pub fn tool(&self) -> Option<MeasureKind> {
+        self.tool
+    }
+
+    /// Switches tool, dropping the measurement in progress
+    pub fn set_tool(&mut self, tool: Option<MeasureKind>) {
+        self.tool = tool;
+        self.current = None;
+    }
+
+    pub fn current(&self) -> Option<&Measurement> {
+        self.current.as_ref()
+    }
+
+    pub fn finished(&self) -> &[Measurement] {
+        &self.finished
+    }
+
+    /// Finished measurements followed by the one in progress
+    pub fn iter(&self) -> impl Iterator<Item = &Measurement> {
+        self.finished.iter().chain(self.current.as_ref())
+    }
+
+    /// Adds a picked point to the current measurement, finishing it once it
+    /// has all its points. Returns false if no tool is active.
+    pub fn add_point(&mut self, point: DVec3) -> bool {
+        let Some(tool) = self.tool else {
+            return false;
+        };
+
+        let current = self.current.get_or_insert_with(|| Measurement::new(tool));
+        current.points.push(point);
+        if current.is_complete() {
+            self.finish();
+        }
+
+        true
+    }
+
+    /// Ends the measurement in progress, dropped if it doesn't have enough points
+    pub fn finish(&mut self) {
+        if let Some(current) = self.current.take() {
+            if current.value().is_some() {
+                self.finished.push(current);
+            }
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.current = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.finished.clear();
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            #[serde(flatten)]
+            measurement: &'a Measurement,
+            value: Option<f64>,
+            unit: &'static str,
+        }
+
+        let entries: Vec<_> = self.finished
+            .iter()
+            .map(|measurement| Entry {
+                measurement,
+                value: measurement.value(),
+                unit: measurement.kind.unit(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&entries)
+    }
+
+    /// One row per point, the value repeated on each row of its measurement
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("measurement,kind,point,x,y,z,value,unit\n");
+        for (i, measurement) in self.finished.iter().enumerate() {
+            let value = measurement.value().map(|value| value.to_string()).unwrap_or_default();
+            for (j, point) in measurement.points.iter().enumerate() {
+                let _ = writeln!(
+                    csv,
+                    "{i},{},{j},{},{},{},{value},{}",
+                    measurement.kind,
+                    point.x,
+                    point.y,
+                    point.z,
+                    measurement.kind.unit(),
+                );
+            }
+        }
+
+        csv
+    }
+}
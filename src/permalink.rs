@@ -0,0 +1,113 @@
+//! What the viewer shows, written into the fragment of the page URL so a link
+//! opens the same view, such as
+//! `#url=https%3A%2F%2Fexample.com%2Fsite.las&view=1.5,2,0.3,1.2,0.5,40&color=elevation`
+
+use glam::Vec3;
+
+use crate::{bookmarks::CameraView, engine::{ColorMode, FilterSettings}};
+
+/// View state in a link, each part left out keeps what the viewer has
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Permalink {
+    pub dataset: Option<String>, // URL of the LAS file
+    pub view: Option<CameraView>,
+    pub color_mode: Option<ColorMode>,
+    pub filter: Option<FilterSettings>,
+}
+
+impl Permalink {
+    /// URL fragment, without the `#`
+    pub fn to_hash(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(dataset) = &self.dataset {
+            parts.push(format!("url={}", encode(dataset)));
+        }
+        if let Some(view) = &self.view {
+            let [x, y, z] = view.target.to_array();
+            parts.push(format!("view={x},{y},{z},{},{},{}", view.theta, view.phi, view.farness));
+            if view.orthographic {
+                parts.push("projection=orthographic".to_owned());
+            }
+        }
+        if let Some(mode) = self.color_mode {
+            parts.push(format!("color={mode}"));
+        }
+        if let Some(filter) = &self.filter {
+            parts.push(format!("filter={}", encode(&filter_json(filter))));
+        }
+        parts.join("&")
+    }
+
+    /// Reads a URL fragment, with or without the `#`. Unknown parts are
+    /// skipped, so links from later versions still open.
+    pub fn from_hash(hash: &str) -> Result<Self, String> {
+        let mut permalink = Self::default();
+        let mut orthographic = false;
+        for part in hash.trim_start_matches('#').split('&').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            let value = decode(value)?;
+            match key {
+                "url" => permalink.dataset = Some(value),
+                "view" => {
+                    let numbers = value.split(',')
+                        .map(|number| number.parse::<f32>().ok().filter(|number| number.is_finite()))
+                        .collect::<Option<Vec<_>>>();
+                    let Some(&[x, y, z, theta, phi, farness]) = numbers.as_deref() else {
+                        return Err(format!("Invalid view {value:?}, expected x,y,z,theta,phi,farness"));
+                    };
+                    let target = Vec3::new(x, y, z);
+                    permalink.view = Some(CameraView { target, theta, phi, farness, orthographic: false });
+                }
+                "projection" => orthographic = value == "orthographic",
+                "color" => permalink.color_mode = Some(value.parse()?),
+                "filter" => {
+                    permalink.filter = Some(serde_json::from_str(&value).map_err(|e| format!("Invalid filter: {e}"))?);
+                }
+                _ => log::warn!("Skipping {key:?} in the link"),
+            }
+        }
+        if let Some(view) = &mut permalink.view {
+            view.orthographic = orthographic;
+        }
+        Ok(permalink)
+    }
+}
+
+/// The filter as JSON, leaving out what lets every point through to keep links short
+fn filter_json(filter: &FilterSettings) -> String {
+    let (Ok(serde_json::Value::Object(mut fields)), Ok(serde_json::Value::Object(defaults))) =
+        (serde_json::to_value(filter), serde_json::to_value(FilterSettings::default()))
+    else {
+        return "{}".to_owned();
+    };
+    fields.retain(|key, value| defaults.get(key) != Some(value));
+    serde_json::Value::Object(fields).to_string()
+}
+
+/// Percent-encodes all but the characters URLs never reserve
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn decode(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+            let decoded = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid escape in {text:?}"))?;
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("Invalid text in {text:?}"))
+}
@@ -8,4 +8,227 @@ pub fn print(s: &String) {
 #[cfg(target_family = "wasm")]
 pub fn print(s: &String) {
     web_sys::console::log_1(&s.into());
-}
\ No newline at end of file
+}
+
+/// Seconds since an arbitrary origin, for timing frames
+#[cfg(not(target_family = "wasm"))]
+pub fn now() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_secs_f64()
+}
+
+#[cfg(target_family = "wasm")]
+pub fn now() -> f64 {
+    js_sys::Date::now() / 1000.
+}
+
+/// Text shown over the viewer: printed and put in the window title on native
+#[cfg(not(target_family = "wasm"))]
+pub fn set_overlay(window: &winit::window::Window, text: &str) {
+    println!("{}", text);
+    window.set_title(&format!("Pointcloud Viewer - {}", text.lines().next().unwrap_or_default()));
+}
+
+/// Canvas a viewer draws on: `target` if it is one, else a new one filling
+/// `target`, or the width of the page and the height of the screen at its end
+#[cfg(target_family = "wasm")]
+pub fn viewer_canvas(target: Option<web_sys::Element>) -> Result<web_sys::HtmlCanvasElement, String> {
+    use wasm_bindgen::JsCast;
+
+    let document = web_sys::window().and_then(|win| win.document()).ok_or("No document to add a viewer to")?;
+    let canvas = match target.map(|target| target.dyn_into::<web_sys::HtmlCanvasElement>()) {
+        Some(Ok(canvas)) => canvas,
+        host => {
+            let (host, height) = match host {
+                Some(Err(element)) => (element, "100%"),
+                _ => (document.body().ok_or("The page has no body")?.into(), "100vh"),
+            };
+            let canvas: web_sys::HtmlCanvasElement = document.create_element("canvas")
+                .ok()
+                .and_then(|element| element.dyn_into().ok())
+                .ok_or("Couldn't make a canvas")?;
+            let _ = canvas.set_attribute("style", &format!("display: block; width: 100%; height: {height};"));
+            host.append_child(&canvas).map_err(|_| "Couldn't add the canvas to the page")?;
+            canvas
+        }
+    };
+    // Touches steer the camera rather than scroll the page
+    let _ = canvas.style().set_property("touch-action", "none");
+    Ok(canvas)
+}
+
+/// Overlay and label elements of each viewer on the page, next to its canvas
+#[cfg(target_family = "wasm")]
+struct Layer {
+    overlay: web_sys::Element,
+    labels: web_sys::Element,
+}
+
+#[cfg(target_family = "wasm")]
+thread_local! {
+    static LAYERS: std::cell::RefCell<std::collections::HashMap<winit::window::WindowId, Layer>> = Default::default();
+}
+
+/// Calls `f` with the layer of `window`, made on first use in the element
+/// holding its canvas, which labels are placed relative to
+#[cfg(target_family = "wasm")]
+fn with_layer(window: &winit::window::Window, f: impl FnOnce(&Layer)) {
+    use wasm_bindgen::JsCast;
+    use winit::platform::web::WindowExtWebSys;
+
+    LAYERS.with_borrow_mut(|layers| {
+        if let Some(layer) = layers.get(&window.id()) {
+            return f(layer);
+        }
+        let make = || -> Option<Layer> {
+            let win = web_sys::window()?;
+            let document = win.document()?;
+            let host = window.canvas()?.parent_element()?;
+            let position = win.get_computed_style(&host).ok()??.get_property_value("position").ok()?;
+            if position == "static" {
+                let _ = host.dyn_ref::<web_sys::HtmlElement>()?.style().set_property("position", "relative");
+            }
+            let overlay = document.create_element("pre").ok()?;
+            overlay.set_class_name("overlay");
+            let _ = overlay.set_attribute("style", "position: absolute; pointer-events: none;");
+            let labels = document.create_element("div").ok()?;
+            host.append_child(&overlay).ok()?;
+            host.append_child(&labels).ok()?;
+            Some(Layer { overlay, labels })
+        };
+        match make() {
+            Some(layer) => f(layers.entry(window.id()).or_insert(layer)),
+            None => log::error!("Couldn't add the overlay to the page"),
+        }
+    });
+}
+
+/// Takes the overlay and labels of the window `id` off the page
+#[cfg(target_family = "wasm")]
+pub fn remove_layer(id: winit::window::WindowId) {
+    if let Some(layer) = LAYERS.with_borrow_mut(|layers| layers.remove(&id)) {
+        layer.overlay.remove();
+        layer.labels.remove();
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+pub fn remove_layer(_id: winit::window::WindowId) {}
+
+/// Where the canvas of `window` is in the element holding it, in CSS pixels
+#[cfg(target_family = "wasm")]
+fn canvas_offset(window: &winit::window::Window) -> Option<(f64, f64)> {
+    use winit::platform::web::WindowExtWebSys;
+
+    let canvas = window.canvas()?;
+    let host = canvas.parent_element()?;
+    let (rect, host_rect) = (canvas.get_bounding_client_rect(), host.get_bounding_client_rect());
+    Some((
+        rect.left() - host_rect.left() - host.client_left() as f64 + host.scroll_left() as f64,
+        rect.top() - host_rect.top() - host.client_top() as f64 + host.scroll_top() as f64,
+    ))
+}
+
+/// Text shown over the top left of the viewer, styled by the page as `.overlay`
+#[cfg(target_family = "wasm")]
+pub fn set_overlay(window: &winit::window::Window, text: &str) {
+    let (left, top) = canvas_offset(window).unwrap_or_default();
+    with_layer(window, |layer| {
+        let _ = layer.overlay.set_attribute("style", &format!(
+            "position: absolute; left: {left}px; top: {top}px; pointer-events: none;",
+        ));
+        layer.overlay.set_text_content(Some(text));
+    });
+}
+
+/// Text anchored to pixels of the viewer. Only the web has them, natively
+/// the same text is in the overlay.
+#[cfg(not(target_family = "wasm"))]
+pub fn set_labels(_window: &winit::window::Window, _labels: &[(f32, f32, String)]) {}
+
+/// Labels are styled by the page as `.label`
+#[cfg(target_family = "wasm")]
+pub fn set_labels(window: &winit::window::Window, labels: &[(f32, f32, String)]) {
+    use winit::platform::web::WindowExtWebSys;
+
+    let Some(document) = web_sys::window().and_then(|win| win.document()) else {
+        return;
+    };
+    let (Some(canvas), Some((left, top))) = (window.canvas(), canvas_offset(window)) else {
+        return;
+    };
+    // Pixels are physical, the page is laid out in CSS pixels
+    let scale = canvas.get_bounding_client_rect().width() / canvas.width().max(1) as f64;
+    with_layer(window, |layer| {
+        layer.labels.set_inner_html("");
+        for (x, y, text) in labels {
+            let Ok(label) = document.create_element("span") else {
+                continue;
+            };
+            label.set_class_name("label");
+            label.set_text_content(Some(text));
+            let _ = label.set_attribute("style", &format!(
+                "position: absolute; left: {}px; top: {}px; pointer-events: none;",
+                left + *x as f64 * scale,
+                top + *y as f64 * scale,
+            ));
+            let _ = layer.labels.append_child(&label);
+        }
+    });
+}
+
+/// Writes `contents` next to the executable's working directory
+#[cfg(not(target_family = "wasm"))]
+pub fn save_file(name: &str, contents: &[u8]) {
+    match std::fs::write(name, contents) {
+        Ok(()) => println!("Saved {}", name),
+        Err(e) => log::error!("Couldn't save {}: {}", name, e),
+    }
+}
+
+/// Hands `contents` to the browser as a download
+#[cfg(target_family = "wasm")]
+pub fn save_file(name: &str, contents: &[u8]) {
+    use wasm_bindgen::JsCast;
+
+    let download = || -> Option<()> {
+        let document = web_sys::window()?.document()?;
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).ok()?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+        let anchor: web_sys::HtmlAnchorElement = document.create_element("a").ok()?.dyn_into().ok()?;
+        anchor.set_href(&url);
+        anchor.set_download(name);
+        anchor.click();
+        web_sys::Url::revoke_object_url(&url).ok()
+    };
+    if download().is_none() {
+        log::error!("Couldn't save {}", name);
+    }
+}
+
+/// Fragment of the page URL without the `#`, natively there is no page
+#[cfg(not(target_family = "wasm"))]
+pub fn url_hash() -> Option<String> {
+    None
+}
+
+#[cfg(target_family = "wasm")]
+pub fn url_hash() -> Option<String> {
+    let hash = web_sys::window()?.location().hash().ok()?;
+    Some(hash.trim_start_matches('#').to_owned())
+}
+
+/// Replaces the fragment of the page URL without adding to the history
+#[cfg(not(target_family = "wasm"))]
+pub fn set_url_hash(_hash: &str) {}
+
+#[cfg(target_family = "wasm")]
+pub fn set_url_hash(hash: &str) {
+    let replaced = web_sys::window()
+        .and_then(|win| win.history().ok())
+        .and_then(|history| history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&format!("#{hash}"))).ok());
+    if replaced.is_none() {
+        log::error!("Couldn't update the page URL");
+    }
+}
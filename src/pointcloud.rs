@@ -1,11 +1,12 @@
-use std::io::Cursor;
-use glam::{DVec3, Vec3};
+use std::{collections::HashMap, io::Cursor};
+use glam::{DVec3, IVec3, Vec3};
 use las::{Reader, Vector};
+use serde::Serialize;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
-use web_sys::{Request, RequestInit, RequestMode, Response};
+use web_sys::{ReadableStreamDefaultReader, Request, RequestInit, RequestMode, Response};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::JsFuture;
 
@@ -18,46 +19,271 @@ fn into_vec3(v: DVec3) -> Vec3 {
     Vec3 { x: v.x as f32, y: v.y as f32, z: v.z as f32 }
 }
 
+type Source = (Vec<SourcePoint>, SourceTransform);
+
+/// Downloads and reads the LAS file on another thread, the blocking client
+/// would otherwise hold up the event loop until it is done
+#[cfg(not(target_family = "wasm"))]
+async fn read_source(url: &str, progress: &mut impl FnMut(f32)) -> Result<Source, Box<dyn std::error::Error>> {
+    use std::{sync::{mpsc, Arc, Mutex}, task::{Poll, Waker}};
+
+    enum Message {
+        Progress(f32),
+        Done(Result<Source, String>),
+    }
+    let (sender, receiver) = mpsc::channel();
+    let waker: Arc<Mutex<Option<Waker>>> = Arc::default();
+    let (url, thread_waker) = (url.to_owned(), waker.clone());
+    std::thread::spawn(move || {
+        let send = |message| {
+            let _ = sender.send(message);
+            if let Some(waker) = thread_waker.lock().unwrap().as_ref() {
+                waker.wake_by_ref();
+            }
+        };
+        let result = download(&url, &mut |fraction| send(Message::Progress(fraction)))
+            .and_then(read_bytes)
+            .map_err(|e| e.to_string());
+        send(Message::Done(result));
+    });
+
+    std::future::poll_fn(|cx| {
+        // Set before looking, so a message sent meanwhile still wakes the task
+        *waker.lock().unwrap() = Some(cx.waker().clone());
+        loop {
+            match receiver.try_recv() {
+                Ok(Message::Progress(fraction)) => progress(fraction),
+                Ok(Message::Done(result)) => return Poll::Ready(result.map_err(Into::into)),
+                Err(mpsc::TryRecvError::Empty) => return Poll::Pending,
+                Err(mpsc::TryRecvError::Disconnected) => return Poll::Ready(Err("The download stopped".into())),
+            }
+        }
+    }).await
+}
+
+#[cfg(target_family = "wasm")]
+async fn read_source(url: &str, progress: &mut impl FnMut(f32)) -> Result<Source, Box<dyn std::error::Error>> {
+    read_bytes(download(url, progress).await?)
+}
+
+fn read_bytes(bytes: Vec<u8>) -> Result<Source, Box<dyn std::error::Error>> {
+    let len = bytes.len();
+    Ok(Pointcloud::read_las(Reader::new(Cursor::new(bytes))?, len))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn download(url: &str, progress: &mut impl FnMut(f32)) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use std::io::Read;
+
+    let mut response = reqwest::blocking::get(url)?.error_for_status()?;
+    let total = response.content_length();
+    let mut bytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+    let mut chunk = vec![0; 1 << 16];
+    loop {
+        let read = response.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        if let Some(total) = total {
+            progress(bytes.len() as f32 / total as f32);
+        }
+    }
+}
+
+/// Reads the response as it streams in, to tell how far along it is
+#[cfg(target_family = "wasm")]
+async fn download(url: &str, progress: &mut impl FnMut(f32)) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let js_error = |e: JsValue| format!("Couldn't fetch {url}: {e:?}");
+    let opts = RequestInit::new();
+    opts.set_method("GET");
+    opts.set_mode(RequestMode::Cors);
+    let req = Request::new_with_str_and_init(url, &opts).map_err(js_error)?;
+    let window = web_sys::window().ok_or("No window")?;
+    let resp: Response = JsFuture::from(window.fetch_with_request(&req)).await.map_err(js_error)?.dyn_into().map_err(js_error)?;
+    if !resp.ok() {
+        return Err(format!("Couldn't fetch {url}: HTTP {}", resp.status()).into());
+    }
+
+    let total = resp.headers().get("content-length").ok().flatten().and_then(|len| len.parse::<f64>().ok());
+    let Some(body) = resp.body() else {
+        let array = JsFuture::from(resp.array_buffer().map_err(js_error)?).await.map_err(js_error)?;
+        return Ok(js_sys::Uint8Array::new(&array).to_vec());
+    };
+    let reader: ReadableStreamDefaultReader = body.get_reader().dyn_into().map_err(|e: js_sys::Object| js_error(e.into()))?;
+    let mut bytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+    loop {
+        let chunk = JsFuture::from(reader.read()).await.map_err(js_error)?;
+        if js_sys::Reflect::get(&chunk, &"done".into()).map_err(js_error)?.is_truthy() {
+            return Ok(bytes);
+        }
+        let value: js_sys::Uint8Array = js_sys::Reflect::get(&chunk, &"value".into()).map_err(js_error)?.dyn_into().map_err(js_error)?;
+        bytes.extend(value.to_vec());
+        if let Some(total) = total {
+            progress((bytes.len() as f64 / total) as f32);
+        }
+    }
+}
+
+/// Maps source coordinates, usually a projected CRS that doesn't fit in an f32,
+/// to the scene coordinates uploaded to the GPU: `local = (source - offset) * scale`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceTransform {
+    pub offset: DVec3,
+    pub scale: DVec3,
+}
+
+impl Default for SourceTransform {
+    fn default() -> Self {
+        Self {
+            offset: DVec3::ZERO,
+            scale: DVec3::ONE,
+        }
+    }
+}
+
+impl SourceTransform {
+    pub fn to_local(&self, source: DVec3) -> Vec3 {
+        into_vec3((source - self.offset) * self.scale)
+    }
+
+    pub fn to_source(&self, local: Vec3) -> DVec3 {
+        local.as_dvec3() / self.scale + self.offset
+    }
+}
+
+/// A point as stored in the source file, with its exact coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize)]
+pub struct SourcePoint {
+    pub position: DVec3,
+    pub intensity: u16,
+    pub return_number: u8,
+    pub number_of_returns: u8,
+    pub classification: u8,
+    pub scan_angle: f32, // degrees
+    pub user_data: u8,
+    pub point_source_id: u16,
+    pub gps_time: Option<f64>,
+    pub color: Option<[u16; 3]>,
+}
+
+impl From<&las::Point> for SourcePoint {
+    fn from(point: &las::Point) -> Self {
+        Self {
+            position: DVec3::new(point.x, point.y, point.z),
+            intensity: point.intensity,
+            return_number: point.return_number,
+            number_of_returns: point.number_of_returns,
+            classification: u8::from(point.classification),
+            scan_angle: point.scan_angle,
+            user_data: point.user_data,
+            point_source_id: point.point_source_id,
+            gps_time: point.gps_time,
+            color: point.color.map(|color| [color.red, color.green, color.blue]),
+        }
+    }
+}
+
+/// Packs the attributes the filters look at, as read by the shaders:
+/// classification, return number and number of returns in the low three
+/// bytes and whether there is a gps time in the fourth, then the gps time
+/// relative to `gps_origin` as f32 bits
+fn pack_attributes(point: &SourcePoint, gps_origin: f64) -> [u32; 2] {
+    let has_gps_time = point.gps_time.is_some() as u32;
+    let packed = point.classification as u32
+        | (point.return_number as u32) << 8
+        | (point.number_of_returns as u32) << 16
+        | has_gps_time << 24;
+    let gps_time = point.gps_time.map_or(0., |time| (time - gps_origin) as f32);
+
+    [packed, gps_time.to_bits()]
+}
+
+// Average amount of points per cell when estimating the spacing, also used
+// for the ground grid
+pub(crate) const POINTS_PER_CELL: f32 = 16.;
+
+/// Approximate distance between each point and its neighbours, from the
+/// amount of points sharing its cell in a uniform grid.
+pub fn estimate_spacing(points: &[Vec3], min: Vec3, max: Vec3) -> Vec<f32> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    // Flat clouds would have no volume, so every extent is at least a fraction of the largest
+    let extent = (max - min).max(Vec3::splat((max - min).max_element() * 0.01)).max(Vec3::splat(f32::EPSILON));
+    let cell = (extent.x * extent.y * extent.z * POINTS_PER_CELL / points.len() as f32).cbrt();
+
+    let key = |p: &Vec3| ((*p - min) / cell).floor().as_ivec3();
+    let mut counts = HashMap::<IVec3, u32>::new();
+    for p in points {
+        *counts.entry(key(p)).or_default() += 1;
+    }
+
+    points.iter()
+        .map(|p| cell / (counts[&key(p)] as f32).cbrt())
+        .collect()
+}
+
 pub struct Pointcloud {
     points: Vec<Vec3>,
     intensities: Vec<f32>,
+    source: Vec<SourcePoint>, // empty for clouds that weren't read from a file
+    transform: SourceTransform,
+    gps_origin: f64, // earliest gps time, the GPU gets times relative to it
+    min: Vec3,
+    max: Vec3,
+    intensity_range: (f32, f32),
     point_buffer: wgpu::Buffer,
     intensity_buffer: wgpu::Buffer,
-    //return_number: Vec<u8>,
-    //number_of_returns: Vec<u8>,
-    //classification: Vec<u8>,
+    spacing_buffer: wgpu::Buffer,
+    attribute_buffer: wgpu::Buffer,
 }
 
 impl Pointcloud {
     pub async fn from_las(device: &wgpu::Device, queue: &wgpu::Queue, url: &str) -> Result<Pointcloud, Box<dyn std::error::Error>> {
-        #[cfg(not(target_family = "wasm"))]
-        let bytes = {
-            reqwest::blocking::get(url)?.bytes()?
-        };
-        #[cfg(target_family = "wasm")]
-        let bytes = {
-            let opts = RequestInit::new();
-            opts.set_method("GET");
-            opts.set_mode(RequestMode::Cors);
-            let req = Request::new_with_str_and_init(&url, &opts).unwrap();
-            let window = web_sys::window().unwrap();
-            let resp_value = JsFuture::from(window.fetch_with_request(&req)).await.unwrap();
-        
-            assert!(resp_value.is_instance_of::<Response>());
-            
-            let resp: Response = resp_value.dyn_into().unwrap();
-            let array = JsFuture::from(resp.array_buffer().unwrap()).await.unwrap();
-            js_sys::Uint8Array::new(&array).to_vec()
-        };
+        Self::from_las_with_progress(device, queue, url, |_| ()).await
+    }
+
+    /// As `from_las`, calling `progress` with the fraction downloaded so far
+    /// when the server tells how much there is
+    pub async fn from_las_with_progress(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        url: &str,
+        mut progress: impl FnMut(f32),
+    ) -> Result<Pointcloud, Box<dyn std::error::Error>> {
+        let (source, transform) = read_source(url, &mut progress).await?;
+
+        Ok(Pointcloud::from_source(device, queue, source, transform))
+    }
+
+    /// Keeps `source` around so picked points can be reported with all their attributes
+    pub fn from_source(device: &wgpu::Device, queue: &wgpu::Queue, source: Vec<SourcePoint>, transform: SourceTransform) -> Pointcloud {
+        let points = source.iter().map(|p| transform.to_local(p.position)).collect();
+        let intensities = source.iter().map(|p| p.intensity as f32).collect();
+        let pointcloud = Pointcloud::from_points(device, queue, points, intensities);
+
+        let gps_origin = source.iter().filter_map(|p| p.gps_time).reduce(f64::min).unwrap_or_default();
+        let attributes: Vec<_> = source.iter().map(|p| pack_attributes(p, gps_origin)).collect();
+        queue.write_buffer(&pointcloud.attribute_buffer, 0, bytemuck::cast_slice(&attributes));
 
-        let len = bytes.len();
-        let (points, intensities) = Pointcloud::read_las(Reader::new(Cursor::new(bytes)).unwrap(), len);
+        Pointcloud {
+            source,
+            transform,
+            gps_origin,
+            ..pointcloud
+        }
+    }
+
+    pub fn from_points(device: &wgpu::Device, queue: &wgpu::Queue, points: Vec<Vec3>, intensities: Vec<f32>) -> Pointcloud {
+        assert_eq!(points.len(), intensities.len(), "every point needs an intensity");
 
         let point_buffer = device.create_buffer(
             &wgpu::BufferDescriptor {
                 label: Some("Point Buffer"),
                 size: (points.len()*12) as u64,
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             }
         );
@@ -67,41 +293,120 @@ impl Pointcloud {
             &wgpu::BufferDescriptor {
                 label: Some("Intensity Buffer"),
                 size: (intensities.len()*4) as u64,
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
                 mapped_at_creation: false,
             }
         );
         queue.write_buffer(&intensity_buffer, 0, bytemuck::cast_slice(&intensities)); 
 
-        Ok(Pointcloud { 
+        let (min, max) = points.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let intensity_range = intensities.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY),
+            |(min, max), i| (min.min(*i), max.max(*i)),
+        );
+
+        let spacing = estimate_spacing(&points, min, max);
+        let spacing_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Spacing Buffer"),
+                size: (spacing.len()*4) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+        queue.write_buffer(&spacing_buffer, 0, bytemuck::cast_slice(&spacing));
+
+        // Zeroed until `from_source` fills it, the filters' defaults let these through
+        let attribute_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Attribute Buffer"),
+                size: (points.len()*8) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }
+        );
+
+        Pointcloud { 
             points, 
             intensities, 
+            source: Vec::new(),
+            transform: SourceTransform::default(),
+            gps_origin: 0.,
+            min,
+            max,
+            intensity_range,
             point_buffer,
-            intensity_buffer
-        })
+            intensity_buffer,
+            spacing_buffer,
+            attribute_buffer,
+        }
     }
 
-    fn read_las(mut reader: Reader, len: usize) -> (Vec<Vec3>, Vec<f32>) {
-        let mut points = Vec::with_capacity(len);
-        let mut intensities = Vec::with_capacity(len);
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    pub fn intensities(&self) -> &[f32] {
+        &self.intensities
+    }
+
+    /// Points as read from the file, empty if the cloud was made from scene coordinates
+    pub fn source(&self) -> &[SourcePoint] {
+        &self.source
+    }
+
+    pub fn transform(&self) -> &SourceTransform {
+        &self.transform
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Axis aligned bounding box in scene coordinates
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        (self.min, self.max)
+    }
+
+    /// Point, intensity, spacing and attribute buffers, for passes that read them as storage
+    pub fn buffers(&self) -> (&wgpu::Buffer, &wgpu::Buffer, &wgpu::Buffer, &wgpu::Buffer) {
+        (&self.point_buffer, &self.intensity_buffer, &self.spacing_buffer, &self.attribute_buffer)
+    }
+
+    /// Gps time the GPU's are relative to
+    pub fn gps_origin(&self) -> f64 {
+        self.gps_origin
+    }
+
+    pub fn intensity_range(&self) -> (f32, f32) {
+        self.intensity_range
+    }
+
+    fn read_las(mut reader: Reader, len: usize) -> (Vec<SourcePoint>, SourceTransform) {
+        let mut source = Vec::with_capacity(len);
         
         let header = reader.header();
 
         let half = (into_dvec3(header.bounds().max) - into_dvec3(header.bounds().min)) * 0.5;
         let transforms = header.transforms().to_owned();
         let min = into_dvec3(header.bounds().min);
+        let transform = SourceTransform {
+            offset: min + half,
+            scale: DVec3::new(transforms.x.scale, transforms.y.scale, transforms.z.scale),
+        };
 
         for wrapped_point in reader.points() {
-            let point = wrapped_point.unwrap();
-            points.push(
-                into_vec3( ( DVec3::new(point.x, point.y, point.z) - min - half)
-                * DVec3::new(transforms.x.scale, transforms.y.scale, transforms.z.scale) )
-            );
-            
-            intensities.push(point.intensity as f32);
+            source.push(SourcePoint::from(&wrapped_point.unwrap()));
         }
 
-        (points, intensities)
+        (source, transform)
     }
 
 }
@@ -111,6 +416,8 @@ impl<'a> Encode<'a> for Pointcloud {
         
         recorder.set_vertex_buffer(0, self.point_buffer.slice(..));
         recorder.set_vertex_buffer(1, self.intensity_buffer.slice(..));
+        recorder.set_vertex_buffer(2, self.spacing_buffer.slice(..));
+        recorder.set_vertex_buffer(3, self.attribute_buffer.slice(..));
         recorder.draw(0..4, 0..self.points.len() as u32);
     }
 }
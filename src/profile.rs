@@ -0,0 +1,190 @@
+use std::{fmt::Write, io::Cursor};
+
+use glam::DVec3;
+
+use crate::pointcloud::{Pointcloud, SourcePoint};
+
+/// Corridor along a path, in source coordinates. Points within `width / 2`
+/// of the path, measured horizontally, make up the profile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub path: Vec<DVec3>,
+    pub width: f64,
+}
+
+/// A point of the profile, unrolled along the path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProfilePoint {
+    pub index: usize, // in the pointcloud
+    pub distance: f64, // along the path, from its start
+    pub offset: f64, // across the path, positive on its left
+    pub position: DVec3, // source coordinates
+    pub intensity: f32,
+    pub source: Option<SourcePoint>,
+}
+
+impl ProfilePoint {
+    /// Unrolled coordinates: distance along, offset across and elevation
+    pub fn unrolled(&self) -> DVec3 {
+        DVec3::new(self.distance, self.offset, self.position.z)
+    }
+}
+
+impl Profile {
+    pub fn new(width: f64) -> Self {
+        Self { path: Vec::new(), width }
+    }
+
+    /// Horizontal length of the path
+    pub fn length(&self) -> f64 {
+        self.path.windows(2).map(|pair| pair[0].truncate().distance(pair[1].truncate())).sum()
+    }
+
+    /// `(distance, offset)` of `point` if it is inside the corridor. The
+    /// corridor ends square at both ends of the path and is rounded at its bends.
+    pub fn station(&self, point: DVec3) -> Option<(f64, f64)> {
+        let point = point.truncate();
+        let last = self.path.len().checked_sub(2)?;
+        let mut start_distance = 0.;
+        let mut closest: Option<(f64, f64, f64)> = None; // (squared distance to the path, distance, offset)
+
+        for (i, pair) in self.path.windows(2).enumerate() {
+            let (a, b) = (pair[0].truncate(), pair[1].truncate());
+            let direction = b - a;
+            let length = direction.length();
+            if length > 0. {
+                let t = (point - a).dot(direction) / (length * length);
+                if !((i == 0 && t < 0.) || (i == last && t > 1.)) {
+                    let t = t.clamp(0., 1.);
+                    let across = point - (a + direction * t);
+                    let squared = across.length_squared();
+                    if closest.is_none_or(|(best, ..)| squared < best) {
+                        let side = if direction.perp_dot(across) < 0. { -1. } else { 1. };
+                        closest = Some((squared, start_distance + t * length, side * squared.sqrt()));
+                    }
+                }
+            }
+            start_distance += length;
+        }
+
+        let half_width = self.width * 0.5;
+        closest
+            .filter(|(squared, ..)| *squared <= half_width * half_width)
+            .map(|(_, distance, offset)| (distance, offset))
+    }
+
+    /// Points of `pointcloud` inside the corridor, sorted along the path
+    pub fn extract(&self, pointcloud: &Pointcloud) -> Vec<ProfilePoint> {
+        let transform = pointcloud.transform();
+        let mut points: Vec<_> = pointcloud
+            .points()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, local)| {
+                let source = pointcloud.source().get(index).copied();
+                let position = source.map_or_else(|| transform.to_source(*local), |source| source.position);
+                let (distance, offset) = self.station(position)?;
+                Some(ProfilePoint {
+                    index,
+                    distance,
+                    offset,
+                    position,
+                    intensity: pointcloud.intensities()[index],
+                    source,
+                })
+            })
+            .collect();
+        points.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        points
+    }
+
+    /// Path and outline of the corridor, for drawing
+    pub fn outline(&self) -> Vec<(DVec3, DVec3)> {
+        let half_width = self.width * 0.5;
+        let mut lines = Vec::new();
+        for pair in self.path.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let side = (b - a).truncate().perp().normalize_or_zero() * half_width;
+            let side = DVec3::new(side.x, side.y, 0.);
+            lines.extend([(a, b), (a + side, b + side), (a - side, b - side)]);
+        }
+        // Square ends
+        if let ([first, second, ..], [.., before_last, last]) = (self.path.as_slice(), self.path.as_slice()) {
+            for (end, next) in [(*first, *second), (*last, *before_last)] {
+                let side = (next - end).truncate().perp().normalize_or_zero() * half_width;
+                let side = DVec3::new(side.x, side.y, 0.);
+                lines.push((end - side, end + side));
+            }
+        }
+
+        lines
+    }
+}
+
+/// One row per point, with the unrolled coordinates first
+pub fn to_csv(points: &[ProfilePoint]) -> String {
+    let mut csv = String::from("distance,offset,elevation,x,y,z,intensity,classification\n");
+    for point in points {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            point.distance,
+            point.offset,
+            point.position.z,
+            point.position.x,
+            point.position.y,
+            point.position.z,
+            point.intensity,
+            point.source.map(|source| source.classification.to_string()).unwrap_or_default(),
+        );
+    }
+
+    csv
+}
+
+/// LAS 1.2 file of the profile points with their source attributes, at millimeter precision
+pub fn to_las(points: &[ProfilePoint]) -> las::Result<Vec<u8>> {
+    let first_source = points.first().and_then(|point| point.source);
+    let mut format = las::point::Format::new(0)?;
+    format.has_gps_time = first_source.is_some_and(|source| source.gps_time.is_some());
+    format.has_color = first_source.is_some_and(|source| source.color.is_some());
+
+    let min = points.iter().fold(DVec3::INFINITY, |min, point| min.min(point.position));
+    let offset = if points.is_empty() { DVec3::ZERO } else { min.floor() };
+    let mut builder = las::Builder::from((1, 2));
+    builder.point_format = format;
+    builder.transforms = las::Vector {
+        x: las::Transform { scale: 0.001, offset: offset.x },
+        y: las::Transform { scale: 0.001, offset: offset.y },
+        z: las::Transform { scale: 0.001, offset: offset.z },
+    };
+
+    let mut writer = las::Writer::new(Cursor::new(Vec::new()), builder.into_header()?)?;
+    for point in points {
+        let source = point.source.unwrap_or(SourcePoint {
+            intensity: point.intensity as u16,
+            ..Default::default()
+        });
+        writer.write_point(las::Point {
+            x: point.position.x,
+            y: point.position.y,
+            z: point.position.z,
+            intensity: source.intensity,
+            return_number: source.return_number,
+            number_of_returns: source.number_of_returns,
+            classification: las::point::Classification::new(source.classification).unwrap_or_default(),
+            scan_angle: source.scan_angle,
+            user_data: source.user_data,
+            point_source_id: source.point_source_id,
+            gps_time: format.has_gps_time.then(|| source.gps_time.unwrap_or_default()),
+            color: format.has_color.then(|| {
+                let [red, green, blue] = source.color.unwrap_or_default();
+                las::Color { red, green, blue }
+            }),
+            ..Default::default()
+        })?;
+    }
+
+    Ok(writer.into_inner()?.into_inner())
+}
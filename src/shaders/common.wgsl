@@ -0,0 +1,185 @@
+// Uniforms and helpers shared by the point shaders, prepended to each of
+// them by `common_shader!` and by build.rs when checking them
+const COLOR_INTENSITY = 0u;
+const COLOR_ELEVATION = 1u;
+const SIZE_ATTENUATED = 1u;
+const SIZE_ADAPTIVE = 2u;
+
+struct Uniforms {
+  time: f32,
+  width: f32,
+  height: f32,
+  pixels: f32,
+  model_matrix: mat4x4f,
+  view_matrix: mat4x4f,
+  color_mode: u32,
+  intensity_min: f32,
+  intensity_max: f32,
+  elevation_min: f32,
+  elevation_max: f32,
+  point_size_mode: u32,
+  world_size: f32,
+  adaptive_scale: f32,
+  min_pixels: f32,
+  max_pixels: f32,
+  projection_scale: f32,
+  depth_epsilon: f32,
+  projection_z: vec4f, // third column of the projection matrix
+  viewport_origin: vec2f, // pixels, for views drawn in part of the target
+};
+@group(0) @binding(0) var<uniform> unif: Uniforms;
+
+fn hsl_to_rgb(hsl: vec3f) -> vec3f {
+  var rgb: vec3f;
+
+  if hsl.y == 0. {
+    rgb = vec3(hsl.z); // achromatic
+  } else {
+    var q: f32;
+    if hsl.z < 0.5 {
+      q = hsl.z * (1. + hsl.y);
+    } else {
+      q = hsl.z + hsl.y - hsl.z * hsl.y;
+    }
+    let p = 2. * hsl.z - q;
+    rgb.r = hue_to_rgb(p, q, hsl.x + 1./3);
+    rgb.g = hue_to_rgb(p, q, hsl.x);
+    rgb.b = hue_to_rgb(p, q, hsl.x - 1./3);
+  }
+  return rgb;
+}
+fn hue_to_rgb(p: f32, q: f32, t: f32) -> f32 {
+  var t2 = fract(t);
+  if t2 < 1./6 {return p + (q - p) * 6. * t2;}
+  if t2 < 1./2 {return q;}
+  if t2 < 2./3 {return p + (q - p) * (2./3 - t2) * 6.;}
+  return p;
+}
+fn normalize_range(value: f32, low: f32, high: f32) -> f32 {
+  return clamp((value - low) / max(high - low, 1e-6), 0., 1.);
+}
+
+// Diameter in pixels of a point `spacing` apart from its neighbours, `w`
+// away from the camera
+fn point_size(spacing: f32, w: f32) -> f32 {
+  var size: f32;
+  switch unif.point_size_mode {
+    case SIZE_ATTENUATED: {
+      size = unif.world_size * unif.projection_scale / w;
+    }
+    case SIZE_ADAPTIVE: {
+      size = spacing * unif.adaptive_scale * unif.projection_scale / w;
+    }
+    default: {
+      size = unif.pixels;
+    }
+  }
+  return clamp(size, unif.min_pixels, unif.max_pixels);
+}
+
+const CLIP_INSIDE = 1u;
+const CLIP_OUTSIDE = 2u;
+const CLIP_HIGHLIGHT = 3u;
+const HIGHLIGHT_COLOR = vec3f(1, 0.3, 0.1);
+
+struct Clipping {
+  boxes: array<mat4x4f, 8>, // world to the cube from -1 to 1
+  planes: array<vec4f, 8>, // outside where dot(plane.xyz, p) + plane.w > 0
+  box_count: u32,
+  plane_count: u32,
+  mode: u32,
+};
+@group(0) @binding(1) var<uniform> clipping: Clipping;
+
+fn in_clip_volume(point: vec3f) -> bool {
+  var in_box = clipping.box_count == 0u;
+  for (var i = 0u; i < clipping.box_count; i++) {
+    let local = clipping.boxes[i] * vec4f(point, 1);
+    in_box = in_box || all(abs(local.xyz) <= vec3f(1));
+  }
+  for (var i = 0u; i < clipping.plane_count; i++) {
+    if dot(clipping.planes[i], vec4f(point, 1)) > 0. {
+      return false;
+    }
+  }
+  return in_box;
+}
+
+// Whether the clip boxes and planes let the point through
+fn is_clipped(point: vec3f) -> bool {
+  if clipping.box_count + clipping.plane_count == 0u {
+    return false;
+  }
+  switch clipping.mode {
+    case CLIP_INSIDE: { return !in_clip_volume(point); }
+    case CLIP_OUTSIDE: { return in_clip_volume(point); }
+    default: { return false; }
+  }
+}
+
+fn is_highlighted(point: vec3f) -> bool {
+  return clipping.mode == CLIP_HIGHLIGHT && clipping.box_count + clipping.plane_count > 0u && in_clip_volume(point);
+}
+
+const RETURNS_FIRST = 1u;
+const RETURNS_LAST = 2u;
+const RETURNS_INTERMEDIATE = 3u;
+const RETURNS_SINGLE = 4u;
+const HAS_GPS_TIME = 0x1000000u;
+
+struct Filter {
+  classifications: array<vec4u, 2>, // one bit per class
+  intensity: vec2f,
+  elevation: vec2f,
+  gps_time: vec2f, // relative to the cloud's earliest
+  number_of_returns: vec2u,
+  returns: u32,
+};
+@group(0) @binding(2) var<uniform> filters: Filter;
+
+fn in_range(value: f32, range: vec2f) -> bool {
+  return range.x <= value && value <= range.y;
+}
+
+// Whether the attribute filters hide the point, `attributes` packs the
+// classification, return number, number of returns and a gps time flag
+fn is_filtered(attributes: u32, gps_time: f32, intensity: f32, elevation: f32) -> bool {
+  let classification = attributes & 0xffu;
+  let return_number = (attributes >> 8u) & 0xffu;
+  let number_of_returns = (attributes >> 16u) & 0xffu;
+
+  var returns_kept = true;
+  switch filters.returns {
+    case RETURNS_FIRST: { returns_kept = return_number == 1u; }
+    case RETURNS_LAST: { returns_kept = return_number == number_of_returns; }
+    case RETURNS_INTERMEDIATE: { returns_kept = return_number > 1u && return_number < number_of_returns; }
+    case RETURNS_SINGLE: { returns_kept = number_of_returns == 1u; }
+    default: {}
+  }
+
+  let kept = (filters.classifications[classification / 128u][(classification / 32u) % 4u] & (1u << (classification % 32u))) != 0u
+    && returns_kept
+    && number_of_returns >= filters.number_of_returns.x && number_of_returns <= filters.number_of_returns.y
+    && in_range(intensity, filters.intensity)
+    && in_range(elevation, filters.elevation)
+    && ((attributes & HAS_GPS_TIME) == 0u || in_range(gps_time, filters.gps_time));
+  return !kept;
+}
+
+// Color of a point in the current color mode, the same for every backend
+fn shade(intensity: f32, elevation: f32, highlighted: bool) -> vec3f {
+  var color: vec3f;
+  if unif.color_mode == COLOR_ELEVATION {
+    let height = normalize_range(elevation, unif.elevation_min, unif.elevation_max);
+    color = hsl_to_rgb(vec3f(
+      (1. - height) * 2./3, // blue to red
+      1,
+      0.5));
+  } else {
+    color = vec3f(normalize_range(intensity, unif.intensity_min, unif.intensity_max));
+  }
+  if highlighted {
+    color = mix(color, HIGHLIGHT_COLOR, 0.5);
+  }
+  return color;
+}
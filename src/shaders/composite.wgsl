@@ -0,0 +1,66 @@
+const NEIGHBOURS = 8;
+const pi = 3.14159265359;
+
+struct CompositeUniforms {
+  edl_strength: f32,
+  edl_radius: f32,
+  near: f32,
+  far: f32,
+  orthographic: u32,
+  edl_enabled: u32,
+  ssao_enabled: u32,
+};
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+@group(0) @binding(1) var depth_texture: texture_2d<f32>; // Depth32Float, read as unfilterable float
+@group(0) @binding(2) var<uniform> unif: CompositeUniforms;
+@group(0) @binding(3) var ao_texture: texture_2d<f32>;
+
+// Single triangle covering the whole screen
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * 2. - 1., 0, 1);
+}
+
+fn linear_depth(depth: f32) -> f32 {
+  if unif.orthographic != 0u {
+    return unif.near + depth * (unif.far - unif.near);
+  }
+  return unif.near * unif.far / (unif.far - depth * (unif.far - unif.near));
+}
+
+fn eye_dome_lighting(coords: vec2i, depth: f32) -> f32 {
+  let size = vec2i(textureDimensions(depth_texture));
+  let center = log2(linear_depth(depth));
+  var response = 0.;
+  for (var i = 0; i < NEIGHBOURS; i++) {
+    let angle = f32(i) * 2. * pi / f32(NEIGHBOURS);
+    let offset = vec2i(round(vec2f(cos(angle), sin(angle)) * unif.edl_radius));
+    let neighbour = textureLoad(depth_texture, clamp(coords + offset, vec2i(0), size - 1), 0).r;
+    if neighbour >= 1. {
+      response += 100.; // Silhouette against the background
+    } else {
+      response += max(0., center - log2(linear_depth(neighbour)));
+    }
+  }
+  response /= f32(NEIGHBOURS);
+
+  return exp(-response * 300. * unif.edl_strength);
+}
+
+@fragment fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+  let coords = vec2i(position.xy);
+  let color = textureLoad(color_texture, coords, 0);
+  let depth = textureLoad(depth_texture, coords, 0).r;
+  if depth >= 1. {
+    return color;
+  }
+
+  var shade = 1.;
+  if unif.edl_enabled != 0u {
+    shade *= eye_dome_lighting(coords, depth);
+  }
+  if unif.ssao_enabled != 0u {
+    shade *= textureLoad(ao_texture, coords, 0).r;
+  }
+  return vec4f(color.rgb * shade, color.a);
+}
@@ -7,77 +7,99 @@ const square = array(
 const pi = 3.14159265359;
 override circleEnabled = true;
 
-fn hsl_to_rgb(hsl: vec3f) -> vec3f {
-  var rgb: vec3f;
-
-  if hsl.y == 0. {
-    rgb = vec3(hsl.z); // achromatic
-  } else {
-    var q: f32;
-    if hsl.z < 0.5 {
-      q = hsl.z * (1. + hsl.y);
-    } else {
-      q = hsl.z + hsl.y - hsl.z * hsl.y;
-    }
-    let p = 2. * hsl.z - q;
-    rgb.r = hue_to_rgb(p, q, hsl.x + 1./3);
-    rgb.g = hue_to_rgb(p, q, hsl.x);
-    rgb.b = hue_to_rgb(p, q, hsl.x - 1./3);
-  }
-  return rgb;
-}
-fn hue_to_rgb(p: f32, q: f32, t: f32) -> f32 {
-  var t2 = fract(t);
-  if t2 < 1./6 {return p + (q - p) * 6. * t2;}
-  if t2 < 1./2 {return q;}
-  if t2 < 2./3 {return p + (q - p) * (2./3 - t2) * 6.;}
-  return p;
-}
 fn to_fragment_coords(x: f32, y: f32) -> vec2f {
-  return vec2f((x+1) * 0.5 * unif.width, (-y+1) * 0.5 * unif.height);
+  return vec2f((x+1) * 0.5 * unif.width, (-y+1) * 0.5 * unif.height) + unif.viewport_origin;
 }
 
 struct VertexIn {
   @location(0) point: vec3f,
   @location(1) intensity: f32,
+  @location(2) spacing: f32,
+  @location(3) attributes: u32,
+  @location(4) gps_time: f32,
   @builtin(vertex_index) index: u32,
+  @builtin(instance_index) instance: u32,
 };
 struct VertexOut {
   @builtin(position) position: vec4f,
   @location(0) point: vec2f,
   @location(1) intensity: f32,
+  @location(2) elevation: f32,
+  @location(3) size: f32,
+  @location(4) @interpolate(flat) id: u32, // index of the point
+  @location(5) @interpolate(flat) highlighted: u32,
 };
 struct FragOut {
   @location(0) color: vec4f,
 };
 
-struct Uniforms {
-  time: f32,
-  width: f32,
-  height: f32,
-  pixels: f32,
-  view_matrix: mat4x4f,
-  model_matrix: mat4x4f,
-};
-@group(0) @binding(0) var<uniform> unif: Uniforms;
+fn make_vertex(in: VertexIn, depth_offset: f32) -> VertexOut {
+  if is_clipped(in.point) || is_filtered(in.attributes, in.gps_time, in.intensity, in.point.z) {
+    var out: VertexOut;
+    out.position = vec4f(0, 0, 2, 1); // beyond the far plane
+    return out;
+  }
 
-@vertex fn vs_main(in: VertexIn) -> VertexOut {
-  var point = unif.view_matrix * unif.model_matrix * vec4f(in.point, 1);
+  // Offsetting in view space is the same as adding a multiple of the projection's z column
+  var point = unif.view_matrix * unif.model_matrix * vec4f(in.point, 1) + depth_offset * unif.projection_z;
+  let size = point_size(in.spacing, point.w);
   point = point/point.w;
-  let vert = point + vec4f(square[in.index] / vec2f(unif.width, unif.height) * unif.pixels * 2, 0, 0);
+  let vert = point + vec4f(square[in.index] / vec2f(unif.width, unif.height) * size * 2, 0, 0);
+
+  return VertexOut(
+    vert, to_fragment_coords(point.x, point.y), in.intensity, in.point.z, size, in.instance, u32(is_highlighted(in.point)),
+  );
+}
 
-  return VertexOut(vert, to_fragment_coords(point.x, point.y), in.intensity);
+@vertex fn vs_main(in: VertexIn) -> VertexOut {
+  return make_vertex(in, 0.);
 } 
 
+// Depth pre-pass of the high quality splats, pushed back so overlapping
+// splats within epsilon still pass the depth test when accumulating
+@vertex fn vs_depth(in: VertexIn) -> VertexOut {
+  return make_vertex(in, unif.depth_epsilon);
+}
+
+fn point_color(in: VertexOut) -> vec3f {
+  return shade(in.intensity, in.elevation, in.highlighted != 0u);
+}
+
+// 0 at the center of the splat, 1 at its border
+fn splat_radius(in: VertexOut) -> f32 {
+  return distance(in.position.xy, in.point) / (in.size*0.5);
+}
+
 @fragment fn fs_main(in: VertexOut) -> FragOut {
-  if circleEnabled && distance(in.position.xy, in.point) > unif.pixels*0.5 {
+  if circleEnabled && splat_radius(in) > 1. {
+    discard;
+  }
+
+  return FragOut(vec4f(point_color(in), 1));
+}
+
+@fragment fn fs_depth(in: VertexOut) {
+  if splat_radius(in) > 1. {
+    discard;
+  }
+}
+
+// Additive pass of the high quality splats, normalised by the weight in alpha
+@fragment fn fs_accumulate(in: VertexOut) -> FragOut {
+  let r = splat_radius(in);
+  if r > 1. {
     discard;
   }
 
-  //let color = hsl_to_rgb(vec3f(
-  //  in.intensity,
-  //  1,
-  //  0.5));
+  let weight = exp(-r * r * 2.);
+  return FragOut(vec4f(point_color(in) * weight, weight));
+}
+
+// Picking pass, 0 is left for the background
+@fragment fn fs_pick(in: VertexOut) -> @location(0) u32 {
+  if circleEnabled && splat_radius(in) > 1. {
+    discard;
+  }
 
-  return FragOut(vec4f(vec3((in.intensity-36.)/4342.), 1)); // rango obtenido a mano WIP
+  return in.id + 1u;
 }
@@ -0,0 +1,27 @@
+// Leading fields of the point Uniforms, the rest isn't needed here
+struct Uniforms {
+  time: f32,
+  width: f32,
+  height: f32,
+  pixels: f32,
+  model_matrix: mat4x4f,
+  view_matrix: mat4x4f,
+};
+@group(0) @binding(0) var<uniform> unif: Uniforms;
+
+struct VertexIn {
+  @location(0) position: vec3f,
+  @location(1) color: vec4f,
+};
+struct VertexOut {
+  @builtin(position) position: vec4f,
+  @location(0) color: vec4f,
+};
+
+@vertex fn vs_main(in: VertexIn) -> VertexOut {
+  return VertexOut(unif.view_matrix * unif.model_matrix * vec4f(in.position, 1), in.color);
+}
+
+@fragment fn fs_main(in: VertexOut) -> @location(0) vec4f {
+  return in.color;
+}
@@ -0,0 +1,12 @@
+// Fills the profile inset's viewport before its points are drawn
+const BACKGROUND = vec4f(0.1, 0.1, 0.12, 1);
+
+@vertex fn vs_background(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  // Triangle covering the whole viewport
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * 2 - 1, 1, 1);
+}
+
+@fragment fn fs_background() -> @location(0) vec4f {
+  return BACKGROUND;
+}
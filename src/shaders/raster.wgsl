@@ -0,0 +1,57 @@
+// Compute rasteriser, 32-bit fallback: a depth pass keeps the closest depth
+// of each pixel and a color pass lets the point that won write its color.
+// raster64.wgsl does both at once when 64-bit atomics are available.
+
+// Same layout as the u64 of raster64.wgsl: color in the low word, depth in the high one
+struct Pixel {
+  color: u32,
+  depth: atomic<u32>,
+};
+@group(1) @binding(3) var<storage, read_write> framebuffer: array<Pixel>;
+
+@compute @workgroup_size(WORKGROUP_SIZE) fn cs_depth(
+  @builtin(global_invocation_id) id: vec3u,
+  @builtin(num_workgroups) groups: vec3u,
+) {
+  let index = point_index(id, groups);
+  if index >= arrayLength(&intensities) {
+    return;
+  }
+  let splat = project(index);
+  if !splat.visible {
+    return;
+  }
+
+  for (var y = -splat.radius; y <= splat.radius; y++) {
+    for (var x = -splat.radius; x <= splat.radius; x++) {
+      let pixel = pixel_index(splat, vec2i(x, y));
+      if pixel >= 0 {
+        atomicMax(&framebuffer[pixel].depth, splat.depth_key);
+      }
+    }
+  }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE) fn cs_color(
+  @builtin(global_invocation_id) id: vec3u,
+  @builtin(num_workgroups) groups: vec3u,
+) {
+  let index = point_index(id, groups);
+  if index >= arrayLength(&intensities) {
+    return;
+  }
+  let splat = project(index);
+  if !splat.visible {
+    return;
+  }
+
+  let color = point_color(index, splat.elevation, splat.highlighted);
+  for (var y = -splat.radius; y <= splat.radius; y++) {
+    for (var x = -splat.radius; x <= splat.radius; x++) {
+      let pixel = pixel_index(splat, vec2i(x, y));
+      if pixel >= 0 && atomicLoad(&framebuffer[pixel].depth) == splat.depth_key {
+        framebuffer[pixel].color = color;
+      }
+    }
+  }
+}
@@ -0,0 +1,30 @@
+// Compute rasteriser, depth and color packed in a single 64-bit atomic so
+// every point is projected once. Needs SHADER_INT64_ATOMIC_MIN_MAX, raster.wgsl
+// is the fallback without it.
+
+// Depth in the high word so atomicMax keeps the closest point, color in the low one
+@group(1) @binding(3) var<storage, read_write> framebuffer: array<atomic<u64>>;
+
+@compute @workgroup_size(WORKGROUP_SIZE) fn cs_main(
+  @builtin(global_invocation_id) id: vec3u,
+  @builtin(num_workgroups) groups: vec3u,
+) {
+  let index = point_index(id, groups);
+  if index >= arrayLength(&intensities) {
+    return;
+  }
+  let splat = project(index);
+  if !splat.visible {
+    return;
+  }
+
+  let value = (u64(splat.depth_key) << 32u) | u64(point_color(index, splat.elevation, splat.highlighted));
+  for (var y = -splat.radius; y <= splat.radius; y++) {
+    for (var x = -splat.radius; x <= splat.radius; x++) {
+      let pixel = pixel_index(splat, vec2i(x, y));
+      if pixel >= 0 {
+        atomicMax(&framebuffer[pixel], value);
+      }
+    }
+  }
+}
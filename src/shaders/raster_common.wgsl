@@ -0,0 +1,49 @@
+// Bindings and helpers shared by the compute rasterisers, prepended to
+// raster.wgsl and raster64.wgsl after common.wgsl. Each adds its framebuffer
+// at binding 3.
+const WORKGROUP_SIZE = 64u;
+
+@group(1) @binding(0) var<storage, read> points: array<f32>; // xyz triplets
+@group(1) @binding(1) var<storage, read> intensities: array<f32>;
+@group(1) @binding(2) var<storage, read> spacings: array<f32>;
+@group(1) @binding(4) var<storage, read> attributes: array<vec2u>; // packed attributes, gps time bits
+
+fn point_color(index: u32, elevation: f32, highlighted: bool) -> u32 {
+  return pack4x8unorm(vec4f(shade(intensities[index], elevation, highlighted), 1));
+}
+
+struct Splat {
+  center: vec2i,
+  radius: i32,
+  depth_key: u32, // larger is closer, so an empty framebuffer (0) is behind everything
+  elevation: f32,
+  visible: bool,
+  highlighted: bool,
+};
+
+fn project(index: u32) -> Splat {
+  let point = vec3f(points[index * 3u], points[index * 3u + 1u], points[index * 3u + 2u]);
+  let clip = unif.view_matrix * unif.model_matrix * vec4f(point, 1);
+  let ndc = clip.xyz / clip.w;
+  let packed = attributes[index];
+  let filtered = is_filtered(packed.x, bitcast<f32>(packed.y), intensities[index], point.z);
+  let visible = clip.w > 0. && ndc.z >= 0. && ndc.z <= 1. && !is_clipped(point) && !filtered;
+  let center = vec2i(floor(vec2f((ndc.x + 1.) * 0.5 * unif.width, (1. - ndc.y) * 0.5 * unif.height)));
+  // Capped by max_pixels like the pipeline backend
+  let radius = i32(point_size(spacings[index], clip.w) * 0.5);
+
+  return Splat(center, radius, 0xffffffffu - bitcast<u32>(ndc.z), point.z, visible, is_highlighted(point));
+}
+
+fn point_index(id: vec3u, groups: vec3u) -> u32 {
+  return id.x + id.y * groups.x * WORKGROUP_SIZE;
+}
+
+fn pixel_index(splat: Splat, offset: vec2i) -> i32 {
+  let pixel = splat.center + offset;
+  if dot(offset, offset) > splat.radius * splat.radius
+    || pixel.x < 0 || pixel.y < 0 || pixel.x >= i32(unif.width) || pixel.y >= i32(unif.height) {
+    return -1;
+  }
+  return pixel.x + pixel.y * i32(unif.width);
+}
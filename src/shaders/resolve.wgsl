@@ -0,0 +1,30 @@
+// Copies the framebuffer of the compute rasteriser into the scene and depth
+// textures, so post-processing doesn't need to know which backend drew it
+// Leading fields of the point Uniforms, the rest isn't needed here
+struct Uniforms {
+  time: f32,
+  width: f32,
+  height: f32,
+};
+@group(0) @binding(0) var<uniform> unif: Uniforms;
+@group(1) @binding(0) var<storage, read> framebuffer: array<vec2u>; // color, depth key
+
+struct FragOut {
+  @location(0) color: vec4f,
+  @builtin(frag_depth) depth: f32,
+};
+
+// Single triangle covering the whole screen
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * 2. - 1., 0, 1);
+}
+
+@fragment fn fs_main(@builtin(position) position: vec4f) -> FragOut {
+  let coords = vec2u(position.xy);
+  let pixel = framebuffer[coords.x + coords.y * u32(unif.width)];
+  if pixel.y == 0u {
+    discard; // no point landed here
+  }
+  return FragOut(unpack4x8unorm(pixel.x), bitcast<f32>(0xffffffffu - pixel.y));
+}
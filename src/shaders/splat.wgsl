@@ -0,0 +1,16 @@
+@group(0) @binding(0) var accumulation_texture: texture_2d<f32>;
+
+// Single triangle covering the whole screen
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * 2. - 1., 0, 1);
+}
+
+// Divides the weighted sum of colours by the sum of weights
+@fragment fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+  let accumulated = textureLoad(accumulation_texture, vec2i(position.xy), 0);
+  if accumulated.a <= 0. {
+    discard;
+  }
+  return vec4f(accumulated.rgb / accumulated.a, 1);
+}
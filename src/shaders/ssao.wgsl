@@ -0,0 +1,115 @@
+const pi = 3.14159265359;
+const GOLDEN_ANGLE = 2.39996323;
+const BLUR_RADIUS = 2;
+
+struct SsaoUniforms {
+  projection: mat4x4f,
+  inverse_projection: mat4x4f,
+  radius: f32, // world units
+  strength: f32,
+  bias: f32,
+  samples: u32,
+};
+@group(0) @binding(0) var depth_texture: texture_2d<f32>; // Depth32Float, read as unfilterable float
+@group(0) @binding(1) var<uniform> ssao: SsaoUniforms;
+@group(0) @binding(2) var ao_texture: texture_2d<f32>; // Only read by the blur
+
+// Single triangle covering the whole screen
+@vertex fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * 2. - 1., 0, 1);
+}
+
+fn depth_at(coords: vec2i) -> f32 {
+  let size = vec2i(textureDimensions(depth_texture));
+  return textureLoad(depth_texture, clamp(coords, vec2i(0), size - 1), 0).r;
+}
+
+fn view_position(coords: vec2i, depth: f32) -> vec3f {
+  let uv = (vec2f(coords) + 0.5) / vec2f(textureDimensions(depth_texture));
+  let view = ssao.inverse_projection * vec4f(uv.x * 2. - 1., 1. - uv.y * 2., depth, 1);
+  return view.xyz / view.w;
+}
+
+// Picks the neighbour closest in depth so edges don't bend the normal
+fn tangent(coords: vec2i, center: vec3f, step: vec2i) -> vec3f {
+  let forward = view_position(coords + step, depth_at(coords + step)) - center;
+  let backward = center - view_position(coords - step, depth_at(coords - step));
+  if abs(forward.z) < abs(backward.z) {
+    return forward;
+  }
+  return backward;
+}
+
+fn hash(coords: vec2i) -> f32 {
+  return fract(sin(dot(vec2f(coords), vec2f(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment fn fs_ao(@builtin(position) position: vec4f) -> @location(0) vec4f {
+  let coords = vec2i(position.xy);
+  let depth = depth_at(coords);
+  if depth >= 1. {
+    return vec4f(1);
+  }
+
+  let center = view_position(coords, depth);
+  var normal = normalize(cross(tangent(coords, center, vec2i(0, 1)), tangent(coords, center, vec2i(1, 0))));
+  if dot(normal, center) > 0. {
+    normal = -normal; // Face the camera
+  }
+
+  let size = vec2f(textureDimensions(depth_texture));
+  let w = (ssao.projection * vec4f(center, 1)).w;
+  let radius_pixels = ssao.radius * ssao.projection[1][1] * 0.5 * size.y / w;
+  let rotation = hash(coords) * 2. * pi;
+
+  var occlusion = 0.;
+  for (var i = 0u; i < ssao.samples; i++) {
+    let t = (f32(i) + 0.5) / f32(ssao.samples);
+    let angle = rotation + f32(i) * GOLDEN_ANGLE;
+    let sample_coords = coords + vec2i(vec2f(cos(angle), sin(angle)) * t * radius_pixels);
+    let sample_depth = depth_at(sample_coords);
+    if sample_depth >= 1. {
+      continue;
+    }
+
+    let v = view_position(sample_coords, sample_depth) - center;
+    let dist = length(v);
+    if dist > 1e-6 && dist < ssao.radius {
+      occlusion += max(0., dot(v / dist, normal) - ssao.bias);
+    }
+  }
+
+  let ao = clamp(1. - ssao.strength * occlusion / f32(max(ssao.samples, 1u)), 0., 1.);
+  return vec4f(ao, ao, ao, 1);
+}
+
+// Bilateral blur, neighbours at a different depth don't bleed into each other
+@fragment fn fs_blur(@builtin(position) position: vec4f) -> @location(0) vec4f {
+  let coords = vec2i(position.xy);
+  let depth = depth_at(coords);
+  if depth >= 1. {
+    return vec4f(1);
+  }
+  let center = view_position(coords, depth).z;
+  let size = vec2i(textureDimensions(ao_texture));
+
+  var total = 0.;
+  var weights = 0.;
+  for (var y = -BLUR_RADIUS; y <= BLUR_RADIUS; y++) {
+    for (var x = -BLUR_RADIUS; x <= BLUR_RADIUS; x++) {
+      let sample_coords = clamp(coords + vec2i(x, y), vec2i(0), size - 1);
+      let sample_depth = depth_at(sample_coords);
+      if sample_depth >= 1. {
+        continue;
+      }
+      let difference = abs(view_position(sample_coords, sample_depth).z - center) / center;
+      let weight = exp(-f32(x * x + y * y) / 8.) * exp(-difference * difference * 1000.);
+      total += textureLoad(ao_texture, sample_coords, 0).r * weight;
+      weights += weight;
+    }
+  }
+
+  let ao = total / max(weights, 1e-6);
+  return vec4f(ao, ao, ao, 1);
+}
@@ -0,0 +1,40 @@
+//! Checks what the page hands to the viewer before it is queued.
+
+use lib_webgpu_pointcloud::{
+    api::Viewer,
+    engine::{ColorMode, PointSizeMode},
+};
+
+#[test]
+fn names_parse() {
+    assert_eq!("Elevation".parse(), Ok(ColorMode::Elevation));
+    assert_eq!(ColorMode::Intensity.to_string(), "intensity");
+    assert!("rainbow".parse::<ColorMode>().is_err());
+    assert_eq!("adaptive".parse(), Ok(PointSizeMode::Adaptive));
+    assert_eq!(PointSizeMode::Attenuated.to_string(), "attenuated");
+}
+
+#[test]
+fn invalid_arguments_are_refused() {
+    let viewer = Viewer::default();
+    assert!(viewer.set_color_mode("elevation").is_ok());
+    assert!(viewer.set_color_mode("rainbow").is_err());
+    assert!(viewer.set_point_size(3.).is_ok());
+    assert!(viewer.set_point_size(0.).is_err());
+    assert!(viewer.set_point_size(f32::NAN).is_err());
+    assert!(viewer.set_point_size_mode("fixed").is_ok());
+    assert!(viewer.set_point_size_mode("huge").is_err());
+
+    let camera = r#"{"target": [1, 2, 3], "theta": 1.2, "phi": 0.5, "farness": 40, "orthographic": false}"#;
+    assert!(viewer.set_camera(camera, None).is_ok());
+    assert!(viewer.set_camera(camera, Some(2.)).is_ok());
+    assert!(viewer.set_camera(r#"{"target": [1, 2, 3]}"#, None).is_err());
+}
+
+#[test]
+fn nothing_to_read_back_before_the_viewer_runs() {
+    let viewer = Viewer::default();
+    assert_eq!(viewer.get_camera(), "null");
+    assert_eq!(viewer.get_bookmarks(), "[]");
+    assert_eq!(viewer.get_path(), "[]");
+}
@@ -0,0 +1,114 @@
+//! Saves views as bookmarks, writes them to JSON and back, and flies between
+//! them without jumping.
+
+mod common;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::{
+    bookmarks::{Bookmarks, CameraView},
+    controls::Action,
+    engine::{CameraMode, InputServer},
+};
+use winit::event::ElementState;
+use common::{assert_near, orbit_camera};
+
+fn view(target: Vec3, phi: f32, farness: f32) -> CameraView {
+    CameraView { target, theta: FRAC_PI_2, phi, farness, orthographic: false }
+}
+
+#[test]
+fn views_come_from_the_orbit_camera() {
+    let view = CameraView::from_camera(&orbit_camera(), 1.);
+    assert_near(view.target, Vec3::ZERO);
+    assert!((view.theta - FRAC_PI_2).abs() < 1e-6 && view.phi.abs() < 1e-6 && (view.farness - 2.).abs() < 1e-6);
+    assert!(!view.orthographic);
+
+    // Orthographic at the scale perspective would show from 2 away
+    let mut camera = orbit_camera();
+    camera.projection.to_orthographic(2.);
+    let orthographic = CameraView::from_camera(&camera, 60f32.to_radians());
+    assert!(orthographic.orthographic && (orthographic.farness - 2.).abs() < 1e-5);
+}
+
+#[test]
+fn bookmarks_by_name() {
+    let mut bookmarks = Bookmarks::default();
+    bookmarks.save("gate", view(Vec3::X, 0., 2.));
+    bookmarks.save("tower", view(Vec3::Y, 1., 3.));
+    bookmarks.save("gate", view(Vec3::Z, 0., 4.));
+    assert_eq!(bookmarks.len(), 2);
+    assert_eq!(bookmarks.get("gate").unwrap().view.target, Vec3::Z);
+    assert_eq!(bookmarks.iter().map(|bookmark| bookmark.name.as_str()).collect::<Vec<_>>(), ["gate", "tower"]);
+
+    assert_eq!(bookmarks.after(None).unwrap().name, "gate");
+    assert_eq!(bookmarks.after(Some("gate")).unwrap().name, "tower");
+    assert_eq!(bookmarks.after(Some("tower")).unwrap().name, "gate");
+
+    let json = bookmarks.to_json().unwrap();
+    assert!(json.contains("\"name\": \"tower\"") && json.contains("\"farness\": 3.0"), "{json}");
+    assert_eq!(Bookmarks::from_json(&json).unwrap(), bookmarks);
+
+    assert!(bookmarks.remove("gate"));
+    assert!(!bookmarks.remove("gate"));
+    assert_eq!(bookmarks.after(Some("gate")).unwrap().name, "tower");
+    assert!(Bookmarks::from_json("{\"name\": 1}").is_err());
+}
+
+#[test]
+fn interpolation_takes_the_short_way() {
+    let from = view(Vec3::ZERO, PI - 0.1, 1.);
+    let to = view(Vec3::new(2., 0., 0.), -PI + 0.1, 4.);
+    let half = from.lerp(&to, 0.5);
+    assert_near(half.target, Vec3::X);
+    assert!((half.phi - PI).abs() < 1e-5, "turned to {}", half.phi);
+    assert!((half.farness - 2.).abs() < 1e-5);
+    assert_eq!(from.lerp(&to, 0.), from);
+}
+
+#[test]
+fn flying_to_a_bookmark() {
+    let mut camera = orbit_camera();
+    let mut input = InputServer::new();
+    input.set_mode(CameraMode::Fly);
+    input.update(&mut camera, 0.);
+    let start = input.view(&camera);
+
+    let to = CameraView { target: Vec3::new(1., 1., 0.), theta: 1., phi: 1., farness: 4., orthographic: true };
+    input.fly_to(to, 1.);
+    input.update(&mut camera, 0.);
+    assert_eq!(input.mode(), CameraMode::Orbit);
+    assert_near(camera.eye, Vec3::new(2., 0., 0.));
+
+    // Eased: a quarter of the way it has covered less than a quarter
+    input.update(&mut camera, 0.25);
+    let quarter = camera.target.distance(start.target) / to.target.distance(start.target);
+    assert!(quarter > 0.05 && quarter < 0.25, "{quarter} of the way");
+
+    input.update(&mut camera, 0.75);
+    let arrived = input.view(&camera);
+    assert_near(arrived.target, to.target);
+    assert!((arrived.theta - 1.).abs() < 1e-4 && (arrived.phi - 1.).abs() < 1e-4, "at {arrived:?}");
+    assert!((arrived.farness - 4.).abs() < 1e-4 && arrived.orthographic, "at {arrived:?}");
+
+    // Stays there
+    input.update(&mut camera, 1.);
+    assert_near(input.view(&camera).target, to.target);
+}
+
+#[test]
+fn input_stops_the_flight() {
+    let mut camera = orbit_camera();
+    let mut input = InputServer::new();
+    input.fly_to(view(Vec3::new(4., 0., 0.), 0., 2.), 1.);
+    input.update(&mut camera, 0.5);
+    let stopped = camera.target;
+    assert!(stopped.x > 1. && stopped.x < 3., "target at {stopped}");
+
+    input.process_action(Action::MoveForward, ElementState::Pressed);
+    input.process_action(Action::MoveForward, ElementState::Released);
+    input.update(&mut camera, 10.);
+    assert!(camera.target.distance(stopped) < 0.5, "target at {}", camera.target);
+}
+
@@ -0,0 +1,207 @@
+//! Switches the camera between perspective and orthographic projections and
+//! checks what is at the target keeps its size on screen, then fits boxes in
+//! view from every preset, zooms towards points and pans with the cursor.
+
+mod common;
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::engine::{Camera, CameraController, Input, InputServer, Projection, ViewPreset, ZoomAnchor};
+use winit::event::MouseScrollDelta;
+use common::{orbit_camera, FOVY, HEIGHT, WIDTH};
+
+// Seconds for zoom and rotation to catch up with the input
+const SETTLE: f32 = 10.;
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() <= 1e-5 * a.abs().max(b.abs()), "{a} != {b}");
+}
+
+#[test]
+fn projections_convert_at_the_same_scale() {
+    let mut camera = orbit_camera();
+    let pixel_size = camera.pixel_size(HEIGHT);
+
+    camera.projection.to_orthographic(2.);
+    let Projection::Orthographic { width, height, .. } = camera.projection else {
+        panic!("expected an orthographic projection");
+    };
+    assert_close(height, 2. * 2. * (FOVY / 2.).tan());
+    assert_close(width / height, WIDTH / HEIGHT);
+    assert_close(camera.pixel_size(HEIGHT), pixel_size);
+
+    camera.projection.to_perspective(2.);
+    let Projection::Perspective { fovy, aspect, .. } = camera.projection else {
+        panic!("expected a perspective projection");
+    };
+    assert_close(fovy, FOVY);
+    assert_close(aspect, WIDTH / HEIGHT);
+}
+
+#[test]
+fn orthographic_resize_keeps_the_scale() {
+    let mut camera = orbit_camera();
+    camera.projection.to_orthographic(2.);
+    let pixel_size = camera.pixel_size(HEIGHT);
+
+    camera.projection.resize(HEIGHT, WIDTH, 300., 1000.);
+    let Projection::Orthographic { width, height, .. } = camera.projection else {
+        panic!("expected an orthographic projection");
+    };
+    assert_close(width / height, 1000. / 300.);
+    assert_close(camera.pixel_size(300.), pixel_size);
+}
+
+#[test]
+fn controller_switches_projection_and_zooms() {
+    let mut camera = orbit_camera();
+    let mut controller = CameraController::new(0.05, 2., 0.01, 0.1);
+    controller.update_camera(&mut camera, SETTLE);
+    let pixel_size = camera.pixel_size(HEIGHT);
+
+    controller.set_orthographic(true);
+    controller.update_camera(&mut camera, SETTLE);
+    assert!(camera.projection.is_orthographic());
+    assert_close(camera.pixel_size(HEIGHT), pixel_size);
+
+    // Scrolling changes the extents, the eye stays where it is
+    controller.process_scroll(&MouseScrollDelta::LineDelta(0., 1.));
+    controller.update_camera(&mut camera, SETTLE);
+    assert_eq!(controller.farness(), 2.);
+    assert_close(camera.pixel_size(HEIGHT), pixel_size / 1.1);
+
+    // Back in perspective the field of view is the same, the eye moved closer instead
+    controller.set_orthographic(false);
+    controller.update_camera(&mut camera, SETTLE);
+    let Projection::Perspective { fovy, .. } = camera.projection else {
+        panic!("expected a perspective projection");
+    };
+    assert_close(fovy, FOVY);
+    assert_close(controller.farness(), 2. / 1.1);
+    assert_close(camera.pixel_size(HEIGHT), pixel_size / 1.1);
+    assert_close(camera.eye.distance(camera.target), 2. / 1.1);
+
+    controller.process_scroll(&MouseScrollDelta::LineDelta(0., -1.));
+    controller.update_camera(&mut camera, SETTLE);
+    assert_close(controller.farness(), 2.);
+}
+
+#[test]
+fn presets_look_along_the_axes() {
+    let mut camera = orbit_camera();
+    let mut controller = CameraController::new(0.05, 2., 0.01, 0.1);
+    for (preset, eye, up) in [
+        (ViewPreset::Top, Vec3::Z, Vec3::Y),
+        (ViewPreset::Front, Vec3::NEG_Y, Vec3::Z),
+        (ViewPreset::Back, Vec3::Y, Vec3::Z),
+        (ViewPreset::Left, Vec3::NEG_X, Vec3::Z),
+        (ViewPreset::Right, Vec3::X, Vec3::Z),
+        (ViewPreset::Isometric, Vec3::new(1., -1., 1.).normalize(), Vec3::new(-1., 1., 2.).normalize()),
+    ] {
+        controller.set_view(preset);
+        controller.update_camera(&mut camera, SETTLE);
+        assert!(((camera.eye - camera.target) / 2.).abs_diff_eq(eye, 1e-6), "{preset:?} eye at {}", camera.eye);
+        assert!(camera.up.abs_diff_eq(up, 1e-6), "{preset:?} up is {}", camera.up);
+    }
+
+    controller.set_view(ViewPreset::Bottom);
+    controller.update_camera(&mut camera, SETTLE);
+    assert!(((camera.eye - camera.target) / 2.).abs_diff_eq(Vec3::NEG_Z, 1e-6));
+    assert_eq!("Isometric".parse(), Ok(ViewPreset::Isometric));
+    assert!("sideways".parse::<ViewPreset>().is_err());
+}
+
+#[test]
+fn fit_brings_the_whole_box_into_view() {
+    let (min, max) = (Vec3::new(100., 200., 10.), Vec3::new(400., 260., 40.));
+    let corners = (0..8).map(|i| Vec3::select(glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min));
+
+    for (width, height) in [(WIDTH, HEIGHT), (300., 900.)] {
+        for orthographic in [false, true] {
+            for preset in [ViewPreset::Top, ViewPreset::Front, ViewPreset::Right, ViewPreset::Isometric] {
+                let mut camera = orbit_camera();
+                camera.projection.resize(HEIGHT, WIDTH, height, width);
+                if orthographic {
+                    camera.projection.to_orthographic(2.);
+                }
+                let mut controller = CameraController::new(0.05, 2., 0.01, 0.1);
+                controller.set_view(preset);
+                controller.fit(&mut camera, min, max);
+                controller.update_camera(&mut camera, SETTLE);
+                assert_eq!(camera.target, (min + max) * 0.5);
+
+                let view_projection = camera.build_view_projection_matrix();
+                for corner in corners.clone() {
+                    let ndc = view_projection.project_point3(corner);
+                    assert!(
+                        ndc.x.abs() <= 1. && ndc.y.abs() <= 1. && (0. ..=1.).contains(&ndc.z),
+                        "{corner} out of view at {ndc} from {preset:?}, {width}x{height}, orthographic {orthographic}",
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Pixels from the centre of the view, y down
+fn to_screen(camera: &Camera, point: Vec3) -> glam::Vec2 {
+    let ndc = camera.build_view_projection_matrix().project_point3(point);
+    glam::Vec2::new(ndc.x * WIDTH, -ndc.y * HEIGHT) * 0.5
+}
+
+#[test]
+fn zoom_keeps_the_anchor_in_place() {
+    for orthographic in [false, true] {
+        let mut camera = orbit_camera();
+        let mut controller = CameraController::new(0.05, 2., 0.01, 0.1);
+        controller.set_orthographic(orthographic);
+        controller.update_camera(&mut camera, SETTLE);
+        let anchor = Vec3::new(0.3, 0.4, -0.2);
+        let before = to_screen(&camera, anchor);
+
+        // Held until the anchor is known
+        controller.set_zoom_anchor(ZoomAnchor::Pending);
+        controller.process_scroll(&MouseScrollDelta::LineDelta(0., 3.));
+        controller.update_camera(&mut camera, SETTLE);
+        assert_eq!(camera.target, Vec3::ZERO);
+
+        controller.set_zoom_anchor(ZoomAnchor::Point(anchor));
+        for _ in 0..10 {
+            controller.update_camera(&mut camera, 0.01);
+            assert!(to_screen(&camera, anchor).abs_diff_eq(before, 1e-2), "anchor moved to {}", to_screen(&camera, anchor));
+        }
+        controller.update_camera(&mut camera, SETTLE);
+        assert!(to_screen(&camera, anchor).abs_diff_eq(before, 1e-2));
+        assert!(camera.target.abs_diff_eq(anchor * (1. - 1. / 1.3), 1e-5), "target at {}", camera.target);
+    }
+}
+
+#[test]
+fn dragging_pans_with_the_cursor() {
+    let mut camera = orbit_camera();
+    let mut controller = CameraController::new(0.05, 2., 0.01, 0.1);
+    controller.viewport_height = HEIGHT;
+    controller.set_view(ViewPreset::Isometric);
+    controller.update_camera(&mut camera, SETTLE);
+
+    let point = camera.target;
+    controller.process_pan(40., -25.);
+    controller.update_camera(&mut camera, SETTLE);
+    assert!(to_screen(&camera, point).abs_diff_eq(glam::Vec2::new(40., -25.), 1e-2), "point at {}", to_screen(&camera, point));
+    assert!((camera.eye.distance(camera.target) - 2.).abs() < 1e-5);
+}
+
+#[test]
+fn recentring_turns_to_the_point() {
+    let mut camera = orbit_camera();
+    let mut input = InputServer::new();
+    input.update(&mut camera, SETTLE);
+    let eye = camera.eye;
+
+    let point = Vec3::new(-1., 1., 0.5);
+    input.recentre(&mut camera, point);
+    assert!(camera.eye.abs_diff_eq(eye, 1e-5));
+    assert!(camera.target.abs_diff_eq(point, 1e-5));
+    input.update(&mut camera, SETTLE);
+    assert!(camera.eye.abs_diff_eq(eye, 1e-5));
+    assert!(camera.target.abs_diff_eq(point, 1e-5));
+}
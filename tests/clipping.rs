@@ -0,0 +1,57 @@
+use glam::{Quat, Vec3};
+use lib_webgpu_pointcloud::engine::{ClipBox, ClipMode, ClipPlane, ClipSettings};
+
+#[test]
+fn oriented_box() {
+    let mut clip_box = ClipBox::new(Vec3::new(1., 0., 0.), Vec3::new(1., 0.1, 0.1));
+    assert!(clip_box.contains(Vec3::new(1.9, 0., 0.)));
+    assert!(!clip_box.contains(Vec3::new(1., 0.5, 0.)));
+
+    // A quarter turn swaps the long side to y
+    clip_box.rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+    assert!(!clip_box.contains(Vec3::new(1.9, 0., 0.)));
+    assert!(clip_box.contains(Vec3::new(1., 0.9, 0.)));
+
+    let edges = clip_box.edges();
+    assert_eq!(edges.len(), 12);
+    for (start, end) in edges {
+        let length = start.distance(end);
+        assert!([2., 0.2].iter().any(|side| (length - side).abs() < 1e-5), "edge of length {length}");
+    }
+}
+
+#[test]
+fn planes_keep_the_back_side() {
+    let plane = ClipPlane { point: Vec3::new(0., 0., 2.), normal: Vec3::Z * 3. };
+    assert!(plane.contains(Vec3::new(5., 5., 1.9)));
+    assert!(!plane.contains(Vec3::new(0., 0., 2.1)));
+    assert_eq!(plane.outline(1.).len(), 5);
+}
+
+#[test]
+fn modes() {
+    let inside = Vec3::ZERO;
+    let outside = Vec3::new(0., 0., 3.);
+    let mut clipping = ClipSettings {
+        mode: ClipMode::Inside,
+        boxes: vec![ClipBox::new(Vec3::ZERO, Vec3::ONE)],
+        planes: vec![ClipPlane { point: Vec3::new(0., 0., 0.5), normal: Vec3::Z }],
+    };
+
+    assert!(clipping.is_visible(inside));
+    assert!(!clipping.is_visible(outside));
+    assert!(!clipping.is_visible(Vec3::new(0., 0., 0.75)), "in the box but above the plane");
+
+    clipping.mode = ClipMode::Outside;
+    assert!(!clipping.is_visible(inside));
+    assert!(clipping.is_visible(outside));
+
+    for mode in [ClipMode::Disabled, ClipMode::Highlight] {
+        clipping.mode = mode;
+        assert!(clipping.is_visible(inside) && clipping.is_visible(outside));
+    }
+
+    // Nothing to clip with
+    clipping = ClipSettings { mode: ClipMode::Inside, ..Default::default() };
+    assert!(clipping.is_visible(outside));
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by the test files, each of which uses only some of them
+
+#![allow(dead_code)]
+
+use std::f32::consts::FRAC_PI_3;
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::engine::{Camera, Projection};
+
+pub const WIDTH: f32 = 800.;
+pub const HEIGHT: f32 = 600.;
+/// Vertical field of view of `camera`, 60°
+pub const FOVY: f32 = FRAC_PI_3;
+
+/// Perspective camera at `eye` looking at `target`, Z up, on an 800x600 view
+pub fn camera(eye: Vec3, target: Vec3) -> Camera {
+    Camera {
+        eye,
+        target,
+        up: Vec3::Z,
+        projection: Projection::perspective(0.01, 100., WIDTH, HEIGHT, FOVY),
+    }
+}
+
+pub fn assert_near(a: Vec3, b: Vec3) {
+    assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+}
+
+/// `camera` 2 along X looking at the origin
+pub fn orbit_camera() -> Camera {
+    camera(Vec3::new(2., 0., 0.), Vec3::ZERO)
+}
@@ -0,0 +1,96 @@
+//! Remaps controls from TOML and JSON, and refuses bindings that would set
+//! off two actions at once.
+
+use lib_webgpu_pointcloud::controls::{Action, Binding, Bindings, BindingsError, Modifiers, Trigger};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+const NONE: Modifiers = Modifiers { shift: false, ctrl: false, alt: false };
+const SHIFT: Modifiers = Modifiers { shift: true, ctrl: false, alt: false };
+const CTRL: Modifiers = Modifiers { shift: false, ctrl: true, alt: false };
+
+fn key(key: KeyCode) -> Trigger {
+    Trigger::Key(key)
+}
+
+#[test]
+fn defaults_have_no_conflicts() {
+    let bindings = Bindings::default();
+    assert_eq!(bindings.conflicts(), []);
+
+    assert_eq!(bindings.action(key(KeyCode::KeyW), NONE), Some(Action::MoveForward));
+    assert_eq!(bindings.action(Trigger::Mouse(MouseButton::Middle), NONE), Some(Action::Pan));
+    assert_eq!(bindings.action(key(KeyCode::Numpad1), CTRL), Some(Action::ViewBack));
+    assert_eq!(bindings.action(key(KeyCode::Numpad1), NONE), Some(Action::ViewFront));
+    assert_eq!(bindings.action(key(KeyCode::BracketRight), SHIFT), Some(Action::WidenProfile));
+    assert_eq!(bindings.action(key(KeyCode::BracketRight), NONE), Some(Action::GrowGizmo));
+    assert_eq!(bindings.action(key(KeyCode::KeyZ), NONE), None);
+
+    // Held actions keep going when modifiers change, unbound modifiers are ignored
+    assert_eq!(bindings.action(key(KeyCode::KeyW), CTRL), Some(Action::MoveForward));
+    assert_eq!(bindings.action(key(KeyCode::ShiftLeft), SHIFT), Some(Action::Faster));
+    assert_eq!(bindings.action(key(KeyCode::KeyC), SHIFT), Some(Action::CycleColorMode));
+
+    // Held actions can share a button with different modifiers
+    let left = Trigger::Mouse(MouseButton::Left);
+    assert_eq!(bindings.action(left, SHIFT), Some(Action::MoveGizmo));
+    assert_eq!(bindings.action(left, NONE), Some(Action::Rotate));
+    assert_eq!(bindings.action(left, CTRL), Some(Action::Rotate));
+}
+
+#[test]
+fn bindings_read_and_write_the_same() {
+    for text in ["KeyW", "Ctrl+Numpad1", "Ctrl+Alt+Shift+F5", "MouseMiddle", "Shift+MouseLeft"] {
+        let binding: Binding = text.parse().unwrap();
+        assert_eq!(binding.to_string(), text);
+    }
+    assert_eq!("control+shift+KeyA".parse(), Ok(Binding::key(KeyCode::KeyA).shift().ctrl()));
+    assert_eq!("mouseright".parse(), Ok(Binding::mouse(MouseButton::Right)));
+    assert!("Hyper+KeyA".parse::<Binding>().is_err());
+    assert!("KeyÄ".parse::<Binding>().is_err());
+}
+
+#[test]
+fn remapping_for_azerty() {
+    let toml = r#"
+        move_forward = ["KeyZ", "ArrowUp"]
+        move_left = ["KeyQ", "ArrowLeft"]
+        move_down = ["KeyA"]
+        cycle_camera_mode = []
+    "#;
+    let bindings = Bindings::from_toml(toml).unwrap();
+    assert_eq!(bindings.action(key(KeyCode::KeyZ), NONE), Some(Action::MoveForward));
+    assert_eq!(bindings.action(key(KeyCode::KeyQ), NONE), Some(Action::MoveLeft));
+    assert_eq!(bindings.action(key(KeyCode::KeyA), NONE), Some(Action::MoveDown));
+    assert_eq!(bindings.action(key(KeyCode::KeyW), NONE), None);
+    assert_eq!(bindings.action(key(KeyCode::KeyR), NONE), None);
+    assert_eq!(bindings.bindings(Action::MoveRight), Bindings::default().bindings(Action::MoveRight));
+
+    // Written out and read back as JSON
+    let json = serde_json::to_string(&bindings).unwrap();
+    assert_eq!(Bindings::from_json(&json).unwrap(), bindings);
+}
+
+#[test]
+fn conflicts_are_refused() {
+    // C already cycles the colour mode
+    let Err(BindingsError::Conflicts(conflicts)) = Bindings::from_json(r#"{"fit_to_extent": ["KeyC"]}"#) else {
+        panic!("C bound twice");
+    };
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].binding, Binding::key(KeyCode::KeyC));
+    assert_eq!(conflicts[0].actions, (Action::FitToExtent, Action::CycleColorMode));
+    assert_eq!(conflicts[0].to_string(), "KeyC is bound to both fit_to_extent and cycle_color_mode");
+
+    // A held key is taken with every modifier, other keys only as written
+    assert!(Bindings::from_json(r#"{"fit_to_extent": ["Ctrl+KeyW"]}"#).is_err());
+    assert!(Bindings::from_json(r#"{"fit_to_extent": ["Ctrl+KeyC"]}"#).is_ok());
+    assert!(Bindings::from_json(r#"{"move_gizmo": ["MouseLeft"]}"#).is_err());
+    assert!(Bindings::from_json(r#"{"move_gizmo": ["Ctrl+MouseLeft"]}"#).is_ok());
+
+    // Swapping two actions is fine when both move
+    let swapped = Bindings::from_json(r#"{"rotate": ["MouseRight"], "pan": ["MouseLeft"]}"#).unwrap();
+    assert_eq!(swapped.action(Trigger::Mouse(MouseButton::Right), NONE), Some(Action::Rotate));
+
+    assert!(matches!(Bindings::from_json(r#"{"jump": ["Space"]}"#), Err(BindingsError::Invalid(_))));
+    assert!(matches!(Bindings::from_toml("move_forward = \"KeyW\""), Err(BindingsError::Invalid(_))));
+}
@@ -0,0 +1,81 @@
+use glam::DVec3;
+use lib_webgpu_pointcloud::{
+    api::Viewer,
+    engine::{ClassificationSet, FilterSettings, ReturnFilter},
+    pointcloud::SourcePoint,
+};
+
+fn point(classification: u8, return_number: u8, number_of_returns: u8) -> SourcePoint {
+    SourcePoint {
+        position: DVec3::new(0., 0., 150.),
+        intensity: 300,
+        classification,
+        return_number,
+        number_of_returns,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn classification_sets() {
+    let mut set: ClassificationSet = [2, 6, 200].into_iter().collect();
+    assert!(set.contains(2) && set.contains(200) && !set.contains(3));
+    set.remove(6);
+    set.insert(255);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [2, 200, 255]);
+    assert!((0..=u8::MAX).all(|class| ClassificationSet::default().contains(class)));
+}
+
+#[test]
+fn returns() {
+    let kept = |filter: ReturnFilter| {
+        [(1, 1), (1, 3), (2, 3), (3, 3)]
+            .into_iter()
+            .filter(|(number, count)| filter.accepts(*number, *count))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(kept(ReturnFilter::All).len(), 4);
+    assert_eq!(kept(ReturnFilter::First), [(1, 1), (1, 3)]);
+    assert_eq!(kept(ReturnFilter::Last), [(1, 1), (3, 3)]);
+    assert_eq!(kept(ReturnFilter::Intermediate), [(2, 3)]);
+    assert_eq!(kept(ReturnFilter::Single), [(1, 1)]);
+}
+
+#[test]
+fn settings_combine() {
+    let filter = FilterSettings {
+        number_of_returns: Some((2, 4)),
+        intensity: Some((100., 500.)),
+        elevation: Some((100., 200.)),
+        gps_time: Some((10., 20.)),
+        ..FilterSettings::hide_vegetation()
+    };
+    assert!(filter.accepts(&point(2, 1, 2)));
+    assert!(!filter.accepts(&point(4, 1, 2)), "vegetation");
+    assert!(!filter.accepts(&point(2, 1, 1)), "single return");
+    assert!(!filter.accepts(&SourcePoint { intensity: 600, ..point(2, 1, 2) }));
+    assert!(!filter.accepts(&SourcePoint { position: DVec3::new(0., 0., 250.), ..point(2, 1, 2) }));
+    assert!(!filter.accepts(&SourcePoint { gps_time: Some(30.), ..point(2, 1, 2) }));
+    assert!(filter.accepts(&SourcePoint { gps_time: Some(15.), ..point(2, 1, 2) }));
+
+    assert!(FilterSettings::ground().accepts(&point(2, 1, 1)));
+    assert!(!FilterSettings::ground().accepts(&point(6, 1, 1)));
+}
+
+#[test]
+fn from_json() {
+    let filter: FilterSettings =
+        serde_json::from_str(r#"{"classifications": [2, 6], "returns": "last", "elevation": [100, 250.5]}"#).unwrap();
+    assert_eq!(filter.classifications, [2, 6].into_iter().collect());
+    assert_eq!(filter.returns, ReturnFilter::Last);
+    assert_eq!(filter.elevation, Some((100., 250.5)));
+    assert_eq!(filter.intensity, None);
+
+    let json = serde_json::to_string(&filter).unwrap();
+    assert_eq!(serde_json::from_str::<FilterSettings>(&json).unwrap(), filter);
+    assert_eq!(serde_json::from_str::<FilterSettings>("{}").unwrap(), FilterSettings::default());
+
+    let viewer = Viewer::default();
+    assert!(viewer.set_filter(r#"{"returns": "last"}"#).is_ok());
+    assert!(viewer.set_filter(r#"{"returns": "second"}"#).is_err());
+}
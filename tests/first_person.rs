@@ -0,0 +1,124 @@
+//! Flies and walks over a sloped ground, and switches between controllers
+//! without the camera jumping.
+
+mod common;
+
+use glam::{Vec2, Vec3};
+use lib_webgpu_pointcloud::engine::{
+    CameraMode, FlyController, Ground, Input, InputServer, WalkController,
+};
+use lib_webgpu_pointcloud::controls::Action;
+use winit::event::ElementState;
+use common::{assert_near, camera};
+
+// Rises 1 for every 10 along x, with a tree every few meters
+fn slope() -> Vec<Vec3> {
+    let mut points = Vec::new();
+    for x in 0..100 {
+        for y in 0..100 {
+            let (x, y) = (x as f32 * 0.5, y as f32 * 0.5);
+            points.push(Vec3::new(x, y, x * 0.1));
+            if x % 5. == 0. && y % 5. == 0. {
+                points.push(Vec3::new(x, y, x * 0.1 + 8.));
+            }
+        }
+    }
+    points
+}
+
+#[test]
+fn ground_is_the_lowest_nearby_point() {
+    let ground = Ground::new(&slope());
+    for x in [1., 12.3, 40.] {
+        let height = ground.height(Vec2::new(x, 20.)).unwrap();
+        assert!((height - x * 0.1).abs() < 0.2, "{height} at {x}");
+    }
+    assert_eq!(ground.height(Vec2::new(-100., 0.)), None);
+    assert_eq!(Ground::new(&[]).height(Vec2::ZERO), None);
+}
+
+#[test]
+fn flying_follows_the_view() {
+    let mut camera = camera(Vec3::new(10., 10., 5.), Vec3::new(20., 10., 5.));
+    let mut fly = FlyController::new(1., 0.01);
+    fly.attach(&camera);
+    fly.update_camera(&mut camera, 1.);
+    assert_near(camera.eye, Vec3::new(10., 10., 5.));
+    assert_near(camera.target, Vec3::new(20., 10., 5.));
+
+    // Looking up stops short of vertical, then back down to 45° and forward,
+    // at full speed once it has picked up
+    fly.process_mouse(0., -1000.);
+    assert!(fly.direction().z > 0.99 && fly.direction().z < 1.);
+    fly.process_mouse(0., (std::f64::consts::FRAC_PI_4 - 0.01) * 100.);
+    fly.process_action(Action::MoveForward, ElementState::Pressed);
+    fly.update_camera(&mut camera, 5.);
+    let start = camera.eye;
+    fly.update_camera(&mut camera, 1.);
+    let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+    assert_near(camera.eye - start, Vec3::new(diagonal, 0., diagonal));
+
+    // Faster, straight up
+    fly.process_action(Action::MoveForward, ElementState::Released);
+    fly.process_action(Action::Faster, ElementState::Pressed);
+    fly.process_action(Action::MoveUp, ElementState::Pressed);
+    fly.update_camera(&mut camera, 5.);
+    let start = camera.eye;
+    fly.update_camera(&mut camera, 1.);
+    assert_near(camera.eye - start, Vec3::new(0., 0., 4.));
+    assert!(!fly.process_action(Action::CycleColorMode, ElementState::Pressed));
+
+    // And slows down to a stop once released
+    fly.process_action(Action::MoveUp, ElementState::Released);
+    fly.update_camera(&mut camera, 5.);
+    let start = camera.eye;
+    fly.update_camera(&mut camera, 1.);
+    assert_near(camera.eye, start);
+}
+
+#[test]
+fn walking_stays_above_the_ground() {
+    let mut camera = camera(Vec3::new(10., 10., 5.), Vec3::new(20., 10., 5.));
+    let mut walk = WalkController::new(1., 0.01, 1.7);
+    walk.set_ground(Ground::new(&slope()), 1.); // 2 m/s
+    walk.attach(&camera);
+    assert!((walk.position().z - (1. + 1.7)).abs() < 0.2, "standing at {}", walk.position());
+
+    // Looking down doesn't dig in, up and down do nothing
+    walk.process_mouse(0., 50.);
+    walk.process_action(Action::MoveForward, ElementState::Pressed);
+    walk.process_action(Action::MoveDown, ElementState::Pressed);
+    for _ in 0..300 {
+        walk.update_camera(&mut camera, 1. / 60.);
+    }
+    // 2 m/s for 5 s, less what it took to pick up speed
+    let position = walk.position();
+    assert!((position.x - (20. - 2. / 8.)).abs() < 1e-3 && (position.y - 10.).abs() < 1e-3, "walked to {position}");
+    assert!((position.z - (2. + 1.7)).abs() < 0.2, "standing at {position}");
+    assert_eq!(camera.eye, position);
+}
+
+#[test]
+fn switching_modes_keeps_the_view() {
+    let mut camera = camera(Vec3::new(10., 10., 5.), Vec3::new(20., 10., 5.));
+    let mut input = InputServer::new();
+    input.set_mode(CameraMode::Fly);
+    assert_eq!(input.mode(), CameraMode::Fly);
+    input.update(&mut camera, 0.);
+    assert_near(camera.eye, Vec3::new(10., 10., 5.));
+    assert_near(camera.target, Vec3::new(20., 10., 5.));
+
+    input.set_mode(CameraMode::Orbit);
+    input.update(&mut camera, 0.);
+    assert_near(camera.eye, Vec3::new(10., 10., 5.));
+    assert_near(camera.target, Vec3::new(20., 10., 5.));
+
+    // First person is perspective only
+    input.set_orthographic(true);
+    input.update(&mut camera, 0.);
+    assert!(camera.projection.is_orthographic());
+    input.set_mode(CameraMode::Walk);
+    input.update(&mut camera, 0.);
+    assert!(!camera.projection.is_orthographic());
+    assert_eq!(input.mode(), CameraMode::Walk);
+}
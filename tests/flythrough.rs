@@ -0,0 +1,112 @@
+//! Records camera keyframes, follows the spline through them at a steady
+//! speed and writes paths to JSON and back.
+
+mod common;
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::flythrough::{CameraPath, Keyframe, Playback, TURN_SPEED};
+use common::{assert_near, camera};
+
+/// Along X, looking ahead and then turning left, spaced unevenly
+fn path() -> CameraPath {
+    let mut path = CameraPath::default();
+    path.push(Keyframe::from_camera(&camera(Vec3::ZERO, Vec3::X)));
+    path.push(Keyframe::from_camera(&camera(Vec3::X, Vec3::new(2., 0., 0.))));
+    path.push(Keyframe::from_camera(&camera(Vec3::new(5., 0., 0.), Vec3::new(5., 2., 0.))));
+    path
+}
+
+#[test]
+fn keyframes_restore_the_camera() {
+    let recorded = camera(Vec3::new(1., 2., 3.), Vec3::new(4., 6., 3.));
+    let keyframe = Keyframe::from_camera(&recorded);
+    assert!((keyframe.focus - 5.).abs() < 1e-5);
+
+    let mut camera = camera(Vec3::ZERO, Vec3::X);
+    keyframe.apply(&mut camera);
+    assert_near(camera.eye, recorded.eye);
+    assert_near(camera.target, recorded.target);
+    assert_near(camera.up, Vec3::Z);
+}
+
+#[test]
+fn the_spline_goes_through_every_keyframe() {
+    let path = path();
+    assert!(CameraPath::default().sample(0.).is_none());
+    for (index, keyframe) in path.keyframes().iter().enumerate() {
+        let sample = path.sample(index as f32).unwrap();
+        assert_near(sample.eye, keyframe.eye);
+        assert!(sample.rotation.abs_diff_eq(keyframe.rotation, 1e-5));
+    }
+    // Clamped past the ends
+    assert_near(path.sample(-1.).unwrap().eye, Vec3::ZERO);
+    assert_near(path.sample(9.).unwrap().eye, Vec3::new(5., 0., 0.));
+
+    let polyline = path.polyline();
+    assert_near(polyline[0], Vec3::ZERO);
+    assert_near(*polyline.last().unwrap(), Vec3::new(5., 0., 0.));
+}
+
+#[test]
+fn rotation_turns_the_short_way_at_a_steady_rate() {
+    let path = path();
+    let (start, end) = (path.keyframes()[1].rotation, path.keyframes()[2].rotation);
+    let half = path.sample(1.5).unwrap();
+    assert!(half.rotation.abs_diff_eq(start.slerp(end, 0.5), 1e-5));
+    // Half way round the quarter turn to the left
+    assert_near(half.direction(), Vec3::new(1., 1., 0.).normalize());
+}
+
+#[test]
+fn playback_keeps_a_steady_speed() {
+    let mut playback = Playback::new(path(), 1.);
+    let duration = playback.duration();
+    // Straight along X, though the curve may overshoot a little, and turning
+    // slower than it moves
+    assert!((5. ..5.5).contains(&duration), "{duration}");
+
+    let mut eyes = Vec::new();
+    while let Some(keyframe) = playback.advance(0.1) {
+        eyes.push(keyframe.eye);
+    }
+    assert_near(eyes[0], Vec3::ZERO);
+    // Ends on the last keyframe, taking a shorter step to it
+    assert_near(*eyes.last().unwrap(), Vec3::new(5., 0., 0.));
+    let steps: Vec<f32> = eyes.windows(2).map(|pair| pair[0].distance(pair[1])).collect();
+    let (last, steps) = steps.split_last().unwrap();
+    for step in steps {
+        assert!((step - 0.1).abs() < 2e-3, "stepped {step}");
+    }
+    assert!(*last <= 0.1 + 1e-3);
+    assert!(playback.advance(0.1).is_none());
+}
+
+#[test]
+fn turning_on_the_spot_takes_time() {
+    let mut path = CameraPath::default();
+    path.push(Keyframe::from_camera(&camera(Vec3::ZERO, Vec3::X)));
+    path.push(Keyframe::from_camera(&camera(Vec3::ZERO, Vec3::Y)));
+    let mut playback = Playback::new(path, 1.);
+    // A quarter turn at TURN_SPEED
+    let duration = playback.duration();
+    assert!((duration - std::f32::consts::FRAC_PI_2 / TURN_SPEED).abs() < 1e-4, "{duration}");
+
+    let mut directions = Vec::new();
+    while let Some(keyframe) = playback.advance(0.1) {
+        assert_near(keyframe.eye, Vec3::ZERO);
+        directions.push(keyframe.direction());
+    }
+    // A frame every tenth of a second, and the last one
+    assert!((11..=12).contains(&directions.len()), "{} poses", directions.len());
+    assert_near(directions[0], Vec3::X);
+    assert_near(*directions.last().unwrap(), Vec3::Y);
+}
+
+#[test]
+fn paths_to_json_and_back() {
+    let path = path();
+    let json = path.to_json().unwrap();
+    assert!(json.contains("\"eye\"") && json.contains("\"rotation\"") && json.contains("\"focus\""), "{json}");
+    assert_eq!(CameraPath::from_json(&json).unwrap(), path);
+    assert!(CameraPath::from_json("{}").is_err());
+}
@@ -0,0 +1,128 @@
+//! Reads frame export options from the command line, steps along the path at
+//! the frame rate and writes the frames offscreen.
+
+use std::{f32::consts::FRAC_PI_2, path::PathBuf};
+
+use glam::{Quat, Vec3};
+use lib_webgpu_pointcloud::{
+    engine::{headless_device, Camera, Projection, RasterBackend, DEFAULT_FAR, DEFAULT_FOVY, DEFAULT_NEAR},
+    flythrough::{CameraPath, Keyframe},
+    frames::{frame_name, FrameExport, FrameOutput},
+    pointcloud::Pointcloud,
+    points::{make_random_intensities, make_random_points},
+};
+
+fn keyframe(eye: Vec3) -> Keyframe {
+    Keyframe::from_camera(&Camera {
+        eye,
+        target: Vec3::ZERO,
+        up: Vec3::Z,
+        projection: Projection::perspective(0.01, 100., 64., 64., 60f32.to_radians()),
+    })
+}
+
+/// Straight from 2 away along X to 2 away along Y, 4 long as the points are
+/// in a line
+fn path() -> CameraPath {
+    let mut path = CameraPath::default();
+    path.push(keyframe(Vec3::new(2., -2., 0.5)));
+    path.push(keyframe(Vec3::new(2., 0., 0.5)));
+    path.push(keyframe(Vec3::new(2., 2., 0.5)));
+    path
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    std::iter::once("viewer").chain(args.iter().copied()).map(str::to_owned).collect()
+}
+
+fn path_file(name: &str) -> PathBuf {
+    let file = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&file, path().to_json().unwrap()).unwrap();
+    file
+}
+
+#[test]
+fn options_from_the_command_line() {
+    assert_eq!(FrameExport::from_args(&args(&["--compute"])), Ok(None));
+
+    let file = path_file("options_path.json");
+    let file = file.to_str().unwrap();
+    let export = FrameExport::from_args(&args(&["--frames", file])).unwrap().unwrap();
+    assert_eq!((export.width, export.height, export.fps), (1920, 1080, 30.));
+    assert_eq!(export.output, FrameOutput::Png("frames".into()));
+    assert_eq!(export.backend, RasterBackend::Pipeline);
+    assert_eq!(export.path, path());
+    // The viewer's own projection unless told otherwise
+    assert_eq!((export.near, export.far, export.fovy), (DEFAULT_NEAR, DEFAULT_FAR, DEFAULT_FOVY));
+
+    let export = FrameExport::from_args(&args(&[
+        "--frames", file, "--size", "640x360", "--fps", "24", "--speed", "2", "--pipe", "ffmpeg -i - out.mp4", "--compute",
+        "--near", "0.5", "--far", "5000", "--fov", "90",
+    ])).unwrap().unwrap();
+    assert_eq!((export.width, export.height, export.fps, export.speed), (640, 360, 24., 2.));
+    assert_eq!((export.near, export.far), (0.5, 5000.));
+    assert!((export.fovy - FRAC_PI_2).abs() < 1e-6);
+    assert_eq!(export.output, FrameOutput::Pipe("ffmpeg -i - out.mp4".to_owned()));
+    assert_eq!(export.backend, RasterBackend::Compute);
+
+    assert!(FrameExport::from_args(&args(&["--frames", file, "--size", "640"])).is_err());
+    assert!(FrameExport::from_args(&args(&["--frames", file, "--fps", "0"])).is_err());
+    assert!(FrameExport::from_args(&args(&["--frames", file, "--near", "10", "--far", "1"])).is_err());
+    assert!(FrameExport::from_args(&args(&["--frames", file, "--fov", "180"])).is_err());
+    assert!(FrameExport::from_args(&args(&["--frames", "missing.json"])).is_err());
+}
+
+#[test]
+fn frames_step_at_the_frame_rate() {
+    let file = path_file("steps_path.json");
+    let mut export = FrameExport::from_args(&args(&["--frames", file.to_str().unwrap(), "--fps", "10", "--speed", "4"])).unwrap().unwrap();
+    // 4 long with half a unit to the meter, at 4 meters a second: 2 seconds
+    let poses = export.poses(0.5);
+    assert_eq!(poses.len(), 21);
+    assert!(poses[0].eye.abs_diff_eq(Vec3::new(2., -2., 0.5), 1e-4));
+    assert!(poses[20].eye.abs_diff_eq(Vec3::new(2., 2., 0.5), 1e-4));
+    assert!(poses[10].eye.abs_diff_eq(Vec3::new(2., 0., 0.5), 1e-3), "{}", poses[10].eye);
+
+    // Twice the frame rate, twice the frames over the same path
+    export.fps = 20.;
+    assert_eq!(export.poses(0.5).len(), 41);
+
+    // Turning on the spot takes time as well, a quarter turn a second
+    export.path = CameraPath::default();
+    export.path.push(keyframe(Vec3::new(2., 0., 0.5)));
+    export.path.push(Keyframe { rotation: Quat::from_rotation_z(FRAC_PI_2) * export.path.keyframes()[0].rotation, ..export.path.keyframes()[0] });
+    assert_eq!(export.poses(0.5).len(), 21);
+    assert_eq!(frame_name(0), "frame_00001.png");
+}
+
+#[test]
+fn frames_are_written_as_numbered_pngs() {
+    let Some((device, queue)) = pollster::block_on(headless_device()) else {
+        eprintln!("No adapter available, skipping frame export test");
+        return;
+    };
+    let pointcloud = Pointcloud::from_points(&device, &queue, make_random_points(500), make_random_intensities(500));
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("frames");
+    let _ = std::fs::remove_dir_all(&out);
+    let export = FrameExport {
+        path: path(),
+        width: 64,
+        height: 48,
+        fps: 1.,
+        speed: 2., // slow enough that turning towards the points doesn't hold it back
+        near: 0.01,
+        far: 100.,
+        fovy: 60f32.to_radians(),
+        output: FrameOutput::Png(out.clone()),
+        backend: RasterBackend::Pipeline,
+    };
+
+    let count = export.render(device, queue, pointcloud).unwrap();
+    assert_eq!(count, 3);
+    for index in 0..count {
+        let decoder = png::Decoder::new(std::fs::File::open(out.join(frame_name(index))).unwrap());
+        let info = decoder.read_info().unwrap();
+        assert_eq!((info.info().width, info.info().height), (64, 48));
+    }
+    assert!(!out.join(frame_name(count)).exists());
+}
@@ -0,0 +1,106 @@
+//! Shapes stick input with dead zones and curves, and drives the camera
+//! controllers with it.
+
+mod common;
+
+use glam::{Vec2, Vec3};
+use lib_webgpu_pointcloud::{
+    controls::{Action, Binding, Bindings, Modifiers, Trigger},
+    engine::{is_space_mouse, Axes, CameraController, FlyController, GamepadButton, GamepadSettings, GamepadState, Input, SpaceMouseState},
+};
+use common::orbit_camera;
+
+#[test]
+fn dead_zone_and_curve() {
+    let settings = GamepadSettings { dead_zone: 0.2, exponent: 2., look_speed: 1. };
+    assert_eq!(settings.curve(0.1), 0.);
+    assert_eq!(settings.curve(-0.2), 0.);
+    assert!((settings.curve(0.6) - 0.25).abs() < 1e-6);
+    assert!((settings.curve(-0.6) + 0.25).abs() < 1e-6);
+    assert_eq!(settings.curve(1.2), 1.);
+
+    // Radial, so a diagonal isn't faster and keeps its direction
+    let diagonal = settings.stick(Vec2::splat(0.6 * std::f32::consts::FRAC_1_SQRT_2));
+    assert!((diagonal.length() - 0.25).abs() < 1e-6 && (diagonal.x - diagonal.y).abs() < 1e-6);
+    assert_eq!(settings.stick(Vec2::new(0.1, -0.1)), Vec2::ZERO);
+
+    let state = GamepadState { left_stick: Vec2::Y, right_stick: Vec2::X, left_trigger: 0.6, right_trigger: 1., buttons: vec![] };
+    let axes = settings.axes(&state);
+    assert_eq!((axes.movement, axes.look), (Vec2::Y, Vec2::X));
+    assert!((axes.lift - 0.75).abs() < 1e-6);
+}
+
+#[test]
+fn space_mouse_axes() {
+    let settings = GamepadSettings { dead_zone: 0.1, exponent: 1., look_speed: 2. };
+    // Pushed away from the user and lifted, twisted clockwise and tilted back
+    let state = SpaceMouseState { translation: Vec3::new(0., -1., -1.), rotation: Vec3::new(1., 0.05, 1.) };
+    let axes = settings.space_mouse_axes(&state);
+    assert_eq!(axes, Axes { movement: Vec2::Y, look: Vec2::new(2., 2.), lift: 1. });
+    assert_eq!(settings.space_mouse_axes(&SpaceMouseState::default()), Axes::default());
+
+    // Added to whatever the gamepad does
+    let both = axes + Axes { movement: Vec2::X, look: Vec2::ZERO, lift: -1. };
+    assert_eq!(both, Axes { movement: Vec2::ONE, look: Vec2::new(2., 2.), lift: 0. });
+
+    assert!(is_space_mouse("3Dconnexion SpaceMouse Compact (Vendor: 256f Product: c635)"));
+    assert!(is_space_mouse("Logitech SpaceNavigator"));
+    assert!(!is_space_mouse("Xbox 360 Controller (XInput STANDARD GAMEPAD)"));
+}
+
+#[test]
+fn sticks_orbit_and_zoom() {
+    let turn_for = |seconds: f32, fps: u32| {
+        let mut camera = orbit_camera();
+        let mut orbit = CameraController::new(3., 2., 0.01, 0.1);
+        orbit.attach(&camera);
+        orbit.process_axes(Axes { movement: Vec2::ZERO, look: Vec2::new(1., 0.), lift: 1. });
+        for _ in 0..(seconds * fps as f32) as u32 {
+            orbit.update_camera(&mut camera, 1. / fps as f32);
+        }
+        orbit.process_axes(Axes::default());
+        orbit.update_camera(&mut camera, 10.);
+        camera
+    };
+    // A quarter turn in pi/2 seconds around the target, and twice as close per second
+    let camera = turn_for(std::f32::consts::FRAC_PI_2, 60);
+    assert!(camera.target.abs_diff_eq(Vec3::ZERO, 1e-4), "target at {}", camera.target);
+    let offset = camera.eye.normalize();
+    assert!(offset.abs_diff_eq(Vec3::Y, 0.02), "looking from {offset}");
+    assert!((camera.eye.length() - 2. / 2f32.powf(std::f32::consts::FRAC_PI_2)).abs() < 0.02, "eye at {}", camera.eye);
+
+    let other = turn_for(std::f32::consts::FRAC_PI_2, 144);
+    assert!(other.eye.abs_diff_eq(camera.eye, 0.02), "eye at {} and {}", other.eye, camera.eye);
+}
+
+#[test]
+fn sticks_fly() {
+    let mut camera = orbit_camera();
+    let mut fly = FlyController::new(1., 0.01);
+    fly.attach(&camera);
+    // Looking at the target, half speed forward and full speed up
+    fly.process_axes(Axes { movement: Vec2::new(0., 0.5), look: Vec2::ZERO, lift: 1. });
+    fly.update_camera(&mut camera, 5.);
+    let start = camera.eye;
+    fly.update_camera(&mut camera, 1.);
+    let moved = camera.eye - start;
+    assert!((moved.length() - 1.).abs() < 1e-3 && moved.x < 0. && moved.z > 0.8, "moved {moved}");
+
+    // Turning right from along -x looks along +y
+    fly.process_axes(Axes { movement: Vec2::ZERO, look: Vec2::new(std::f32::consts::FRAC_PI_2, 0.), lift: 0. });
+    fly.update_camera(&mut camera, 1.);
+    assert!(fly.direction().abs_diff_eq(Vec3::Y, 1e-4), "looking along {}", fly.direction());
+}
+
+#[test]
+fn gamepad_buttons_are_bindable() {
+    let binding: Binding = "GamepadDPadUp".parse().unwrap();
+    assert_eq!(binding, Binding::gamepad(GamepadButton::DPadUp));
+    assert_eq!(binding.to_string(), "GamepadDPadUp");
+    assert!("GamepadTurbo".parse::<Binding>().is_err());
+
+    let bindings = Bindings::default();
+    let pad = |button| bindings.action(Trigger::Gamepad(button), Modifiers::default());
+    assert_eq!(pad(GamepadButton::South), Some(Action::FitToExtent));
+    assert_eq!(pad(GamepadButton::RightShoulder), Some(Action::Faster));
+}
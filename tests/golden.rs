@@ -0,0 +1,410 @@
+//! Renders small synthetic clouds offscreen and compares them against the
+//! reference images in `tests/golden`. Run with `UPDATE_GOLDEN=1` to
+//! regenerate the references after an intended visual change.
+
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use glam::{DVec3, Quat, Vec3};
+use lib_webgpu_pointcloud::{
+    engine::{
+        headless_device, Camera, ClipBox, ClipMode, ClipPlane, ColorMode, FilterSettings, LineVertex, Offscreen, PointSizeMode, Projection, RasterBackend, Renderer, ReturnFilter,
+        SplatMode, SsaoSettings,
+    },
+    pointcloud::{Pointcloud, SourcePoint, SourceTransform},
+    points::{make_random_intensities, make_random_points},
+    profile::Profile,
+};
+
+const SIZE: u32 = 128;
+const POINTS: usize = 2000;
+// Per channel difference allowed before a pixel counts as different
+const CHANNEL_TOLERANCE: u8 = 16;
+// Fraction of pixels that may differ, to absorb rasterisation differences between drivers
+const PIXEL_TOLERANCE: f64 = 0.01;
+
+fn perspective_camera(eye: Vec3, up: Vec3) -> Camera {
+    Camera {
+        eye,
+        target: Vec3::ZERO,
+        up,
+        projection: Projection::perspective(0.01, 1000., SIZE as f32, SIZE as f32, 60f32.to_radians()),
+    }
+}
+
+fn orthographic_camera(eye: Vec3, up: Vec3) -> Camera {
+    Camera {
+        eye,
+        target: Vec3::ZERO,
+        up,
+        projection: Projection::Orthographic { near: 0.01, far: 1000., width: 1.5, height: 1.5 },
+    }
+}
+
+fn render(camera: &Camera, backend: RasterBackend, configure: impl FnOnce(&mut Renderer)) -> Option<Vec<u8>> {
+    let Some((device, queue)) = pollster::block_on(headless_device()) else {
+        eprintln!("No adapter available, skipping golden image test");
+        return None;
+    };
+
+    let pointcloud = Pointcloud::from_points(
+        &device,
+        &queue,
+        make_random_points(POINTS),
+        make_random_intensities(POINTS),
+    );
+    let target = Offscreen::new(&device, SIZE, SIZE);
+    let mut renderer = Renderer::with_backend(device, queue, Offscreen::FORMAT, camera, SIZE, SIZE, backend);
+    renderer.set_pointcloud(pointcloud);
+    configure(&mut renderer);
+    renderer.update(camera);
+    renderer.render(target.view());
+
+    Some(target.read_pixels(renderer.device(), renderer.queue()))
+}
+
+fn write_png(path: &PathBuf, pixels: &[u8]) {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path).unwrap()), SIZE, SIZE);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+}
+
+fn read_png(path: &PathBuf) -> Vec<u8> {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (SIZE, SIZE), "{} has the wrong size", path.display());
+    pixels.truncate(info.buffer_size());
+
+    pixels
+}
+
+fn check_golden(name: &str, camera: Camera, configure: impl FnOnce(&mut Renderer)) {
+    check_golden_with_backend(name, RasterBackend::Pipeline, camera, configure);
+}
+
+fn check_golden_with_backend(name: &str, backend: RasterBackend, camera: Camera, configure: impl FnOnce(&mut Renderer)) {
+    let Some(pixels) = render(&camera, backend, configure) else {
+        return;
+    };
+
+    let reference = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_png(&reference, &pixels);
+        return;
+    }
+    assert!(
+        reference.exists(),
+        "Missing reference {}, run with UPDATE_GOLDEN=1 to create it",
+        reference.display()
+    );
+
+    let expected = read_png(&reference);
+    let different = pixels
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let fraction = different as f64 / (SIZE * SIZE) as f64;
+
+    if fraction > PIXEL_TOLERANCE {
+        let actual = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.png"));
+        write_png(&actual, &pixels);
+        panic!(
+            "{name}: {:.2}% of pixels differ from {}, output written to {}",
+            fraction * 100.,
+            reference.display(),
+            actual.display(),
+        );
+    }
+}
+
+#[test]
+fn intensity_perspective() {
+    check_golden(
+        "intensity_perspective",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| renderer.set_color_mode(ColorMode::Intensity),
+    );
+}
+
+#[test]
+fn elevation_perspective() {
+    check_golden(
+        "elevation_perspective",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| renderer.set_color_mode(ColorMode::Elevation),
+    );
+}
+
+#[test]
+fn elevation_top_orthographic() {
+    check_golden(
+        "elevation_top_orthographic",
+        orthographic_camera(Vec3::new(0., 0., 2.), Vec3::Y),
+        |renderer| renderer.set_color_mode(ColorMode::Elevation),
+    );
+}
+
+#[test]
+fn intensity_side_orthographic() {
+    check_golden(
+        "intensity_side_orthographic",
+        orthographic_camera(Vec3::new(0., -2., 0.), Vec3::Z),
+        |renderer| renderer.set_color_mode(ColorMode::Intensity),
+    );
+}
+
+#[test]
+fn eye_dome_lighting() {
+    check_golden(
+        "eye_dome_lighting",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Intensity);
+            renderer.edl_mut().enabled = true;
+        },
+    );
+}
+
+#[test]
+fn ambient_occlusion() {
+    check_golden(
+        "ambient_occlusion",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            *renderer.ssao_mut() = SsaoSettings { enabled: true, radius: 0.2, strength: 4., ..Default::default() };
+        },
+    );
+}
+
+#[test]
+fn attenuated_point_size() {
+    check_golden(
+        "attenuated_point_size",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            let point_size = renderer.point_size_mut();
+            point_size.mode = PointSizeMode::Attenuated;
+            point_size.world_size = 0.03;
+        },
+    );
+}
+
+#[test]
+fn adaptive_point_size() {
+    check_golden(
+        "adaptive_point_size",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            renderer.point_size_mut().mode = PointSizeMode::Adaptive;
+        },
+    );
+}
+
+#[test]
+fn high_quality_splats() {
+    check_golden(
+        "high_quality_splats",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            renderer.splats_mut().mode = SplatMode::HighQuality;
+            renderer.splats_mut().depth_epsilon = 0.1;
+        },
+    );
+}
+
+#[test]
+fn compute_raster() {
+    check_golden_with_backend(
+        "compute_raster",
+        RasterBackend::Compute,
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| renderer.set_color_mode(ColorMode::Elevation),
+    );
+}
+
+#[test]
+fn compute_raster_eye_dome_lighting() {
+    check_golden_with_backend(
+        "compute_raster_eye_dome_lighting",
+        RasterBackend::Compute,
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Intensity);
+            renderer.edl_mut().enabled = true;
+        },
+    );
+}
+
+#[test]
+fn compute_raster_large_points() {
+    check_golden_with_backend(
+        "compute_raster_large_points",
+        RasterBackend::Compute,
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            renderer.point_size_mut().pixels = 40.;
+        },
+    );
+}
+
+#[test]
+fn measurement_lines() {
+    check_golden(
+        "measurement_lines",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Intensity);
+            let corners = [[-0.5, -0.5, 0.5], [0.5, -0.5, 0.5], [0.5, 0.5, 0.5], [-0.5, 0.5, 0.5]];
+            let vertices: Vec<_> = (0..corners.len())
+                .flat_map(|i| [corners[i], corners[(i + 1) % corners.len()]])
+                .map(|position| LineVertex { position, color: [1., 0.85, 0., 1.] })
+                .collect();
+            renderer.set_lines(&vertices);
+        },
+    );
+}
+
+fn clip_box() -> ClipBox {
+    ClipBox {
+        rotation: Quat::from_rotation_z(0.5),
+        ..ClipBox::new(Vec3::new(0., 0., 0.1), Vec3::new(0.5, 0.2, 0.3))
+    }
+}
+
+#[test]
+fn clip_box_inside() {
+    check_golden(
+        "clip_box_inside",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            let clipping = renderer.clipping_mut();
+            clipping.mode = ClipMode::Inside;
+            clipping.boxes.push(clip_box());
+        },
+    );
+}
+
+#[test]
+fn clip_plane_outside() {
+    check_golden(
+        "clip_plane_outside",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            let clipping = renderer.clipping_mut();
+            clipping.mode = ClipMode::Outside;
+            // Cuts away everything below the tilted plane
+            clipping.planes.push(ClipPlane { point: Vec3::ZERO, normal: Vec3::new(0.3, 0., 1.) });
+        },
+    );
+}
+
+#[test]
+fn clip_highlight() {
+    check_golden(
+        "clip_highlight",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Intensity);
+            let clipping = renderer.clipping_mut();
+            clipping.mode = ClipMode::Highlight;
+            clipping.boxes.push(clip_box());
+        },
+    );
+}
+
+#[test]
+fn compute_raster_clip_box() {
+    check_golden_with_backend(
+        "compute_raster_clip_box",
+        RasterBackend::Compute,
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            let clipping = renderer.clipping_mut();
+            clipping.mode = ClipMode::Inside;
+            clipping.boxes.push(clip_box());
+        },
+    );
+}
+
+#[test]
+fn profile_inset() {
+    check_golden(
+        "profile_inset",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_color_mode(ColorMode::Elevation);
+            renderer.point_size_mut().pixels = 3.;
+            let profile = Profile {
+                path: vec![DVec3::new(-0.5, -0.5, 0.), DVec3::new(0.5, 0.5, 0.)],
+                width: 0.2,
+            };
+            let points = profile.extract(renderer.pointcloud().unwrap());
+            renderer.set_profile(&points);
+        },
+    );
+}
+
+/// The random cloud with attributes: ground below z = 0, high vegetation
+/// above, three returns per pulse and increasing gps times
+fn classified_pointcloud(renderer: &Renderer) -> Pointcloud {
+    let source = make_random_points(POINTS)
+        .into_iter()
+        .zip(make_random_intensities(POINTS))
+        .enumerate()
+        .map(|(i, (point, intensity))| SourcePoint {
+            position: point.as_dvec3(),
+            intensity: (intensity * 1000.) as u16,
+            classification: if point.z < 0. { 2 } else { 5 },
+            return_number: (i % 3) as u8 + 1,
+            number_of_returns: 3,
+            gps_time: Some(400_000_000. + i as f64),
+            ..Default::default()
+        })
+        .collect();
+
+    Pointcloud::from_source(renderer.device(), renderer.queue(), source, SourceTransform::default())
+}
+
+#[test]
+fn filter_classes_and_returns() {
+    check_golden(
+        "filter_classes_and_returns",
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_pointcloud(classified_pointcloud(renderer));
+            *renderer.filter_mut() = FilterSettings {
+                returns: ReturnFilter::Last,
+                ..FilterSettings::hide_vegetation()
+            };
+        },
+    );
+}
+
+#[test]
+fn compute_raster_filter_ranges() {
+    check_golden_with_backend(
+        "compute_raster_filter_ranges",
+        RasterBackend::Compute,
+        perspective_camera(Vec3::new(1.2, -1.2, 1.), Vec3::Z),
+        |renderer| {
+            renderer.set_pointcloud(classified_pointcloud(renderer));
+            renderer.set_color_mode(ColorMode::Elevation);
+            *renderer.filter_mut() = FilterSettings {
+                elevation: Some((-0.3, 0.3)),
+                gps_time: Some((400_000_000., 400_000_000. + POINTS as f64 / 2.)),
+                ..Default::default()
+            };
+        },
+    );
+}
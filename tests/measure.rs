@@ -0,0 +1,100 @@
+use glam::DVec3;
+use lib_webgpu_pointcloud::measure::{MeasureKind, Measurement, Measurements};
+
+fn measurement(kind: MeasureKind, points: &[[f64; 3]]) -> Measurement {
+    Measurement {
+        kind,
+        points: points.iter().map(|p| DVec3::from_array(*p)).collect(),
+    }
+}
+
+fn assert_close(value: Option<f64>, expected: f64) {
+    let value = value.expect("measurement has a value");
+    assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+}
+
+#[test]
+fn values() {
+    let base = [500_000., 4_000_000., 100.];
+    let offset = |x: f64, y: f64, z: f64| [base[0] + x, base[1] + y, base[2] + z];
+
+    assert_close(measurement(MeasureKind::Distance, &[base, offset(3., 4., 0.)]).value(), 5.);
+    assert_close(
+        measurement(MeasureKind::Polyline, &[base, offset(3., 4., 0.), offset(3., 4., 2.)]).value(),
+        7.,
+    );
+    assert_close(
+        measurement(MeasureKind::Area, &[base, offset(2., 0., 0.), offset(2., 3., 0.), offset(0., 3., 0.)]).value(),
+        6.,
+    );
+    // Vertical wall, the area isn't projected on the ground
+    assert_close(
+        measurement(MeasureKind::Area, &[base, offset(2., 0., 0.), offset(2., 0., 3.), offset(0., 0., 3.)]).value(),
+        6.,
+    );
+    assert_close(measurement(MeasureKind::Height, &[offset(5., 5., 2.5), base]).value(), -2.5);
+    assert_close(
+        measurement(MeasureKind::Angle, &[offset(1., 0., 0.), base, offset(0., 1., 0.)]).value(),
+        90.,
+    );
+    assert_eq!(measurement(MeasureKind::Area, &[base, offset(1., 0., 0.)]).value(), None);
+}
+
+#[test]
+fn tools_finish_themselves() {
+    let mut measurements = Measurements::default();
+    assert!(!measurements.add_point(DVec3::ZERO), "no tool is active");
+
+    measurements.set_tool(Some(MeasureKind::Distance));
+    measurements.add_point(DVec3::ZERO);
+    assert!(measurements.current().is_some());
+    measurements.add_point(DVec3::X);
+    assert!(measurements.current().is_none());
+    assert_eq!(measurements.finished().len(), 1);
+
+    // Polylines wait for finish, too short ones are dropped
+    measurements.set_tool(Some(MeasureKind::Polyline));
+    measurements.add_point(DVec3::ZERO);
+    measurements.finish();
+    assert_eq!(measurements.finished().len(), 1);
+    for x in 0..4 {
+        measurements.add_point(DVec3::new(x as f64, 0., 0.));
+    }
+    measurements.finish();
+    assert_eq!(measurements.finished().len(), 2);
+    assert_close(measurements.finished()[1].value(), 3.);
+}
+
+#[test]
+fn cycles_tools() {
+    let mut tool = None;
+    let mut seen = Vec::new();
+    loop {
+        tool = MeasureKind::next(tool);
+        match tool {
+            Some(kind) => seen.push(kind),
+            None => break,
+        }
+    }
+    assert_eq!(seen, MeasureKind::ALL);
+}
+
+#[test]
+fn exports_source_coordinates() {
+    let mut measurements = Measurements::default();
+    measurements.set_tool(Some(MeasureKind::Distance));
+    measurements.add_point(DVec3::new(500_000.125, 4_000_000.25, 100.5));
+    measurements.add_point(DVec3::new(500_003.125, 4_000_004.25, 100.5));
+
+    let json: serde_json::Value = serde_json::from_str(&measurements.to_json().unwrap()).unwrap();
+    assert_eq!(json[0]["kind"], "distance");
+    assert_eq!(json[0]["points"][0][0], 500_000.125);
+    assert_eq!(json[0]["value"], 5.);
+    assert_eq!(json[0]["unit"], "m");
+
+    let csv = measurements.to_csv();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "measurement,kind,point,x,y,z,value,unit");
+    assert_eq!(lines[1], "0,distance,0,500000.125,4000000.25,100.5,5,m");
+    assert_eq!(lines.len(), 3);
+}
@@ -0,0 +1,83 @@
+//! Replays the same input at different frame rates, the camera has to end up
+//! in the same place.
+
+mod common;
+
+use glam::Vec2;
+use lib_webgpu_pointcloud::engine::{ease, Camera, CameraController, FlyController, Inertia, Input};
+use lib_webgpu_pointcloud::controls::Action;
+use winit::event::{ElementState, MouseScrollDelta};
+use common::orbit_camera;
+
+const FRAME_RATES: [u32; 4] = [30, 60, 144, 240];
+
+/// Camera after 1.5 s of `controller` at `fps`: a drag and a scroll, then
+/// `action` held from 0.5 s to 1 s
+fn replay(mut controller: impl Input, action: Action, fps: u32) -> Camera {
+    let mut camera = orbit_camera();
+    controller.attach(&camera);
+    controller.process_mouse(30., -20.);
+    controller.process_scroll(&MouseScrollDelta::LineDelta(0., 2.));
+    for frame in 0..fps * 3 / 2 {
+        if frame == fps / 2 {
+            controller.process_action(action, ElementState::Pressed);
+        }
+        if frame == fps {
+            controller.process_action(action, ElementState::Released);
+        }
+        controller.update_camera(&mut camera, 1. / fps as f32);
+    }
+
+    camera
+}
+
+fn assert_same(cameras: &[Camera]) {
+    for camera in &cameras[1..] {
+        assert!(camera.eye.abs_diff_eq(cameras[0].eye, 1e-4), "eye at {} and {}", camera.eye, cameras[0].eye);
+        assert!(camera.target.abs_diff_eq(cameras[0].target, 1e-4), "target at {} and {}", camera.target, cameras[0].target);
+    }
+}
+
+#[test]
+fn inertia_is_exact_in_steps() {
+    let mut once = Inertia::new(Vec2::new(1., 0.), 5.);
+    let mut steps = once;
+    let displacement = once.step(Vec2::Y, 0.8);
+    let stepped = (0..8).map(|_| steps.step(Vec2::Y, 0.1)).fold(Vec2::ZERO, |sum, step| sum + step);
+    assert!(displacement.abs_diff_eq(stepped, 1e-6), "{displacement} and {stepped}");
+    assert!(once.velocity.abs_diff_eq(steps.velocity, 1e-6));
+
+    // Eases out: from rest it lags what the goal speed would cover, then stops without a goal
+    let mut inertia = Inertia::new(Vec2::ZERO, 5.);
+    assert!(inertia.step(Vec2::Y, 10.).abs_diff_eq(Vec2::new(0., 10. - 1. / 5.), 1e-5));
+    assert!(inertia.velocity.abs_diff_eq(Vec2::Y, 1e-6));
+    assert!(inertia.step(Vec2::ZERO, 10.).abs_diff_eq(Vec2::new(0., 1. / 5.), 1e-5));
+    assert!(inertia.velocity.abs_diff_eq(Vec2::ZERO, 1e-6));
+
+    let (half, whole) = (ease(8., 0.5), ease(8., 1.));
+    assert!((1. - (1. - half) * (1. - half) - whole).abs() < 1e-6);
+}
+
+#[test]
+fn orbit_is_frame_rate_independent() {
+    let cameras: Vec<_> = FRAME_RATES.iter()
+        .map(|fps| replay(CameraController::new(3., 2., 0.01, 0.1), Action::MoveForward, *fps))
+        .collect();
+    assert_same(&cameras);
+
+    // Everything happened: panned, turned and zoomed in
+    let camera = &cameras[0];
+    assert!(camera.target.length() > 0.5, "target at {}", camera.target);
+    assert!((camera.eye.distance(camera.target) - 2. / 1.2).abs() < 1e-4, "eye at {}", camera.eye);
+    let offset = camera.eye - camera.target;
+    assert!(offset.y > 0. && offset.z < 0., "looking from {offset}"); // dragged right and up
+}
+
+#[test]
+fn flight_is_frame_rate_independent() {
+    let cameras: Vec<_> = FRAME_RATES.iter()
+        .map(|fps| replay(FlyController::new(3., 0.01), Action::MoveForward, *fps))
+        .collect();
+    assert_same(&cameras);
+    assert!(cameras[0].eye.distance(orbit_camera().eye) > 1., "eye at {}", cameras[0].eye);
+}
@@ -0,0 +1,49 @@
+//! Writes the view into a URL fragment and reads it back.
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::{
+    bookmarks::CameraView,
+    engine::{ColorMode, FilterSettings, ReturnFilter},
+    permalink::Permalink,
+};
+
+fn permalink() -> Permalink {
+    Permalink {
+        dataset: Some("https://example.com/sites/north pier.las?v=2&x=1".to_owned()),
+        view: Some(CameraView { target: Vec3::new(1.5, -2., 0.25), theta: 1.2, phi: -0.5, farness: 40., orthographic: true }),
+        color_mode: Some(ColorMode::Elevation),
+        filter: Some(FilterSettings { returns: ReturnFilter::Last, elevation: Some((100., 250.)), ..Default::default() }),
+    }
+}
+
+#[test]
+fn links_round_trip() {
+    let hash = permalink().to_hash();
+    assert!(hash.starts_with("url=https%3A%2F%2Fexample.com%2Fsites%2Fnorth%20pier.las%3Fv%3D2%26x%3D1&"), "{hash}");
+    assert!(hash.contains("&view=1.5,-2,0.25,1.2,-0.5,40&projection=orthographic&color=elevation&filter="), "{hash}");
+    // Only what differs from letting everything through
+    assert!(!hash.contains("classifications"), "{hash}");
+    assert_eq!(Permalink::from_hash(&hash).unwrap(), permalink());
+    assert_eq!(Permalink::from_hash(&format!("#{hash}")).unwrap(), permalink());
+
+    let perspective = Permalink::from_hash("view=0,0,0,1,2,3").unwrap().view.unwrap();
+    assert!(!perspective.orthographic && perspective.farness == 3.);
+}
+
+#[test]
+fn missing_parts_keep_what_the_viewer_has() {
+    assert_eq!(Permalink::default().to_hash(), "");
+    assert_eq!(Permalink::from_hash("").unwrap(), Permalink::default());
+    let link = Permalink::from_hash("#color=Intensity&later=1").unwrap();
+    assert_eq!(link, Permalink { color_mode: Some(ColorMode::Intensity), ..Default::default() });
+}
+
+#[test]
+fn broken_links_are_refused() {
+    assert!(Permalink::from_hash("view=1,2,3").is_err());
+    assert!(Permalink::from_hash("view=1,2,3,4,5,NaN").is_err());
+    assert!(Permalink::from_hash("color=rainbow").is_err());
+    assert!(Permalink::from_hash("url=%E2%28").is_err());
+    assert!(Permalink::from_hash("url=%4").is_err());
+    assert!(Permalink::from_hash("filter=%7B").is_err());
+}
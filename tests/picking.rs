@@ -0,0 +1,112 @@
+//! Picks points of a small cloud with large source coordinates and checks that
+//! the exact source point comes back.
+
+use std::task::Poll;
+
+use glam::{DVec3, Vec3};
+use lib_webgpu_pointcloud::{
+    engine::{headless_device, Camera, PickedPoint, Projection, Renderer},
+    pointcloud::{Pointcloud, SourcePoint, SourceTransform},
+};
+
+const SIZE: u32 = 64;
+
+fn camera() -> Camera {
+    Camera {
+        eye: Vec3::new(0., 0., 2.),
+        target: Vec3::ZERO,
+        up: Vec3::Y,
+        projection: Projection::Orthographic { near: 0.01, far: 1000., width: 2., height: 2. },
+    }
+}
+
+fn source_points() -> (Vec<SourcePoint>, SourceTransform) {
+    let transform = SourceTransform {
+        offset: DVec3::new(500_000., 4_000_000., 100.),
+        scale: DVec3::ONE,
+    };
+    let source = [(-0.5, -0.5, 0.), (0.5, 0.5, 0.25), (0.5, -0.5, -0.25)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (x, y, z))| SourcePoint {
+            position: transform.offset + DVec3::new(x, y, z) + 0.001,
+            intensity: 100 * (i as u16 + 1),
+            classification: 2 + i as u8,
+            gps_time: Some(1000. + i as f64),
+            ..Default::default()
+        })
+        .collect();
+
+    (source, transform)
+}
+
+fn to_pixel(camera: &Camera, point: Vec3) -> (u32, u32) {
+    let ndc = camera.build_view_projection_matrix().project_point3(point);
+    (
+        ((ndc.x + 1.) * 0.5 * SIZE as f32) as u32,
+        ((1. - ndc.y) * 0.5 * SIZE as f32) as u32,
+    )
+}
+
+fn pick(renderer: &mut Renderer, x: u32, y: u32) -> Option<PickedPoint> {
+    assert!(renderer.request_pick(x, y));
+    loop {
+        if let Poll::Ready(picked) = renderer.poll_pick() {
+            return picked;
+        }
+        renderer.device().poll(wgpu::PollType::Wait).unwrap();
+    }
+}
+
+fn make_renderer() -> Option<(Renderer, Camera)> {
+    let Some((device, queue)) = pollster::block_on(headless_device()) else {
+        eprintln!("No adapter available, skipping picking test");
+        return None;
+    };
+
+    let (source, transform) = source_points();
+    let pointcloud = Pointcloud::from_source(&device, &queue, source, transform);
+    let camera = camera();
+    let mut renderer = Renderer::new(device, queue, wgpu::TextureFormat::Rgba8UnormSrgb, &camera, SIZE, SIZE);
+    renderer.set_pointcloud(pointcloud);
+    renderer.update(&camera);
+
+    Some((renderer, camera))
+}
+
+#[test]
+fn picks_exact_source_point() {
+    let Some((mut renderer, camera)) = make_renderer() else {
+        return;
+    };
+    let (source, _) = source_points();
+
+    for (index, expected) in source.iter().enumerate() {
+        let local = renderer.pointcloud().unwrap().points()[index];
+        let (x, y) = to_pixel(&camera, local);
+        // Slightly off the center, within the pick radius
+        let picked = pick(&mut renderer, x + 2, y).expect("point under the cursor");
+
+        assert_eq!(picked.index, index);
+        assert_eq!(picked.position, expected.position);
+        assert_eq!(picked.source, Some(*expected));
+    }
+}
+
+#[test]
+fn misses_background() {
+    let Some((mut renderer, _)) = make_renderer() else {
+        return;
+    };
+
+    assert_eq!(pick(&mut renderer, SIZE / 2, SIZE / 2), None);
+}
+
+#[test]
+fn nothing_to_poll_without_request() {
+    let Some((mut renderer, _)) = make_renderer() else {
+        return;
+    };
+
+    assert_eq!(renderer.poll_pick(), Poll::Pending);
+}
@@ -0,0 +1,118 @@
+//! Extracts profiles from small clouds with known positions and reads the
+//! exports back.
+
+use std::io::Cursor;
+
+use glam::{DVec3, Vec3};
+use lib_webgpu_pointcloud::{
+    engine::headless_device,
+    pointcloud::{Pointcloud, SourcePoint, SourceTransform},
+    profile::{self, Profile},
+};
+
+// An L: 10 m east then 10 m north
+fn corner_profile() -> Profile {
+    Profile {
+        path: vec![DVec3::new(0., 0., 5.), DVec3::new(10., 0., 5.), DVec3::new(10., 10., 7.)],
+        width: 2.,
+    }
+}
+
+#[test]
+fn stations_are_unrolled_along_the_path() {
+    let profile = corner_profile();
+    assert_eq!(profile.length(), 20.);
+
+    let station = |x, y| profile.station(DVec3::new(x, y, 100.));
+    assert_eq!(station(4., 0.5), Some((4., 0.5)), "left of the first leg");
+    assert_eq!(station(4., -0.5), Some((4., -0.5)), "right of the first leg");
+    assert_eq!(station(9.5, 5.), Some((15., 0.5)), "left of the second leg");
+    assert_eq!(station(4., 1.5), None, "outside the corridor");
+    assert_eq!(station(-0.5, 0.), None, "before the start");
+    assert_eq!(station(10., 10.5), None, "after the end");
+
+    // Rounded around the bend
+    let (distance, offset) = station(10.5, -0.5).unwrap();
+    assert_eq!(distance, 10.);
+    assert!((offset + 0.5f64.hypot(0.5)).abs() < 1e-12);
+
+    assert_eq!(Profile { path: vec![DVec3::ZERO], width: 2. }.station(DVec3::ZERO), None);
+}
+
+#[test]
+fn outline_has_both_sides_and_ends() {
+    // Path and both sides of each leg, and the two ends
+    assert_eq!(corner_profile().outline().len(), 2 * 3 + 2);
+    assert!(Profile::new(1.).outline().is_empty());
+}
+
+fn source_points() -> Vec<SourcePoint> {
+    let offset = DVec3::new(600_000., 5_000_000., 0.);
+    [(12., 0.2), (3., -0.9), (10.2, 6.), (5., 3.), (3., 0.3)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (x, y))| SourcePoint {
+            position: offset + DVec3::new(x, y, 5. + i as f64 * 0.25),
+            intensity: 10 * i as u16,
+            classification: 2,
+            gps_time: Some(i as f64),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[test]
+fn extracts_sorted_points_and_exports_them() {
+    let Some((device, queue)) = pollster::block_on(headless_device()) else {
+        eprintln!("No adapter available, skipping profile test");
+        return;
+    };
+    let source = source_points();
+    let transform = SourceTransform { offset: source[0].position, scale: DVec3::ONE };
+    let pointcloud = Pointcloud::from_source(&device, &queue, source.clone(), transform);
+
+    let mut profile = corner_profile();
+    for point in &mut profile.path {
+        *point += DVec3::new(600_000., 5_000_000., 0.);
+    }
+    let points = profile.extract(&pointcloud);
+
+    // (12, 0.2) is past the corner on the outside, (5, 3) is off the path
+    let indices: Vec<_> = points.iter().map(|point| point.index).collect();
+    assert_eq!(indices, [1, 4, 2]);
+    assert_eq!(points[2].distance, 16.);
+    assert_eq!(points[2].position, source[2].position);
+    assert_eq!(points[1].source, Some(source[4]));
+
+    let csv = profile::to_csv(&points);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("distance,offset,elevation,x,y,z,intensity,classification"));
+    assert_eq!(lines.count(), 3);
+
+    let las = profile::to_las(&points).unwrap();
+    let mut reader = las::Reader::new(Cursor::new(las)).unwrap();
+    let read: Vec<_> = reader.points().map(|point| SourcePoint::from(&point.unwrap())).collect();
+    assert_eq!(read.len(), 3);
+    for (read, point) in read.iter().zip(&points) {
+        assert!(read.position.distance(point.position) < 1e-3);
+        assert_eq!(read.intensity, point.source.unwrap().intensity);
+        assert_eq!(read.gps_time, point.source.unwrap().gps_time);
+    }
+}
+
+#[test]
+fn local_clouds_extract_in_scene_coordinates() {
+    let Some((device, queue)) = pollster::block_on(headless_device()) else {
+        return;
+    };
+    let points = vec![Vec3::new(0., 0., 0.), Vec3::new(0., 0.4, 1.), Vec3::new(0., 2., 0.)];
+    let pointcloud = Pointcloud::from_points(&device, &queue, points, vec![1., 2., 3.]);
+    let profile = Profile {
+        path: vec![DVec3::new(-1., 0., 0.), DVec3::new(1., 0., 0.)],
+        width: 1.,
+    };
+
+    let extracted = profile.extract(&pointcloud);
+    assert_eq!(extracted.len(), 2);
+    assert!(extracted.iter().all(|point| point.source.is_none() && point.distance == 1.));
+}
@@ -0,0 +1,87 @@
+//! Feeds fingers to the input server: one orbits, two pan and pinch, taps
+//! come back as gestures for the engine.
+
+mod common;
+
+use glam::Vec3;
+use lib_webgpu_pointcloud::engine::{Gesture, InputServer};
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceId, Touch, TouchPhase, WindowEvent},
+};
+use common::orbit_camera;
+
+// Seconds for the camera to catch up with the input
+const SETTLE: f32 = 10.;
+
+fn touch(input: &mut InputServer, id: u64, phase: TouchPhase, x: f64, y: f64) {
+    let touch = Touch {
+        device_id: DeviceId::dummy(),
+        phase,
+        location: PhysicalPosition::new(x, y),
+        force: None,
+        id,
+    };
+    assert!(input.window_input(&WindowEvent::Touch(touch)));
+}
+
+#[test]
+fn one_finger_orbits() {
+    let mut camera = orbit_camera();
+    let mut input = InputServer::new();
+    touch(&mut input, 1, TouchPhase::Started, 100., 100.);
+    touch(&mut input, 1, TouchPhase::Moved, 150., 100.);
+    touch(&mut input, 1, TouchPhase::Ended, 150., 100.);
+    assert_eq!(input.take_gesture(), None); // moved too far for a tap
+    input.update(&mut camera, SETTLE);
+
+    assert!(camera.target.abs_diff_eq(Vec3::ZERO, 1e-5), "target at {}", camera.target);
+    assert!((camera.eye.length() - 2.).abs() < 1e-4 && camera.eye.y.abs() > 0.1, "eye at {}", camera.eye);
+}
+
+#[test]
+fn two_fingers_pan_and_pinch() {
+    let mut camera = orbit_camera();
+    let mut input = InputServer::new();
+    input.resize(600);
+    touch(&mut input, 1, TouchPhase::Started, 300., 300.);
+    touch(&mut input, 2, TouchPhase::Started, 500., 300.);
+    assert_eq!(input.take_gesture(), Some(Gesture::Pinch(PhysicalPosition::new(400., 300.))));
+
+    // Spread to twice as far apart
+    touch(&mut input, 1, TouchPhase::Moved, 200., 300.);
+    touch(&mut input, 2, TouchPhase::Moved, 600., 300.);
+    input.update(&mut camera, SETTLE);
+    assert!(camera.target.abs_diff_eq(Vec3::ZERO, 1e-5), "target at {}", camera.target);
+    assert!((camera.eye.length() - 1.).abs() < 1e-4, "eye at {}", camera.eye);
+
+    // Both move down together, the target follows the fingers
+    touch(&mut input, 1, TouchPhase::Moved, 200., 350.);
+    touch(&mut input, 2, TouchPhase::Moved, 600., 350.);
+    input.update(&mut camera, SETTLE);
+    assert!(camera.target.z > 0.01 && camera.target.truncate().length() < 1e-4, "target at {}", camera.target);
+    assert!((camera.eye.distance(camera.target) - 1.).abs() < 1e-4);
+
+    // Lifting them isn't a tap
+    touch(&mut input, 1, TouchPhase::Ended, 200., 350.);
+    touch(&mut input, 2, TouchPhase::Ended, 600., 350.);
+    assert_eq!(input.take_gesture(), None);
+}
+
+#[test]
+fn taps_and_double_taps() {
+    let mut input = InputServer::new();
+    touch(&mut input, 1, TouchPhase::Started, 100., 100.);
+    touch(&mut input, 1, TouchPhase::Moved, 103., 102.);
+    touch(&mut input, 1, TouchPhase::Ended, 103., 102.);
+    assert_eq!(input.take_gesture(), Some(Gesture::Tap(PhysicalPosition::new(100., 100.))));
+
+    touch(&mut input, 2, TouchPhase::Started, 104., 100.);
+    touch(&mut input, 2, TouchPhase::Ended, 104., 100.);
+    assert_eq!(input.take_gesture(), Some(Gesture::DoubleTap(PhysicalPosition::new(104., 100.))));
+
+    // A cancelled touch is no tap
+    touch(&mut input, 3, TouchPhase::Started, 300., 100.);
+    touch(&mut input, 3, TouchPhase::Cancelled, 300., 100.);
+    assert_eq!(input.take_gesture(), None);
+}